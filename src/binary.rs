@@ -0,0 +1,141 @@
+//! Helpers for the compact binary files, integers are written as LEB128 varints
+//! so that small numbers (indices, deltas) take a single byte
+
+use std::io::{self, Read, Write};
+
+pub fn write_u8<W: Write>(writer: &mut W, value: u8) -> io::Result<()> {
+    writer.write_all(&[value])
+}
+
+pub fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut buffer = [0; 1];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer[0])
+}
+
+pub fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            return write_u8(writer, byte);
+        }
+        write_u8(writer, byte | 0x80)?;
+    }
+}
+
+pub fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = read_u8(reader)?;
+        if shift > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "varint is too long",
+            ));
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+/// Maps signed integers to unsigned ones so that small negative numbers stay small
+pub fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+pub fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+pub fn write_signed<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+    write_varint(writer, zigzag(value))
+}
+
+pub fn read_signed<R: Read>(reader: &mut R) -> io::Result<i64> {
+    read_varint(reader).map(unzigzag)
+}
+
+pub fn write_usize<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    write_varint(writer, value as u64)
+}
+
+pub fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    read_varint(reader).map(|value| value as usize)
+}
+
+pub fn write_u32<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    let mut buffer = [0; 4];
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = (value >> (i * 8)) as u8;
+    }
+    writer.write_all(&buffer)
+}
+
+pub fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buffer = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    Ok((0..4).fold(0, |value, i| value | (buffer[i] as u32) << (i * 8)))
+}
+
+pub fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    let mut buffer = [0; 8];
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = (value >> (i * 8)) as u8;
+    }
+    writer.write_all(&buffer)
+}
+
+pub fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buffer = [0; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok((0..8).fold(0, |value, i| value | (buffer[i] as u64) << (i * 8)))
+}
+
+pub fn write_f32<W: Write>(writer: &mut W, value: f32) -> io::Result<()> {
+    write_u32(writer, value.to_bits())
+}
+
+pub fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
+
+pub fn write_f64<W: Write>(writer: &mut W, value: f64) -> io::Result<()> {
+    write_u64(writer, value.to_bits())
+}
+
+pub fn read_f64<R: Read>(reader: &mut R) -> io::Result<f64> {
+    read_u64(reader).map(f64::from_bits)
+}
+
+pub fn write_bool<W: Write>(writer: &mut W, value: bool) -> io::Result<()> {
+    write_u8(writer, value as u8)
+}
+
+pub fn read_bool<R: Read>(reader: &mut R) -> io::Result<bool> {
+    read_u8(reader).map(|value| value != 0)
+}
+
+pub fn write_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_usize(writer, value.len())?;
+    writer.write_all(value.as_bytes())
+}
+
+pub fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = read_varint(reader)?;
+    // Read through `take` so that a corrupted length doesn't allocate everything at once
+    let mut buffer = Vec::new();
+    if reader.take(len).read_to_end(&mut buffer)? as u64 != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "string is cut short"));
+    }
+    String::from_utf8(buffer).map_err(|_| invalid_data("string is not UTF-8"))
+}
+
+/// An error for files which don't follow the format
+pub fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
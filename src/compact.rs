@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use binary::*;
+use physics::contacts::ContactCache;
+use physics::determinism::Rng;
+use physics::fluid::Particle;
+use physics::material::Material;
+use physics::regions::{FluidRegion, RegionShape};
+use physics::simulation::{Vertex, World};
+use physics::snapshot::Snapshot;
+use physics::surface::Surface;
+use replay::{Parameter, WORLD_PARAMETERS};
+use Vector;
+
+const WORLD_MAGIC: &[u8; 4] = b"SPRW";
+const SNAPSHOT_MAGIC: &[u8; 4] = b"SPRS";
+const VERSION: u64 = 1;
+
+/// How the positions and velocities are written
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Precision {
+    /// As they are, the loaded world runs exactly like the saved one
+    Double,
+    /// Rounded to f32, half the size but the loaded world drifts from the saved one
+    Single,
+}
+
+impl Precision {
+    fn to_u8(self) -> u8 {
+        match self {
+            Precision::Double => 0,
+            Precision::Single => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> io::Result<Precision> {
+        match value {
+            0 => Ok(Precision::Double),
+            1 => Ok(Precision::Single),
+            _ => Err(invalid_data("unknown precision")),
+        }
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, magic: &[u8; 4], precision: Precision) -> io::Result<()> {
+    writer.write_all(magic)?;
+    write_varint(writer, VERSION)?;
+    write_u8(writer, precision.to_u8())
+}
+
+fn read_header<R: Read>(reader: &mut R, magic: &[u8; 4]) -> io::Result<Precision> {
+    let mut read = [0; 4];
+    reader.read_exact(&mut read)?;
+    if &read != magic {
+        return Err(invalid_data("not a compact world or snapshot"));
+    }
+    if read_varint(reader)? != VERSION {
+        return Err(invalid_data("unsupported compact version"));
+    }
+    Precision::from_u8(read_u8(reader)?)
+}
+
+fn write_vector<W: Write>(writer: &mut W, vector: &Vector, precision: Precision) -> io::Result<()> {
+    match precision {
+        Precision::Double => {
+            write_f64(writer, vector.x)?;
+            write_f64(writer, vector.y)
+        }
+        Precision::Single => {
+            write_f32(writer, vector.x as f32)?;
+            write_f32(writer, vector.y as f32)
+        }
+    }
+}
+
+fn read_vector<R: Read>(reader: &mut R, precision: Precision) -> io::Result<Vector> {
+    match precision {
+        Precision::Double => Ok(Vector::new(read_f64(reader)?, read_f64(reader)?)),
+        Precision::Single => Ok(Vector::new(
+            read_f32(reader)? as f64,
+            read_f32(reader)? as f64,
+        )),
+    }
+}
+
+fn write_material<W: Write>(writer: &mut W, material: &Option<String>) -> io::Result<()> {
+    write_bool(writer, material.is_some())?;
+    match *material {
+        Some(ref name) => write_string(writer, name),
+        None => Ok(()),
+    }
+}
+
+fn read_material<R: Read>(reader: &mut R) -> io::Result<Option<String>> {
+    if read_bool(reader)? {
+        Ok(Some(read_string(reader)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn write_vertex<W: Write>(writer: &mut W, vertex: &Vertex, precision: Precision) -> io::Result<()> {
+    write_vector(writer, &vertex.position, precision)?;
+    write_vector(writer, &vertex.velocity, precision)?;
+    write_f32(writer, vertex.mass)?;
+    write_bool(writer, vertex.is_static)?;
+    write_f32(writer, vertex.static_friction)?;
+    write_f32(writer, vertex.dynamic_friction)?;
+    write_f32(writer, vertex.restitution)?;
+    write_material(writer, &vertex.material)?;
+    write_f32(writer, vertex.charge)?;
+    write_f32(writer, vertex.cohesion)
+}
+
+pub fn read_vertex<R: Read>(reader: &mut R, precision: Precision) -> io::Result<Vertex> {
+    let mut vertex = Vertex::new(read_vector(reader, precision)?);
+    vertex.velocity = read_vector(reader, precision)?;
+    vertex.mass = read_f32(reader)?;
+    vertex.is_static = read_bool(reader)?;
+    vertex.static_friction = read_f32(reader)?;
+    vertex.dynamic_friction = read_f32(reader)?;
+    vertex.restitution = read_f32(reader)?;
+    vertex.material = read_material(reader)?;
+    vertex.charge = read_f32(reader)?;
+    vertex.cohesion = read_f32(reader)?;
+    Ok(vertex)
+}
+
+pub fn write_surface<W: Write>(writer: &mut W, surface: &Surface) -> io::Result<()> {
+    write_usize(writer, surface.index_a)?;
+    write_usize(writer, surface.index_b)?;
+    write_f32(writer, surface.damping_ratio)?;
+    write_f32(writer, surface.strength)?;
+    write_f64(writer, surface.target_distance)?;
+    write_f64(writer, surface.thickness)?;
+    write_f32(writer, surface.static_friction)?;
+    write_f32(writer, surface.dynamic_friction)?;
+    write_f32(writer, surface.restitution)?;
+    write_f32(writer, surface.drag_coefficient)?;
+    write_f32(writer, surface.lift_coefficient)?;
+    write_material(writer, &surface.material)
+}
+
+/// Reads a surface, checking that its vertices are among the first `vertex_count`
+pub fn read_surface<R: Read>(reader: &mut R, vertex_count: usize) -> io::Result<Surface> {
+    let index_a = read_usize(reader)?;
+    let index_b = read_usize(reader)?;
+    if index_a >= vertex_count || index_b >= vertex_count {
+        return Err(invalid_data("surface of an unknown vertex"));
+    }
+    Ok(Surface {
+        index_a,
+        index_b,
+        damping_ratio: read_f32(reader)?,
+        strength: read_f32(reader)?,
+        target_distance: read_f64(reader)?,
+        thickness: read_f64(reader)?,
+        static_friction: read_f32(reader)?,
+        dynamic_friction: read_f32(reader)?,
+        restitution: read_f32(reader)?,
+        drag_coefficient: read_f32(reader)?,
+        lift_coefficient: read_f32(reader)?,
+        material: read_material(reader)?,
+    })
+}
+
+pub fn write_library_material<W: Write>(writer: &mut W, material: &Material) -> io::Result<()> {
+    write_string(writer, &material.name)?;
+    write_f32(writer, material.damping_ratio)?;
+    write_f32(writer, material.strength)?;
+    write_f32(writer, material.mass)?;
+    write_f32(writer, material.static_friction)?;
+    write_f32(writer, material.dynamic_friction)?;
+    write_f32(writer, material.restitution)
+}
+
+pub fn read_library_material<R: Read>(reader: &mut R) -> io::Result<Material> {
+    let material = Material {
+        name: read_string(reader)?,
+        damping_ratio: read_f32(reader)?,
+        strength: read_f32(reader)?,
+        mass: read_f32(reader)?,
+        static_friction: read_f32(reader)?,
+        dynamic_friction: read_f32(reader)?,
+        restitution: read_f32(reader)?,
+    };
+    material.validate().map_err(|error| invalid_data(&error))?;
+    Ok(material)
+}
+
+pub fn write_region<W: Write>(writer: &mut W, region: &FluidRegion) -> io::Result<()> {
+    match region.shape {
+        RegionShape::Level(height) => {
+            write_u8(writer, 0)?;
+            write_f64(writer, height)?;
+        }
+        RegionShape::Polygon(ref points) => {
+            write_u8(writer, 1)?;
+            write_usize(writer, points.len())?;
+            for point in points {
+                write_vector(writer, point, Precision::Double)?;
+            }
+        }
+    }
+    write_f64(writer, region.density)?;
+    write_f64(writer, region.linear_drag)?;
+    write_f64(writer, region.quadratic_drag)
+}
+
+pub fn read_region<R: Read>(reader: &mut R) -> io::Result<FluidRegion> {
+    let mut region = match read_u8(reader)? {
+        0 => FluidRegion::level(read_f64(reader)?),
+        1 => {
+            let mut points = Vec::new();
+            for _ in 0..read_usize(reader)? {
+                points.push(read_vector(reader, Precision::Double)?);
+            }
+            FluidRegion::polygon(points)
+        }
+        _ => return Err(invalid_data("unknown region shape")),
+    };
+    region.density = read_f64(reader)?;
+    region.linear_drag = read_f64(reader)?;
+    region.quadratic_drag = read_f64(reader)?;
+    Ok(region)
+}
+
+fn write_contacts<W: Write>(writer: &mut W, contacts: &ContactCache) -> io::Result<()> {
+    write_usize(writer, contacts.len())?;
+    for (&(vertex, surface), &(normal, tangent, stuck)) in contacts {
+        write_usize(writer, vertex)?;
+        write_usize(writer, surface)?;
+        write_f64(writer, normal)?;
+        write_f64(writer, tangent)?;
+        write_bool(writer, stuck)?;
+    }
+    Ok(())
+}
+
+/// Reads the cached contacts, which must be between existing vertices and surfaces
+fn read_contacts<R: Read>(
+    reader: &mut R,
+    verts: usize,
+    surfaces: usize,
+) -> io::Result<ContactCache> {
+    let mut contacts = BTreeMap::new();
+    for _ in 0..read_usize(reader)? {
+        let key = (read_usize(reader)?, read_usize(reader)?);
+        if key.0 >= verts || key.1 >= surfaces {
+            return Err(invalid_data("contact with an unknown vertex or surface"));
+        }
+        contacts.insert(
+            key,
+            (read_f64(reader)?, read_f64(reader)?, read_bool(reader)?),
+        );
+    }
+    Ok(contacts)
+}
+
+/// Writes the state of a snapshot, without any header
+fn write_state<W: Write>(
+    writer: &mut W,
+    snapshot: &Snapshot,
+    precision: Precision,
+) -> io::Result<()> {
+    write_usize(writer, snapshot.verts.len())?;
+    for vertex in &snapshot.verts {
+        write_vertex(writer, vertex, precision)?;
+    }
+
+    write_usize(writer, snapshot.surfaces.len())?;
+    for surface in &snapshot.surfaces {
+        write_surface(writer, surface)?;
+    }
+
+    write_usize(writer, snapshot.particles.len())?;
+    for particle in &snapshot.particles {
+        write_vector(writer, &particle.position, precision)?;
+        write_vector(writer, &particle.velocity, precision)?;
+    }
+
+    write_usize(writer, snapshot.regions.len())?;
+    for region in &snapshot.regions {
+        write_region(writer, region)?;
+    }
+
+    write_contacts(writer, &snapshot.contacts)?;
+    write_u64(writer, snapshot.rng.state())?;
+    write_f64(writer, snapshot.time)?;
+    write_varint(writer, snapshot.step)?;
+    write_u64(writer, snapshot.last_hash)
+}
+
+fn read_state<R: Read>(reader: &mut R, precision: Precision) -> io::Result<Snapshot> {
+    let mut verts = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        verts.push(read_vertex(reader, precision)?);
+    }
+
+    let mut surfaces = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        surfaces.push(read_surface(reader, verts.len())?);
+    }
+
+    let mut particles = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        let mut particle = Particle::new(read_vector(reader, precision)?);
+        particle.velocity = read_vector(reader, precision)?;
+        particles.push(particle);
+    }
+
+    let mut regions = Vec::new();
+    for _ in 0..read_usize(reader)? {
+        regions.push(read_region(reader)?);
+    }
+
+    let contacts = read_contacts(reader, verts.len(), surfaces.len())?;
+    Ok(Snapshot {
+        verts,
+        surfaces,
+        particles,
+        regions,
+        contacts,
+        rng: Rng::new(read_u64(reader)?),
+        time: read_f64(reader)?,
+        step: read_varint(reader)?,
+        last_hash: read_u64(reader)?,
+    })
+}
+
+pub fn write_snapshot<W: Write>(
+    writer: &mut W,
+    snapshot: &Snapshot,
+    precision: Precision,
+) -> io::Result<()> {
+    write_header(writer, SNAPSHOT_MAGIC, precision)?;
+    write_state(writer, snapshot, precision)
+}
+
+pub fn read_snapshot<R: Read>(reader: &mut R) -> io::Result<Snapshot> {
+    let precision = read_header(reader, SNAPSHOT_MAGIC)?;
+    read_state(reader, precision)
+}
+
+/// Writes a world with its parameters and the materials added to the library,
+/// the built-in materials and the viewer settings are left out
+pub fn write_world<W: Write>(
+    writer: &mut W,
+    world: &World,
+    precision: Precision,
+) -> io::Result<()> {
+    write_header(writer, WORLD_MAGIC, precision)?;
+
+    let parameters: Vec<(&str, f64)> = WORLD_PARAMETERS
+        .iter()
+        .filter_map(|&name| Parameter::World(name).get(world).map(|value| (name, value)))
+        .collect();
+    write_usize(writer, parameters.len())?;
+    for &(name, value) in &parameters {
+        write_string(writer, name)?;
+        write_f64(writer, value)?;
+    }
+    write_bool(writer, world.deterministic)?;
+
+    let materials = world.materials.custom();
+    write_usize(writer, materials.len())?;
+    for material in &materials {
+        write_library_material(writer, material)?;
+    }
+
+    write_state(writer, &world.snapshot(), precision)
+}
+
+/// Reads a world, the parameters with unknown names are ignored
+/// The bodies are found again and the world must pass `World::validate`
+pub fn read_world<R: Read>(reader: &mut R) -> io::Result<World> {
+    let precision = read_header(reader, WORLD_MAGIC)?;
+
+    let mut world = World::new();
+    for _ in 0..read_usize(reader)? {
+        let name = read_string(reader)?;
+        let value = read_f64(reader)?;
+        if let Some(parameter) = Parameter::world(&name) {
+            parameter
+                .set(&mut world, value)
+                .map_err(|error| invalid_data(&error.to_string()))?;
+        }
+    }
+    world.deterministic = read_bool(reader)?;
+
+    for _ in 0..read_usize(reader)? {
+        world.materials.insert(read_library_material(reader)?);
+    }
+
+    let snapshot = read_state(reader, precision)?;
+    world.restore(&snapshot);
+
+    let report = world.validate();
+    if !report.is_valid() {
+        return Err(invalid_data(&report.to_string()));
+    }
+    Ok(world)
+}
+
+pub fn save(path: &Path, world: &World, precision: Precision) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_world(&mut writer, world, precision)?;
+    writer.flush()
+}
+
+pub fn load(path: &Path) -> io::Result<World> {
+    read_world(&mut BufReader::new(File::open(path)?))
+}
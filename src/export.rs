@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::{self, Map, Value};
+
+use physics::simulation::World;
+use Vector;
+
+/// A value which can be exported for each vertex, surface or body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantity {
+    Position,
+    Velocity,
+    /// Measured from the change of velocity since the previous step
+    Acceleration,
+    /// How much longer than its target distance each surface is
+    Extension,
+    /// The spring and damping force of each surface, positive when pulling
+    Force,
+    /// The center of mass of each body
+    BodyPosition,
+    /// The velocity of the center of mass of each body
+    BodyVelocity,
+}
+
+/// The kind of object a quantity is measured on
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Object {
+    Vertex,
+    Surface,
+    Body,
+}
+
+impl Object {
+    fn name(self) -> &'static str {
+        match self {
+            Object::Vertex => "vertex",
+            Object::Surface => "surface",
+            Object::Body => "body",
+        }
+    }
+
+    /// The key of the objects in the JSON lines
+    fn plural(self) -> &'static str {
+        match self {
+            Object::Vertex => "vertices",
+            Object::Surface => "surfaces",
+            Object::Body => "bodies",
+        }
+    }
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 7] = [
+        Quantity::Position,
+        Quantity::Velocity,
+        Quantity::Acceleration,
+        Quantity::Extension,
+        Quantity::Force,
+        Quantity::BodyPosition,
+        Quantity::BodyVelocity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::Position => "position",
+            Quantity::Velocity => "velocity",
+            Quantity::Acceleration => "acceleration",
+            Quantity::Extension => "extension",
+            Quantity::Force => "force",
+            Quantity::BodyPosition => "body_position",
+            Quantity::BodyVelocity => "body_velocity",
+        }
+    }
+
+    fn object(self) -> Object {
+        match self {
+            Quantity::Position | Quantity::Velocity | Quantity::Acceleration => Object::Vertex,
+            Quantity::Extension | Quantity::Force => Object::Surface,
+            Quantity::BodyPosition | Quantity::BodyVelocity => Object::Body,
+        }
+    }
+
+    /// The name of the value in the JSON lines, the object is already in the key of the list
+    fn field(self) -> &'static str {
+        match self {
+            Quantity::BodyPosition => "position",
+            Quantity::BodyVelocity => "velocity",
+            _ => self.name(),
+        }
+    }
+
+    fn is_vector(self) -> bool {
+        self.object() != Object::Surface
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Quantity, String> {
+        Quantity::ALL
+            .iter()
+            .find(|quantity| quantity.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown quantity {}", name))
+    }
+}
+
+/// Parses a list of quantities separated by commas, like `position,force`
+pub fn parse_quantities(list: &str) -> Result<Vec<Quantity>, String> {
+    list.split(',').map(|name| name.trim().parse()).collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A row for each value: step, time, object, index, quantity, value
+    Csv,
+    /// A JSON object for each exported step
+    JsonLines,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::Csv, Format::JsonLines];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+
+    /// JSON lines for the .jsonl and .json files, CSV for everything else
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") | Some("json") => Format::JsonLines,
+            _ => Format::Csv,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Format, String> {
+        Format::ALL
+            .iter()
+            .find(|format| format.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown format {}, use csv or jsonl", name))
+    }
+}
+
+/// The values of a quantity for each object, by index
+type Sample = Vec<(usize, Vec<f64>)>;
+
+/// Writes the chosen quantities to a file every `interval` steps
+pub struct Exporter {
+    pub format: Format,
+    pub quantities: Vec<Quantity>,
+    pub interval: u64,
+    /// The number of steps exported so far
+    pub samples: u64,
+
+    writer: BufWriter<File>,
+    /// The number of times `record` was called
+    steps: u64,
+    /// The velocities and time of the previous step, to measure the acceleration
+    last_velocities: Vec<Vector>,
+    last_time: f64,
+}
+
+impl Exporter {
+    pub fn create(
+        path: &Path,
+        format: Format,
+        quantities: Vec<Quantity>,
+        interval: u64,
+    ) -> io::Result<Exporter> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == Format::Csv {
+            writeln!(writer, "step,time,object,index,quantity,value")?;
+        }
+
+        Ok(Exporter {
+            format,
+            quantities,
+            interval: u64::max(interval, 1),
+            samples: 0,
+            writer,
+            steps: 0,
+            last_velocities: Vec::new(),
+            last_time: 0.0,
+        })
+    }
+
+    /// Call after every step, the first call and then every `interval` calls are exported
+    /// The acceleration is 0 when there is no previous step to compare with
+    pub fn record(&mut self, world: &World) -> io::Result<()> {
+        if self.steps % self.interval == 0 {
+            let samples: Vec<(Quantity, Sample)> = self
+                .quantities
+                .iter()
+                .map(|&quantity| (quantity, self.sample(world, quantity)))
+                .collect();
+            match self.format {
+                Format::Csv => self.write_csv(world, &samples)?,
+                Format::JsonLines => self.write_json(world, &samples)?,
+            }
+            self.samples += 1;
+        }
+
+        self.steps += 1;
+        self.last_velocities = world
+            .verts
+            .iter()
+            .map(|vertex| vertex.borrow().velocity)
+            .collect();
+        self.last_time = world.time;
+        Ok(())
+    }
+
+    /// Writes out everything still buffered
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn sample(&self, world: &World, quantity: Quantity) -> Sample {
+        let vector = |vector: Vector| vec![vector.x, vector.y];
+
+        match quantity {
+            Quantity::Position => world
+                .verts
+                .iter()
+                .enumerate()
+                .map(|(index, vertex)| (index, vector(vertex.borrow().position)))
+                .collect(),
+            Quantity::Velocity => world
+                .verts
+                .iter()
+                .enumerate()
+                .map(|(index, vertex)| (index, vector(vertex.borrow().velocity)))
+                .collect(),
+            Quantity::Acceleration => {
+                let dt = world.time - self.last_time;
+                let known = self.last_velocities.len() == world.verts.len() && dt > 0.0;
+                world
+                    .verts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, vertex)| {
+                        let acceleration = if known {
+                            (vertex.borrow().velocity - self.last_velocities[index]) / dt
+                        } else {
+                            Vector::new(0.0, 0.0)
+                        };
+                        (index, vector(acceleration))
+                    })
+                    .collect()
+            }
+            Quantity::Extension => world
+                .surfaces
+                .iter()
+                .enumerate()
+                .map(|(index, surface)| (index, vec![surface.extension(&world.verts)]))
+                .collect(),
+            Quantity::Force => world
+                .surfaces
+                .iter()
+                .enumerate()
+                .map(|(index, surface)| (index, vec![surface.tension(&world.verts)]))
+                .collect(),
+            Quantity::BodyPosition | Quantity::BodyVelocity => {
+                // The mass and the mass weighted position or velocity of each body
+                let mut bodies: BTreeMap<usize, (f64, Vector)> = BTreeMap::new();
+                for vertex in &world.verts {
+                    let vertex = vertex.borrow();
+                    let value = if quantity == Quantity::BodyPosition {
+                        vertex.position
+                    } else {
+                        vertex.velocity
+                    };
+                    let body = bodies
+                        .entry(vertex.body)
+                        .or_insert((0.0, Vector::new(0.0, 0.0)));
+                    body.0 += vertex.mass as f64;
+                    body.1 += value * vertex.mass as f64;
+                }
+                bodies
+                    .into_iter()
+                    .map(|(index, (mass, total))| (index, vector(total / mass)))
+                    .collect()
+            }
+        }
+    }
+
+    fn write_csv(&mut self, world: &World, samples: &[(Quantity, Sample)]) -> io::Result<()> {
+        for &(quantity, ref sample) in samples {
+            let object = quantity.object().name();
+            for &(index, ref values) in sample {
+                for (component, value) in values.iter().enumerate() {
+                    let suffix = match (quantity.is_vector(), component) {
+                        (false, _) => "",
+                        (true, 0) => "_x",
+                        (true, _) => "_y",
+                    };
+                    writeln!(
+                        self.writer,
+                        "{},{},{},{},{}{},{}",
+                        world.step,
+                        world.time,
+                        object,
+                        index,
+                        quantity.name(),
+                        suffix,
+                        value
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_json(&mut self, world: &World, samples: &[(Quantity, Sample)]) -> io::Result<()> {
+        // The values of each object, grouped by the kind of object
+        let mut objects: BTreeMap<&str, BTreeMap<usize, Map<String, Value>>> = BTreeMap::new();
+        for &(quantity, ref sample) in samples {
+            let list = objects
+                .entry(quantity.object().plural())
+                .or_insert_with(BTreeMap::new);
+            for &(index, ref values) in sample {
+                let value = if quantity.is_vector() {
+                    Value::from(values.clone())
+                } else {
+                    Value::from(values[0])
+                };
+                list.entry(index)
+                    .or_insert_with(|| {
+                        let mut object = Map::new();
+                        object.insert(String::from("index"), Value::from(index));
+                        object
+                    })
+                    .insert(quantity.field().to_string(), value);
+            }
+        }
+
+        let mut line = Map::new();
+        line.insert(String::from("step"), Value::from(world.step));
+        line.insert(String::from("time"), Value::from(world.time));
+        for (name, list) in objects {
+            let list = list
+                .into_iter()
+                .map(|(_, object)| Value::Object(object))
+                .collect();
+            line.insert(name.to_string(), Value::Array(list));
+        }
+
+        serde_json::to_writer(&mut self.writer, &Value::Object(line))
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error))?;
+        writeln!(self.writer)
+    }
+}
@@ -1,11 +1,26 @@
 #[macro_use]
 extern crate gfx;
+extern crate gif;
 extern crate imgui;
 extern crate nalgebra;
 extern crate piston_window;
+extern crate png;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
 type Vector = nalgebra::Vector2<f64>;
 
+pub mod binary;
+pub mod compact;
+pub mod export;
+pub mod migration;
 pub mod physics;
+pub mod raster;
+pub mod recording;
+pub mod replay;
+pub mod runner;
+pub mod scene;
 pub mod viewer;
 pub mod shapes;
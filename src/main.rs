@@ -1,12 +1,106 @@
 extern crate nalgebra;
 extern crate spring;
 
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process;
+
 use nalgebra::Vector2;
+use spring::replay::Replay;
+use spring::runner::{self, ReplayOptions, RunOptions};
+use spring::scene::Scene;
 use spring::{physics, shapes, viewer};
 
+/// Simulates a scene without a window, exits with 2 if the world becomes invalid or blows up
+fn run(args: &[String]) {
+    let options = RunOptions::parse(args).unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, runner::USAGE);
+        process::exit(1);
+    });
+    let (mut world, view, report) = runner::load_world(&options.scene).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", options.scene.display(), error);
+        process::exit(1);
+    });
+    if !report.is_empty() {
+        eprintln!("{}", report);
+    }
+
+    let view = view.unwrap_or_default();
+
+    let stdout = io::stdout();
+    let statistics =
+        runner::run(&mut world, &view, &options, &mut stdout.lock()).unwrap_or_else(|error| {
+            eprintln!("Failed to run {}: {}", options.scene.display(), error);
+            process::exit(1);
+        });
+    println!("{}", statistics);
+
+    if statistics.failed() {
+        process::exit(2);
+    }
+}
+
+/// Plays a replay without a window, printing the final step and state hash
+fn replay(args: &[String]) {
+    let options = ReplayOptions::parse(args).unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, runner::REPLAY_USAGE);
+        process::exit(1);
+    });
+    let replay = Replay::load(&options.replay).unwrap_or_else(|error| {
+        eprintln!("Failed to load {}: {}", options.replay.display(), error);
+        process::exit(1);
+    });
+
+    let stdout = io::stdout();
+    let world =
+        runner::replay(&replay, &options, &mut stdout.lock()).unwrap_or_else(|error| {
+            eprintln!("Failed to replay {}: {}", options.replay.display(), error);
+            process::exit(1);
+        });
+    println!("Replayed up to step {}, hash {:016x}", world.step, world.last_hash);
+}
+
 fn main() {
-    let mut world = physics::simulation::World::new();
-    shapes::make_polygon(&mut world, Vector2::new(0.0, 0.0), 5.0, 8);
-    let view = viewer::ViewState::new(world);
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("run") => return run(&args[1..]),
+        Some("replay") => return replay(&args[1..]),
+        _ => {}
+    }
+
+    // Open the scene given as argument, or start with an octagon
+    let view = match args.first().cloned() {
+        Some(path) => {
+            let (scene, report) = Scene::load(Path::new(&path)).unwrap_or_else(|error| {
+                eprintln!("Failed to load {}: {}", path, error);
+                process::exit(1);
+            });
+            if !report.is_empty() {
+                eprintln!("{}", report);
+            }
+            let world = scene.to_world().unwrap_or_else(|error| {
+                eprintln!("Failed to load {}: {}", path, error);
+                process::exit(1);
+            });
+            let validation = world.validate();
+            if !validation.is_valid() {
+                eprintln!("{}", validation);
+            }
+
+            let mut view = viewer::ViewState::new(world);
+            if let Some(ref settings) = scene.view {
+                view.apply_settings(settings);
+            }
+            view
+        }
+        None => {
+            let mut world = physics::simulation::World::new();
+            shapes::make_polygon(&mut world, Vector2::new(0.0, 0.0), 5.0, 8)
+                .expect("Failed to create the starting polygon");
+            viewer::ViewState::new(world)
+        }
+    };
+
     viewer::drawing::view_loop(view);
 }
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{self, Map, Value};
+
+use physics::fluid::Particle;
+use physics::health::HealthPolicy;
+use physics::material::{CombineRule, Material};
+use physics::regions::FluidRegion;
+use physics::simulation::{Vertex, World};
+use physics::surface::Surface;
+use replay::WORLD_PARAMETERS;
+use scene::{Scene, ViewSettings, NAMED_PARAMETERS, SCENE_VERSION};
+
+/// Upgrades a scene by one version, returning what it changed
+type Migration = fn(&mut Map<String, Value>) -> String;
+
+/// The migration at index `i` upgrades the scenes of version `i` to version `i + 1`,
+/// a new scene version doesn't build until its migration is added here
+const MIGRATIONS: [Migration; SCENE_VERSION as usize] = [unversioned_to_1, rules_by_name];
+
+/// The scenes written before the version was added are version 0,
+/// they have the same structure as version 1
+fn unversioned_to_1(_scene: &mut Map<String, Value>) -> String {
+    String::from("added the schema version")
+}
+
+/// Version 1 kept the combine rules and the health policy in the settings,
+/// as their position in `CombineRule::ALL` and `HealthPolicy::ALL`
+fn rules_by_name(scene: &mut Map<String, Value>) -> String {
+    let mut moved = Vec::new();
+    if let Some(settings) = scene.get_mut("settings").and_then(Value::as_object_mut) {
+        for &name in NAMED_PARAMETERS {
+            let position = match settings.remove(name).and_then(|value| value.as_f64()) {
+                Some(position) => position as usize,
+                None => continue,
+            };
+            let value = if name == "health_policy" {
+                HealthPolicy::ALL
+                    .get(position)
+                    .and_then(|policy| serde_json::to_value(policy).ok())
+            } else {
+                CombineRule::ALL
+                    .get(position)
+                    .and_then(|rule| serde_json::to_value(rule).ok())
+            };
+            if let Some(value) = value {
+                moved.push((name, value));
+            }
+        }
+    }
+
+    let names: Vec<&str> = moved.iter().map(|&(name, _)| name).collect();
+    let description = format!("wrote {:?} by name", names);
+    for (name, value) in moved {
+        scene.insert(name.to_string(), value);
+    }
+    description
+}
+
+/// What was changed to make an old scene readable
+#[derive(Clone, Debug, Default)]
+pub struct MigrationReport {
+    /// The version of the file before the migrations
+    pub from_version: u32,
+    /// What each migration did
+    pub migrations: Vec<String>,
+    /// The fields missing from the file, which got their default value, with how many times
+    pub defaulted: BTreeMap<String, usize>,
+    /// The fields this version doesn't know, which were ignored, with how many times
+    pub dropped: BTreeMap<String, usize>,
+}
+
+impl MigrationReport {
+    /// True if the scene was read exactly as it was written
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty() && self.defaulted.is_empty() && self.dropped.is_empty()
+    }
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "Scene read as written");
+        }
+
+        write!(f, "Scene version {}", self.from_version)?;
+        for migration in &self.migrations {
+            write!(f, "\nMigrated: {}", migration)?;
+        }
+        for (field, count) in &self.defaulted {
+            write!(f, "\nDefaulted: {} ({})", field, count)?;
+        }
+        for (field, count) in &self.dropped {
+            write!(f, "\nDropped: {} ({})", field, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names of the fields written for a value
+fn field_names<T: Serialize>(value: &T) -> Vec<String> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Compares the fields of an object with the known ones, counting the missing and unknown ones
+fn check_fields(
+    object: &Map<String, Value>,
+    known: &[String],
+    path: &str,
+    report: &mut MigrationReport,
+) {
+    for name in known {
+        if !object.contains_key(name) {
+            *report
+                .defaulted
+                .entry(format!("{}{}", path, name))
+                .or_insert(0) += 1;
+        }
+    }
+    for name in object.keys() {
+        if !known.contains(name) {
+            *report
+                .dropped
+                .entry(format!("{}{}", path, name))
+                .or_insert(0) += 1;
+        }
+    }
+}
+
+fn check_list(
+    scene: &Map<String, Value>,
+    list: &str,
+    known: &[String],
+    report: &mut MigrationReport,
+) {
+    if let Some(items) = scene.get(list).and_then(Value::as_array) {
+        for item in items {
+            if let Some(object) = item.as_object() {
+                check_fields(object, known, &format!("{}[].", list), report);
+            }
+        }
+    }
+}
+
+/// Upgrades a scene to the current version, reporting what was defaulted or dropped
+pub fn migrate(value: Value) -> Result<(Scene, MigrationReport), String> {
+    let mut scene = match value {
+        Value::Object(scene) => scene,
+        _ => return Err(String::from("a scene must be an object")),
+    };
+
+    let version = match scene.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| String::from("the version must be a positive integer"))?,
+    };
+    if version > SCENE_VERSION as u64 {
+        return Err(format!(
+            "the scene is version {}, newer than the supported version {}",
+            version, SCENE_VERSION
+        ));
+    }
+    let version = version as u32;
+
+    let mut report = MigrationReport {
+        from_version: version,
+        ..MigrationReport::default()
+    };
+    for from in version..SCENE_VERSION {
+        let description = MIGRATIONS[from as usize](&mut scene);
+        report
+            .migrations
+            .push(format!("{} to {}, {}", from, from + 1, description));
+        scene.insert(String::from("version"), Value::from(from + 1));
+    }
+
+    // Unlike the other fields, the vertices of a surface have no sensible default
+    if let Some(surfaces) = scene.get("surfaces").and_then(Value::as_array) {
+        for (index, surface) in surfaces.iter().enumerate() {
+            for &name in &["index_a", "index_b"] {
+                if surface.get(name).is_none() {
+                    return Err(format!("surface {} has no {}", index, name));
+                }
+            }
+        }
+    }
+
+    // The current structure, from what gets written for default values
+    let empty = Scene::new(&World::new(), Some(ViewSettings::default()));
+    check_fields(&scene, &field_names(&empty), "", &mut report);
+    check_list(&scene, "verts", &field_names(&Vertex::default()), &mut report);
+    check_list(&scene, "surfaces", &field_names(&Surface::default()), &mut report);
+    check_list(&scene, "particles", &field_names(&Particle::default()), &mut report);
+    check_list(&scene, "regions", &field_names(&FluidRegion::default()), &mut report);
+    check_list(&scene, "materials", &field_names(&Material::default()), &mut report);
+    if let Some(view) = scene.get("view").and_then(Value::as_object) {
+        check_fields(view, &field_names(&ViewSettings::default()), "view.", &mut report);
+    }
+    if let Some(settings) = scene.get("settings").and_then(Value::as_object) {
+        let known: Vec<String> = WORLD_PARAMETERS
+            .iter()
+            .filter(|name| !NAMED_PARAMETERS.contains(name))
+            .map(|name| name.to_string())
+            .collect();
+        check_fields(settings, &known, "settings.", &mut report);
+    }
+
+    let scene = serde_json::from_value(Value::Object(scene)).map_err(|error| error.to_string())?;
+    Ok((scene, report))
+}
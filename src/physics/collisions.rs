@@ -1,10 +1,9 @@
-use physics::simulation::{DebugView, Vertex};
+use physics::material::ContactMaterial;
+use physics::simulation::Vertex;
 use physics::surface::Surface;
 use nalgebra::Vector2;
 use Vector;
 
-const VERTEX_RADIUS: f64 = 0.005;
-
 #[inline]
 fn cross(a: Vector2<f64>, b: Vector2<f64>) -> f64 {
     a.x * b.y - a.y * b.x
@@ -21,146 +20,290 @@ fn get_crossing(a: &Vector, b: &Vector, c: &Vector, d: &Vector) -> bool {
         && distance_vector(c, a, b).signum() != distance_vector(d, a, b).signum()
 }
 
+/// Returns the smallest root of `a t^2 + b t + c = 0` between `min` and `max`
 #[inline]
-fn ray_intersect_seg(mut p: Vector, mut a: Vector, mut b: Vector) -> bool {
-    use std;
-    use std::f64;
+fn smallest_root(a: f64, b: f64, c: f64, min: f64, max: f64) -> Option<f64> {
+    const EPSILON: f64 = 1e-12;
+
+    let roots = if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return None;
+        }
+        [-c / b, -c / b]
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
 
-    const ESP: f64 = 0.00001;
+        // Avoid the cancellation of the textbook formula
+        let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+        if q == 0.0 {
+            [0.0, 0.0]
+        } else {
+            [q / a, c / q]
+        }
+    };
 
-    if a.y > b.y {
-        std::mem::swap(&mut a, &mut b);
+    let mut smallest = None;
+    for &root in &roots {
+        if root >= min && root <= max && smallest.map_or(true, |s| root < s) {
+            smallest = Some(root);
+        }
     }
+    smallest
+}
 
-    if p.y == a.y || p.y == b.y {
-        p.y += ESP;
+/// Impacts sooner than this are ignored, a vertex which was just resolved lies on the line
+/// of the segment up to rounding and would otherwise hit it again straight away
+const MIN_IMPACT_TIME: f64 = 1e-9;
+
+/// Returns the time after which `vertex` will hit the moving segment `ab`, if it does within `dt`
+/// Solves for when the vertex lies on the line through `ab`, (b - a) x (vertex - a) = 0,
+/// which in 2D is a quadratic in time
+pub fn time_of_impact(vertex: &Vertex, a: &Vertex, b: &Vertex, dt: f64) -> Option<f64> {
+    let segment = b.position - a.position;
+    let segment_velocity = b.velocity - a.velocity;
+    let offset = vertex.position - a.position;
+    let offset_velocity = vertex.velocity - a.velocity;
+
+    let c = cross(segment, offset);
+    // A vertex already on the line is handled by the capsule
+    if c == 0.0 {
+        return None;
     }
+    let b_coeff = cross(segment, offset_velocity) + cross(segment_velocity, offset);
+    let a_coeff = cross(segment_velocity, offset_velocity);
 
-    if (p.y > b.y || p.y < a.y) || p.x > a.x.max(b.x) {
-        false
-    } else if p.x < a.x.min(b.x) {
-        true
+    let t = smallest_root(a_coeff, b_coeff, c, MIN_IMPACT_TIME, dt)?;
+
+    // The vertex must hit the segment and not the rest of the line
+    let segment = segment + segment_velocity * t;
+    let offset = offset + offset_velocity * t;
+    let length = segment.norm_squared();
+    if length == 0.0 {
+        return None;
+    }
+
+    let s = offset.dot(&segment) / length;
+    if s < 0.0 || s > 1.0 {
+        return None;
+    }
+
+    // Only count the vertex moving into the segment from the side it started on, not grazing it
+    let normal = Vector::new(-segment.y, segment.x) * c.signum();
+    let relative_velocity = offset_velocity - segment_velocity * s;
+    if relative_velocity.dot(&normal) < 0.0 {
+        Some(t)
     } else {
-        let m_red = if (a.x - b.x).abs() > f64::MIN_POSITIVE {
-            (b.y - a.y) / (b.x - a.x)
-        } else {
-            f64::MAX
-        };
-        let m_blue = if (a.x - p.x).abs() > f64::MIN_POSITIVE {
-            (p.y - a.y) / (p.x - a.x)
-        } else {
-            f64::MAX
-        };
-        m_blue >= m_red
+        None
     }
 }
 
-#[inline]
-fn get_inside(quad: [&Vector; 4], p: &Vector) -> bool {
-    let p = p.clone();
+/// Checks if `vertex` is going to hit the capsule around the segment `ab`
+pub fn colliding(vertex: &Vertex, a: &Vertex, b: &Vertex, surface: &Surface, dt: f64) -> bool {
+    let ratio = segment_ratio(&vertex.position, &a.position, &b.position);
+    let gap = segment_distance(&vertex.position, &a.position, &b.position) - surface.thickness / 2.0;
 
-    let mut count = 0;
+    // A vertex resting on the capsule or reaching it within the step is kept as a contact,
+    // otherwise it would fall in and get pushed out again every other step
+    let relative_velocity = vertex.velocity - (a.velocity * (1.0 - ratio) + b.velocity * ratio);
+    let approach = -relative_velocity.dot(&normal(&vertex.position, &a.position, &b.position));
+    let colliding_capsule = gap <= f64::max(approach, 0.0) * dt;
 
-    for i in 0..quad.len() {
-        let ni = (i + 1) % quad.len();
-        let mut a = quad[i].clone();
-        let mut b = quad[ni].clone();
+    colliding_capsule || time_of_impact(vertex, a, b, dt).is_some()
+}
 
-        if ray_intersect_seg(p, a, b) {
-            count += 1;
-        }
+/// Returns when the moving segments `ab` and `cd` first cross within `dt`, 0 if they already do
+/// Two edges can only start crossing when an endpoint of one goes through the other,
+/// so this catches the edges which cross and uncross within the step
+pub fn edges_crossing_time(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex, dt: f64) -> Option<f64> {
+    if get_crossing(&a.position, &b.position, &c.position, &d.position) {
+        return Some(0.0);
     }
 
-    count % 2 == 1
+    let impacts = [
+        time_of_impact(a, c, d, dt),
+        time_of_impact(b, c, d, dt),
+        time_of_impact(c, a, b, dt),
+        time_of_impact(d, a, b, dt),
+    ];
+    impacts
+        .iter()
+        .filter_map(|&t| t)
+        .fold(None, |earliest: Option<f64>, t| {
+            Some(earliest.map_or(t, |earliest| earliest.min(t)))
+        })
 }
 
-#[inline]
-fn get_colliding_poly(quad: [&Vector; 4], segment: [&Vector; 2]) -> bool {
-    for i in 0..quad.len() {
-        let ni = (i + 1) % quad.len();
+/// Checks if the segments `ab` and `cd` are crossing now or will be at any time within `dt`
+pub fn edges_colliding(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex, dt: f64) -> bool {
+    edges_crossing_time(a, b, c, d, dt).is_some()
+}
 
-        if get_crossing(segment[0], segment[1], quad[i], quad[ni]) {
-            return true;
+/// Separates two edges `ab` and `cd`, crossing or about to cross,
+/// by moving them apart along the normal of the shallowest crossing
+pub fn resolve_crossing(
+    ab: [&mut Vertex; 2],
+    cd: [&mut Vertex; 2],
+    surface_ab: &Surface,
+    surface_cd: &Surface,
+    restitution: f64,
+) {
+    let [a, b] = ab;
+    let [c, d] = cd;
+
+    // The endpoint closest to the line of the other edge is the one that went through the least
+    let candidates = [
+        (a.position, c.position, d.position, b.position, 1.0),
+        (b.position, c.position, d.position, a.position, 1.0),
+        (c.position, a.position, b.position, d.position, -1.0),
+        (d.position, a.position, b.position, c.position, -1.0),
+    ];
+
+    let mut normal = Vector::new(0.0, 0.0);
+    let mut depth = f64::MAX;
+    for &(point, line_a, line_b, other, sign) in &candidates {
+        let line = line_b - line_a;
+        if line.norm() == 0.0 {
+            continue;
         }
-    }
 
-    for i in 0..2 {
-        if get_inside(quad, segment[i]) {
-            return true;
+        let line_normal = Vector::new(-line.y, line.x).normalize();
+        let distance = (point - line_a).dot(&line_normal);
+        if distance.abs() < depth {
+            depth = distance.abs();
+            // Point towards the side where the rest of the edge is
+            let side = if (other - line_a).dot(&line_normal) >= 0.0 {
+                line_normal
+            } else {
+                -line_normal
+            };
+            normal = side * sign;
         }
     }
 
-    false
-}
+    if depth == f64::MAX {
+        return;
+    }
 
-pub fn colliding(vertex: &Vertex, a: &Vertex, b: &Vertex, dt: f64) -> bool {
-    let quad = [
-        &a.position,
-        &b.position,
-        &b.next_position(dt),
-        &a.next_position(dt),
-    ];
+    // From here on the normal points from edge cd to edge ab
+    // Both edges are pushed from their middle point
+    let inverse_mass_ab = segment_inverse_mass(a, b, 0.5);
+    let inverse_mass_cd = segment_inverse_mass(c, d, 0.5);
+    let inverse_mass = inverse_mass_ab + inverse_mass_cd;
+    if inverse_mass == 0.0 {
+        return;
+    }
 
-    let segment = [&vertex.position, &vertex.next_position(dt)];
+    let velocity_ab = (a.velocity + b.velocity) / 2.0;
+    let velocity_cd = (c.velocity + d.velocity) / 2.0;
 
-    let colliding_poly = get_colliding_poly(quad, segment);
-    let colliding_segment = distance_vector(&vertex.position, &a.position, &b.position).abs()
-        < VERTEX_RADIUS
-        && inside_box(&vertex.position, &a.position, &b.position);
-    let colliding_vertex = (vertex.position - a.position).norm() < VERTEX_RADIUS
-        || (vertex.position - b.position).norm() < VERTEX_RADIUS;
+    let normal_velocity = (velocity_ab - velocity_cd).dot(&normal);
+    if normal_velocity < 0.0 {
+        let j = -(1.0 + restitution) * normal_velocity / inverse_mass;
+        let impulse = normal * j / 2.0;
 
-    colliding_poly || colliding_segment || colliding_vertex
+        a.velocity += impulse * a.inverse_mass();
+        b.velocity += impulse * b.inverse_mass();
+        c.velocity -= impulse * c.inverse_mass();
+        d.velocity -= impulse * d.inverse_mass();
+    }
+
+    // Move the edges apart, leaving them as far as their capsules
+    // Edges which only cross later in the step are already on the right side
+    let thickness = (surface_ab.thickness + surface_cd.thickness) / 2.0;
+    let separation = if get_crossing(&a.position, &b.position, &c.position, &d.position) {
+        depth + thickness
+    } else {
+        (thickness - depth).max(0.0)
+    };
+    let correction = normal * separation / inverse_mass / 2.0;
+    a.position += correction * a.inverse_mass();
+    b.position += correction * b.inverse_mass();
+    c.position -= correction * c.inverse_mass();
+    d.position -= correction * d.inverse_mass();
 }
 
 /// Resolves the impulses between a `vertex` and a segment `ab`
-pub fn resolve_impulses(vertex: &mut Vertex, a: &mut Vertex, b: &mut Vertex, surface: &Surface) {
-    let e = surface.restitution as f64;
-
-    let normal = normal(&vertex.position, &a.position, &b.position);
-
-    let segment_mass = a.mass + b.mass;
-    let segment_vel =
-        (a.velocity * a.mass as f64 + b.velocity * b.mass as f64) / segment_mass as f64;
+/// given the `normal` pointing from the segment to the vertex
+pub fn resolve_impact(
+    vertex: &mut Vertex,
+    a: &mut Vertex,
+    b: &mut Vertex,
+    surface: &Surface,
+    material: &ContactMaterial,
+    normal: Vector,
+) {
+    let e = material.restitution;
+
+    // Where the vertex hits the segment
+    let ratio = segment_ratio(&vertex.position, &a.position, &b.position);
+    let inverse_mass = vertex.inverse_mass() + segment_inverse_mass(a, b, ratio);
+    if inverse_mass == 0.0 {
+        return;
+    }
 
+    let segment_vel = a.velocity * (1.0 - ratio) + b.velocity * ratio;
     let normal_velocity = (vertex.velocity - segment_vel).dot(&normal);
 
     // Resolve the collision bounce
-    let j = -(1.0 + e) * normal_velocity / (1.0 / vertex.mass + 1.0 / segment_mass) as f64;
-    let impulse = j * normal;
-
-    let distance_ratio_ab =
-        (b.position - vertex.position).norm() / (a.position - b.position).norm();
-    let distance_ratio_ba = 1.0 - distance_ratio_ab;
+    let j = if normal_velocity < 0.0 {
+        -(1.0 + e) * normal_velocity / inverse_mass
+    } else {
+        0.0
+    };
 
     // Resolve the collision friction
-    let tangent = tangent(&vertex.velocity, &a.position, &b.position);
+    let tangent = tangent(&(vertex.velocity - segment_vel), &a.position, &b.position);
     let tangent_velocity = (vertex.velocity - segment_vel).dot(&tangent);
-    let coeff = surface.friction as f64;
-
-    // Make the friction only stop the body and not make it go backwards
-    let friction = {
-        tangent * if normal_velocity.abs() * coeff < tangent_velocity.abs() {
-            normal_velocity.abs() * coeff
-        } else {
-            tangent_velocity
-        }
+    // Stopping the sliding entirely sticks the vertex, otherwise it keeps sliding
+    let stopping = tangent_velocity / inverse_mass;
+    let friction = if stopping <= j * material.static_friction {
+        stopping
+    } else {
+        j * material.dynamic_friction
     };
 
     // Assign the new after-collision velocities
-    vertex.velocity += impulse / vertex.mass as f64 - friction;
-    a.velocity += -impulse * distance_ratio_ab / a.mass as f64 + friction;
-    b.velocity += -impulse * distance_ratio_ba / b.mass as f64 + friction;
-
-    if !vertex.is_static {
-        vertex.position += normal * VERTEX_RADIUS / 2.0;
+    let impulse = normal * j - tangent * friction;
+    apply_impulse(impulse, vertex, a, b, ratio);
+
+    // Push the vertex out of the surface capsule
+    let penetration =
+        surface.thickness / 2.0 - segment_distance(&vertex.position, &a.position, &b.position);
+    if penetration > 0.0 {
+        push_apart(normal * penetration, vertex, a, b, ratio);
     }
-    if !a.is_static {
-        a.position -= normal * VERTEX_RADIUS / 2.0;
-    }
-    if !b.is_static {
-        b.position -= normal * VERTEX_RADIUS / 2.0;
+}
+
+/// The inverse of the mass felt when pushing the segment `ab` at `ratio` along it
+#[inline]
+pub fn segment_inverse_mass(a: &Vertex, b: &Vertex, ratio: f64) -> f64 {
+    (1.0 - ratio).powi(2) * a.inverse_mass() + ratio.powi(2) * b.inverse_mass()
+}
+
+/// Applies `impulse` to `vertex` and the opposite to the segment `ab` at `ratio` along it
+#[inline]
+pub fn apply_impulse(impulse: Vector, vertex: &mut Vertex, a: &mut Vertex, b: &mut Vertex, ratio: f64) {
+    vertex.velocity += impulse * vertex.inverse_mass();
+    a.velocity -= impulse * (1.0 - ratio) * a.inverse_mass();
+    b.velocity -= impulse * ratio * b.inverse_mass();
+}
+
+/// Moves `vertex` and the segment `ab` apart by `separation`,
+/// each one as much as its inverse mass allows
+#[inline]
+pub fn push_apart(separation: Vector, vertex: &mut Vertex, a: &mut Vertex, b: &mut Vertex, ratio: f64) {
+    let inverse_mass = vertex.inverse_mass() + segment_inverse_mass(a, b, ratio);
+    if inverse_mass == 0.0 {
+        return;
     }
+
+    let correction = separation / inverse_mass;
+    vertex.position += correction * vertex.inverse_mass();
+    a.position -= correction * (1.0 - ratio) * a.inverse_mass();
+    b.position -= correction * ratio * b.inverse_mass();
 }
 
 #[inline]
@@ -178,6 +321,30 @@ pub fn normal(vertex: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> Vect
     }
 }
 
+/// Returns where the point of the segment `ab` closest to `vertex` is,
+/// 0.0 being `a` and 1.0 being `b`
+#[inline]
+pub fn segment_ratio(vertex: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> f64 {
+    let segment = b - a;
+    let length = segment.norm_squared();
+    if length == 0.0 {
+        return 0.0;
+    }
+
+    ((vertex - a).dot(&segment) / length).max(0.0).min(1.0)
+}
+
+/// Returns the point of the segment `ab` closest to `vertex`
+#[inline]
+pub fn closest_point(vertex: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> Vector2<f64> {
+    a + (b - a) * segment_ratio(vertex, a, b)
+}
+
+#[inline]
+pub fn segment_distance(vertex: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> f64 {
+    (vertex - closest_point(vertex, a, b)).norm()
+}
+
 #[inline]
 pub fn tangent(direction: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> Vector2<f64> {
     let tangent = (a - b).normalize();
@@ -189,19 +356,3 @@ pub fn tangent(direction: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) ->
         -tangent
     }
 }
-
-#[inline]
-pub fn inside_box(vertex: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>) -> bool {
-    use std::f64;
-
-    let max_x = f64::max(a.x, b.x);
-    let min_x = f64::min(a.x, b.x);
-    let max_y = f64::max(a.y, b.y);
-    let min_y = f64::min(a.y, b.y);
-
-    if vertex.x <= max_x && vertex.x >= min_x && vertex.y <= max_y && vertex.y >= min_y {
-        return true;
-    }
-
-    false
-}
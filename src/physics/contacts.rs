@@ -0,0 +1,277 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use physics::collisions;
+use physics::material::{CombineRule, ContactMaterial};
+use physics::simulation::Vertex;
+use physics::surface::Surface;
+use Vector;
+
+/// A contact between a vertex and a surface found at the start of a step
+pub struct Contact {
+    pub vertex: usize,
+    pub surface: usize,
+    /// Points from the surface to the vertex
+    pub normal: Vector,
+    pub tangent: Vector,
+    /// Where the vertex touches the surface, 0.0 at `index_a` and 1.0 at `index_b`
+    pub ratio: f64,
+    pub penetration: f64,
+    pub material: ContactMaterial,
+    /// The normal velocity the contact must reach, the separating velocity because of restitution,
+    /// or a negative one for a contact still apart, which can approach until it closes the gap
+    pub bounce: f64,
+
+    // The impulses accumulated over the iterations
+    pub normal_impulse: f64,
+    pub tangent_impulse: f64,
+    /// Whether the vertex is held still by static friction or sliding
+    pub sticking: bool,
+}
+
+impl Contact {
+    /// The velocity of the vertex relative to the point of the surface it touches
+    fn relative_velocity(&self, vertex: &Vertex, a: &Vertex, b: &Vertex) -> Vector {
+        vertex.velocity - (a.velocity * (1.0 - self.ratio) + b.velocity * self.ratio)
+    }
+
+    /// The inverse of the mass the contact impulses act against
+    fn inverse_mass(&self, vertex: &Vertex, a: &Vertex, b: &Vertex) -> f64 {
+        vertex.inverse_mass() + collisions::segment_inverse_mass(a, b, self.ratio)
+    }
+
+    fn apply_impulse(&self, impulse: Vector, vertex: &mut Vertex, a: &mut Vertex, b: &mut Vertex) {
+        collisions::apply_impulse(impulse, vertex, a, b, self.ratio);
+    }
+}
+
+/// The accumulated normal and tangent impulses and the stick state of each (vertex, surface) contact
+pub type ContactCache = BTreeMap<(usize, usize), (f64, f64, bool)>;
+
+/// A sequential impulse solver which resolves all the contacts of a step together
+pub struct ContactSolver {
+    /// Contacts approaching slower than this do not bounce, so they can come to rest
+    pub restitution_threshold: f64,
+    /// Start each step from the impulses found in the previous one
+    pub warm_starting: bool,
+
+    pub friction_rule: CombineRule,
+    pub restitution_rule: CombineRule,
+
+    /// The impulses and stick state of the last step for each (vertex, surface) couple
+    cache: ContactCache,
+}
+
+impl Default for ContactSolver {
+    fn default() -> ContactSolver {
+        ContactSolver::new()
+    }
+}
+
+impl ContactSolver {
+    pub fn new() -> ContactSolver {
+        ContactSolver {
+            restitution_threshold: 1.0,
+            warm_starting: true,
+            friction_rule: CombineRule::Average,
+            restitution_rule: CombineRule::Average,
+            cache: BTreeMap::new(),
+        }
+    }
+
+    /// Forgets the cached impulses, needed when the vertex or surface indices change
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn cache(&self) -> &ContactCache {
+        &self.cache
+    }
+
+    /// Replaces the cached impulses, like when going back to a snapshot
+    pub fn set_cache(&mut self, cache: ContactCache) {
+        self.cache = cache;
+    }
+
+    pub fn find_contacts(
+        &self,
+        verts: &[RefCell<Vertex>],
+        surfaces: &[Surface],
+        dt: f64,
+    ) -> Vec<Contact> {
+        let mut contacts = Vec::new();
+
+        for vertex_i in 0..verts.len() {
+            let vertex = verts[vertex_i].borrow();
+            for (surface_i, surface) in surfaces.iter().enumerate() {
+                if surface.index_a == vertex_i || surface.index_b == vertex_i {
+                    continue;
+                }
+
+                let a = verts[surface.index_a].borrow();
+                let b = verts[surface.index_b].borrow();
+
+                if !collisions::colliding(&vertex, &a, &b, surface, dt) {
+                    continue;
+                }
+
+                let ratio = collisions::segment_ratio(&vertex.position, &a.position, &b.position);
+                let distance = collisions::segment_distance(&vertex.position, &a.position, &b.position);
+                let normal = collisions::normal(&vertex.position, &a.position, &b.position);
+                let tangent = Vector::new(-normal.y, normal.x);
+                let material = ContactMaterial::new(
+                    &vertex,
+                    surface,
+                    self.friction_rule,
+                    self.restitution_rule,
+                );
+
+                let mut contact = Contact {
+                    vertex: vertex_i,
+                    surface: surface_i,
+                    normal,
+                    tangent,
+                    ratio,
+                    penetration: surface.thickness / 2.0 - distance,
+                    material,
+                    bounce: 0.0,
+                    normal_impulse: 0.0,
+                    tangent_impulse: 0.0,
+                    sticking: true,
+                };
+
+                let normal_velocity = contact.relative_velocity(&vertex, &a, &b).dot(&normal);
+                if contact.penetration < 0.0 {
+                    // Found ahead of time, fast vertices bounce at the impact instead
+                    if normal_velocity < -self.restitution_threshold {
+                        continue;
+                    }
+                    contact.bounce = contact.penetration / dt;
+                } else if normal_velocity < -self.restitution_threshold {
+                    contact.bounce = -contact.material.restitution * normal_velocity;
+                }
+
+                if let Some(&(normal_impulse, tangent_impulse, sticking)) =
+                    self.cache.get(&(vertex_i, surface_i))
+                {
+                    contact.sticking = sticking;
+                    if self.warm_starting {
+                        contact.normal_impulse = normal_impulse;
+                        contact.tangent_impulse = tangent_impulse;
+                    }
+                }
+
+                contacts.push(contact);
+            }
+        }
+
+        contacts
+    }
+
+    /// Iteratively finds the impulses which satisfy all the contacts at once
+    pub fn solve(
+        &mut self,
+        contacts: &mut [Contact],
+        verts: &[RefCell<Vertex>],
+        surfaces: &[Surface],
+        iterations: u32,
+    ) {
+        // Apply the impulses carried over from the last step
+        for contact in contacts.iter() {
+            let surface = &surfaces[contact.surface];
+            let mut vertex = verts[contact.vertex].borrow_mut();
+            let mut a = verts[surface.index_a].borrow_mut();
+            let mut b = verts[surface.index_b].borrow_mut();
+
+            let impulse =
+                contact.normal * contact.normal_impulse + contact.tangent * contact.tangent_impulse;
+            contact.apply_impulse(impulse, &mut vertex, &mut a, &mut b);
+        }
+
+        for _ in 0..iterations {
+            for contact in contacts.iter_mut() {
+                let surface = &surfaces[contact.surface];
+                let mut vertex = verts[contact.vertex].borrow_mut();
+                let mut a = verts[surface.index_a].borrow_mut();
+                let mut b = verts[surface.index_b].borrow_mut();
+
+                let inverse_mass = contact.inverse_mass(&vertex, &a, &b);
+                // Two anchored things can't push each other
+                if inverse_mass == 0.0 {
+                    continue;
+                }
+
+                // The contact can only push, so the total impulse is never negative
+                let normal_velocity = contact
+                    .relative_velocity(&vertex, &a, &b)
+                    .dot(&contact.normal);
+                let delta = (contact.bounce - normal_velocity) / inverse_mass;
+                let normal_impulse = f64::max(contact.normal_impulse + delta, 0.0);
+                let delta = normal_impulse - contact.normal_impulse;
+                contact.normal_impulse = normal_impulse;
+                contact.apply_impulse(contact.normal * delta, &mut vertex, &mut a, &mut b);
+
+                // The friction is bound by the normal impulse,
+                // static friction holds the vertex until it is overcome, then it slides
+                let tangent_velocity = contact
+                    .relative_velocity(&vertex, &a, &b)
+                    .dot(&contact.tangent);
+                let delta = -tangent_velocity / inverse_mass;
+                let tangent_impulse = contact.tangent_impulse + delta;
+
+                let coefficient = if contact.sticking {
+                    contact.material.static_friction
+                } else {
+                    contact.material.dynamic_friction
+                };
+                let max_friction = coefficient * contact.normal_impulse;
+                contact.sticking = tangent_impulse.abs() <= max_friction;
+
+                let max_friction = if contact.sticking {
+                    max_friction
+                } else {
+                    contact.material.dynamic_friction * contact.normal_impulse
+                };
+                let tangent_impulse =
+                    f64::max(-max_friction, f64::min(tangent_impulse, max_friction));
+                let delta = tangent_impulse - contact.tangent_impulse;
+                contact.tangent_impulse = tangent_impulse;
+                contact.apply_impulse(contact.tangent * delta, &mut vertex, &mut a, &mut b);
+            }
+        }
+
+        // Push the vertices out of the surfaces capsules
+        for contact in contacts.iter() {
+            if contact.penetration <= 0.0 {
+                continue;
+            }
+
+            let surface = &surfaces[contact.surface];
+            let mut vertex = verts[contact.vertex].borrow_mut();
+            let mut a = verts[surface.index_a].borrow_mut();
+            let mut b = verts[surface.index_b].borrow_mut();
+
+            collisions::push_apart(
+                contact.normal * contact.penetration,
+                &mut vertex,
+                &mut a,
+                &mut b,
+                contact.ratio,
+            );
+        }
+
+        self.cache = contacts
+            .iter()
+            .map(|contact| {
+                (
+                    (contact.vertex, contact.surface),
+                    (
+                        contact.normal_impulse,
+                        contact.tangent_impulse,
+                        contact.sticking,
+                    ),
+                )
+            })
+            .collect();
+    }
+}
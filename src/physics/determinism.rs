@@ -0,0 +1,203 @@
+use physics::regions::RegionShape;
+use physics::simulation::World;
+use Vector;
+
+/// A small xorshift random number generator, so that runs with the same seed
+/// produce the same numbers on every machine
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // Xorshift gets stuck on a zero state
+        Rng {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    /// The current state, `Rng::new(state)` continues with the same numbers
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number between 0 (included) and 1 (excluded)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number between min (included) and max (excluded)
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+/// The 64 bit FNV-1a hash
+pub struct StateHasher {
+    hash: u64,
+}
+
+impl Default for StateHasher {
+    fn default() -> StateHasher {
+        StateHasher::new()
+    }
+}
+
+impl StateHasher {
+    pub fn new() -> StateHasher {
+        StateHasher {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        for i in 0..8 {
+            self.hash ^= (value >> (i * 8)) & 0xff;
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u64(value.to_bits() as u64);
+    }
+
+    /// Hashes the exact bit pattern, so that even the smallest difference shows up
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    pub fn write_vector(&mut self, vector: &Vector) {
+        self.write_f64(vector.x);
+        self.write_f64(vector.y);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Hashes everything a step depends on: what moves, what the next steps start from
+/// (the warm started contacts and the random numbers) and every parameter of the world and its parts
+pub fn hash_state(world: &World) -> u64 {
+    let mut hasher = StateHasher::new();
+    hasher.write_u64(world.step);
+    hasher.write_f64(world.time);
+    hasher.write_u64(world.deterministic as u64);
+    hasher.write_f64(world.fixed_dt);
+
+    hasher.write_u64(world.verts.len() as u64);
+    for vertex in &world.verts {
+        let vertex = vertex.borrow();
+        hasher.write_vector(&vertex.position);
+        hasher.write_vector(&vertex.velocity);
+        hasher.write_f32(vertex.mass);
+        hasher.write_u64(vertex.is_static as u64);
+        hasher.write_f32(vertex.static_friction);
+        hasher.write_f32(vertex.dynamic_friction);
+        hasher.write_f32(vertex.restitution);
+        hasher.write_f32(vertex.charge);
+        hasher.write_f32(vertex.cohesion);
+    }
+
+    hasher.write_u64(world.surfaces.len() as u64);
+    for surface in &world.surfaces {
+        hasher.write_u64(surface.index_a as u64);
+        hasher.write_u64(surface.index_b as u64);
+        hasher.write_f32(surface.damping_ratio);
+        hasher.write_f32(surface.strength);
+        hasher.write_f64(surface.target_distance);
+        hasher.write_f64(surface.thickness);
+        hasher.write_f32(surface.static_friction);
+        hasher.write_f32(surface.dynamic_friction);
+        hasher.write_f32(surface.restitution);
+        hasher.write_f32(surface.drag_coefficient);
+        hasher.write_f32(surface.lift_coefficient);
+    }
+
+    let fluid = &world.fluid;
+    hasher.write_f64(fluid.smoothing_radius);
+    hasher.write_f64(fluid.particle_mass);
+    hasher.write_f64(fluid.rest_density);
+    hasher.write_f64(fluid.stiffness);
+    hasher.write_f64(fluid.viscosity);
+    hasher.write_f32(fluid.friction);
+    hasher.write_f32(fluid.restitution);
+    hasher.write_u64(fluid.particles.len() as u64);
+    for particle in &fluid.particles {
+        hasher.write_vector(&particle.position);
+        hasher.write_vector(&particle.velocity);
+    }
+
+    hasher.write_u64(world.regions.len() as u64);
+    for region in &world.regions {
+        match region.shape {
+            RegionShape::Level(height) => {
+                hasher.write_u64(0);
+                hasher.write_f64(height);
+            }
+            RegionShape::Polygon(ref points) => {
+                hasher.write_u64(1);
+                hasher.write_u64(points.len() as u64);
+                for point in points {
+                    hasher.write_vector(point);
+                }
+            }
+        }
+        hasher.write_f64(region.density);
+        hasher.write_f64(region.linear_drag);
+        hasher.write_f64(region.quadratic_drag);
+    }
+
+    hasher.write_f64(world.air_density);
+    hasher.write_vector(&world.wind);
+
+    let interactions = &world.interactions;
+    hasher.write_u64(interactions.coulomb as u64);
+    hasher.write_f64(interactions.coulomb_constant);
+    hasher.write_u64(interactions.gravity as u64);
+    hasher.write_f64(interactions.gravitational_constant);
+    hasher.write_u64(interactions.lennard_jones as u64);
+    hasher.write_f64(interactions.cohesion_distance);
+    hasher.write_f64(interactions.theta);
+    hasher.write_f64(interactions.softening);
+
+    let adaptive_steps = &world.adaptive_steps;
+    hasher.write_u64(adaptive_steps.enabled as u64);
+    hasher.write_u64(adaptive_steps.min as u64);
+    hasher.write_u64(adaptive_steps.max as u64);
+    hasher.write_f64(adaptive_steps.courant);
+    hasher.write_f64(adaptive_steps.oscillation);
+
+    let health = &world.health;
+    hasher.write_u64(health.enabled as u64);
+    hasher.write_u64(health.policy as u64);
+    hasher.write_f64(health.max_speed);
+    hasher.write_u64(health.max_rollbacks as u64);
+    hasher.write_u64(health.snapshot_interval);
+
+    let solver = &world.solver;
+    hasher.write_f64(solver.restitution_threshold);
+    hasher.write_u64(solver.warm_starting as u64);
+    hasher.write_u64(solver.friction_rule as u64);
+    hasher.write_u64(solver.restitution_rule as u64);
+    hasher.write_u64(solver.cache().len() as u64);
+    for (&(vertex, surface), &(normal_impulse, tangent_impulse, sticking)) in solver.cache() {
+        hasher.write_u64(vertex as u64);
+        hasher.write_u64(surface as u64);
+        hasher.write_f64(normal_impulse);
+        hasher.write_f64(tangent_impulse);
+        hasher.write_u64(sticking as u64);
+    }
+
+    hasher.write_u64(world.rng.state());
+
+    hasher.finish()
+}
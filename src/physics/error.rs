@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt;
+
+/// The ways editing a world can go wrong
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorldError {
+    /// There is no vertex with this index
+    UnknownVertex(usize),
+    /// There is no surface with this index
+    UnknownSurface(usize),
+    /// A surface between these two vertices already exists
+    DuplicateSurface(usize, usize),
+    /// The two vertices of the surface are in the same spot
+    DegenerateSurface(usize, usize),
+    /// A surface from a vertex to itself
+    SelfLoop(usize),
+    /// There is no material with this name in the library
+    UnknownMaterial(String),
+    /// A material has properties it can't take, with what is wrong
+    InvalidMaterial(String),
+}
+
+impl fmt::Display for WorldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WorldError::UnknownVertex(index) => write!(f, "there is no vertex {}", index),
+            WorldError::UnknownSurface(index) => write!(f, "there is no surface {}", index),
+            WorldError::DuplicateSurface(a, b) => {
+                write!(f, "a surface between {} and {} already exists", a, b)
+            }
+            WorldError::DegenerateSurface(a, b) => {
+                write!(f, "vertices {} and {} are in the same spot", a, b)
+            }
+            WorldError::SelfLoop(index) => {
+                write!(f, "vertex {} can't be connected to itself", index)
+            }
+            WorldError::UnknownMaterial(ref name) => write!(f, "there is no material '{}'", name),
+            WorldError::InvalidMaterial(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for WorldError {
+    fn description(&self) -> &str {
+        match *self {
+            WorldError::UnknownVertex(_) => "unknown vertex",
+            WorldError::UnknownSurface(_) => "unknown surface",
+            WorldError::DuplicateSurface(_, _) => "duplicate surface",
+            WorldError::DegenerateSurface(_, _) => "degenerate surface",
+            WorldError::SelfLoop(_) => "self loop",
+            WorldError::UnknownMaterial(_) => "unknown material",
+            WorldError::InvalidMaterial(_) => "invalid material",
+        }
+    }
+}
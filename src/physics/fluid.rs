@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use physics::collisions;
+use physics::determinism::Rng;
+use physics::material::{CombineRule, ContactMaterial};
+use physics::simulation::Vertex;
+use physics::surface::Surface;
+use Vector;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Particle {
+    pub position: Vector,
+    pub velocity: Vector,
+    #[serde(skip)]
+    pub acceleration: Vector,
+    #[serde(skip)]
+    pub density: f64,
+    #[serde(skip)]
+    pub pressure: f64,
+}
+
+impl Default for Particle {
+    fn default() -> Particle {
+        Particle::new(Vector::new(0.0, 0.0))
+    }
+}
+
+impl Particle {
+    pub fn new(position: Vector) -> Particle {
+        Particle {
+            position,
+            velocity: Vector::new(0.0, 0.0),
+            acceleration: Vector::new(0.0, 0.0),
+            density: 0.0,
+            pressure: 0.0,
+        }
+    }
+}
+
+/// Buckets the particles in square cells as big as the smoothing radius,
+/// so that the neighbours of a particle are all in the 9 cells around it
+struct SpatialHash {
+    cell_size: f64,
+    cells: BTreeMap<(i64, i64), Vec<usize>>,
+}
+
+impl SpatialHash {
+    fn new(cell_size: f64) -> SpatialHash {
+        SpatialHash {
+            cell_size,
+            cells: BTreeMap::new(),
+        }
+    }
+
+    fn cell(&self, position: &Vector) -> (i64, i64) {
+        (
+            (position.x / self.cell_size).floor() as i64,
+            (position.y / self.cell_size).floor() as i64,
+        )
+    }
+
+    fn build(&mut self, particles: &[Particle], cell_size: f64) {
+        self.cell_size = cell_size;
+        self.cells.clear();
+        for (index, particle) in particles.iter().enumerate() {
+            let cell = self.cell(&particle.position);
+            self.cells.entry(cell).or_insert_with(Vec::new).push(index);
+        }
+    }
+
+    /// Returns the indices of the particles which could be closer than a cell to `position`
+    fn neighbours(&self, position: &Vector) -> Vec<usize> {
+        let (x, y) = self.cell(position);
+        let mut neighbours = Vec::new();
+        for dx in -1..2 {
+            for dy in -1..2 {
+                if let Some(cell) = self.cells.get(&(x + dx, y + dy)) {
+                    neighbours.extend(cell);
+                }
+            }
+        }
+        neighbours
+    }
+}
+
+/// A smoothed particle hydrodynamics fluid
+pub struct Fluid {
+    pub particles: Vec<Particle>,
+
+    /// The distance within which the particles affect each other
+    pub smoothing_radius: f64,
+    pub particle_mass: f64,
+    pub rest_density: f64,
+    /// How strongly the fluid resists being compressed
+    pub stiffness: f64,
+    pub viscosity: f64,
+
+    // The properties used when the particles hit the surfaces
+    pub friction: f32,
+    pub restitution: f32,
+
+    grid: SpatialHash,
+}
+
+impl Default for Fluid {
+    fn default() -> Fluid {
+        Fluid::new()
+    }
+}
+
+impl Fluid {
+    pub fn new() -> Fluid {
+        Fluid {
+            particles: Vec::new(),
+            smoothing_radius: 0.4,
+            particle_mass: 0.04,
+            rest_density: 1.0,
+            stiffness: 200.0,
+            viscosity: 0.5,
+            friction: 0.1,
+            restitution: 0.0,
+            grid: SpatialHash::new(0.4),
+        }
+    }
+
+    pub fn add_particle(&mut self, position: Vector) {
+        self.particles.push(Particle::new(position));
+    }
+
+    /// Fills a disk with particles, leaving out the spots already taken by other particles
+    /// The particles are slightly jittered so they don't stack in perfect columns
+    pub fn emit(&mut self, center: Vector, radius: f64, rng: &mut Rng) {
+        let spacing = self.spacing();
+        let steps = (radius / spacing).ceil() as i64;
+
+        for x in -steps..steps + 1 {
+            for y in -steps..steps + 1 {
+                let offset = Vector::new(x as f64, y as f64) * spacing;
+                if offset.norm() > radius {
+                    continue;
+                }
+
+                let jitter = Vector::new(rng.range(-0.05, 0.05), rng.range(-0.05, 0.05));
+                let position = center + offset + jitter * spacing;
+                let taken = self.particles
+                    .iter()
+                    .any(|particle| (particle.position - position).norm() < spacing * 0.9);
+                if !taken {
+                    self.add_particle(position);
+                }
+            }
+        }
+    }
+
+    /// The distance between the particles of a fluid at rest
+    pub fn spacing(&self) -> f64 {
+        (self.particle_mass / self.rest_density).sqrt()
+    }
+
+    // The 2D smoothing kernels from Müller et al. 2003
+    fn poly6(&self, distance_squared: f64) -> f64 {
+        let h2 = self.smoothing_radius.powi(2);
+        if distance_squared >= h2 {
+            return 0.0;
+        }
+        4.0 / (PI * self.smoothing_radius.powi(8)) * (h2 - distance_squared).powi(3)
+    }
+
+    fn spiky_gradient(&self, delta: &Vector, distance: f64) -> Vector {
+        let h = self.smoothing_radius;
+        if distance >= h || distance == 0.0 {
+            return Vector::new(0.0, 0.0);
+        }
+        delta / distance * (-30.0 / (PI * h.powi(5)) * (h - distance).powi(2))
+    }
+
+    fn viscosity_laplacian(&self, distance: f64) -> f64 {
+        let h = self.smoothing_radius;
+        if distance >= h {
+            return 0.0;
+        }
+        40.0 / (PI * h.powi(5)) * (h - distance)
+    }
+
+    /// Computes the density, pressure and the acceleration of every particle
+    pub fn apply_forces(&mut self, gravity: Vector) {
+        self.grid.build(&self.particles, self.smoothing_radius);
+        let neighbours: Vec<Vec<usize>> = self.particles
+            .iter()
+            .map(|particle| self.grid.neighbours(&particle.position))
+            .collect();
+
+        for (i, near) in neighbours.iter().enumerate() {
+            let mut density = 0.0;
+            for &j in near {
+                let delta = self.particles[i].position - self.particles[j].position;
+                density += self.particle_mass * self.poly6(delta.norm_squared());
+            }
+
+            let particle = &mut self.particles[i];
+            particle.density = density;
+            // Only push, pulling makes the particles clump together
+            particle.pressure = f64::max(self.stiffness * (density - self.rest_density), 0.0);
+        }
+
+        for (i, near) in neighbours.iter().enumerate() {
+            let mut force = Vector::new(0.0, 0.0);
+            {
+                let particle = &self.particles[i];
+                for &j in near {
+                    if i == j {
+                        continue;
+                    }
+
+                    let other = &self.particles[j];
+                    let delta = particle.position - other.position;
+                    let distance = delta.norm();
+
+                    // The pressure pushes away from the denser particles
+                    force -= self.spiky_gradient(&delta, distance) * self.particle_mass
+                        * (particle.pressure + other.pressure)
+                        / (2.0 * other.density);
+
+                    // The viscosity evens out the velocities
+                    force += (other.velocity - particle.velocity) * self.viscosity
+                        * self.particle_mass
+                        * self.viscosity_laplacian(distance) / other.density;
+                }
+            }
+
+            let particle = &mut self.particles[i];
+            particle.acceleration = force / particle.density + gravity;
+        }
+    }
+
+    pub fn force_to_velocity(&mut self, dt: f64) {
+        for particle in &mut self.particles {
+            particle.velocity += particle.acceleration * dt;
+        }
+    }
+
+    /// Bounces the particles off the surfaces, pushing the surfaces back,
+    /// the properties of the contacts are combined with the rules of the contact solver
+    pub fn resolve_collisions(
+        &mut self,
+        verts: &[RefCell<Vertex>],
+        surfaces: &Vec<Surface>,
+        friction_rule: CombineRule,
+        restitution_rule: CombineRule,
+        dt: f64,
+    ) {
+        for particle in &mut self.particles {
+            // The particle goes through the same collision path as vertices
+            let mut vertex = Vertex::new(particle.position);
+            vertex.velocity = particle.velocity;
+            vertex.mass = self.particle_mass as f32;
+            vertex.static_friction = self.friction;
+            vertex.dynamic_friction = self.friction;
+            vertex.restitution = self.restitution;
+
+            for surface in surfaces {
+                let mut a = verts[surface.index_a].borrow_mut();
+                let mut b = verts[surface.index_b].borrow_mut();
+
+                if collisions::colliding(&vertex, &a, &b, surface, dt) {
+                    let normal = collisions::normal(&vertex.position, &a.position, &b.position);
+                    let material =
+                        ContactMaterial::new(&vertex, surface, friction_rule, restitution_rule);
+                    collisions::resolve_impact(&mut vertex, &mut a, &mut b, surface, &material, normal);
+                }
+            }
+
+            particle.position = vertex.position;
+            particle.velocity = vertex.velocity;
+        }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        for particle in &mut self.particles {
+            particle.position += particle.velocity * dt;
+        }
+    }
+}
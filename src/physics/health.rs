@@ -0,0 +1,259 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use physics::fluid::Particle;
+use physics::simulation::Vertex;
+use physics::snapshot::Snapshot;
+use physics::surface::Surface;
+use Vector;
+
+/// What to do when the simulation blows up
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HealthPolicy {
+    /// Go back to the last healthy snapshot, at most `snapshot_interval` steps ago
+    Rollback,
+    /// Slow down the offending vertices, putting back the broken ones
+    ClampVelocities,
+    /// Make the offending vertices static where they last were healthy
+    FreezeOffenders,
+}
+
+impl HealthPolicy {
+    pub const ALL: [HealthPolicy; 3] = [
+        HealthPolicy::Rollback,
+        HealthPolicy::ClampVelocities,
+        HealthPolicy::FreezeOffenders,
+    ];
+}
+
+/// What went wrong in a step
+#[derive(Clone, Debug)]
+pub struct HealthReport {
+    /// The vertices with a NaN or infinite position or velocity
+    pub broken_vertices: Vec<usize>,
+    /// The vertices faster than the maximum speed
+    pub fast_vertices: Vec<usize>,
+    /// The surfaces connected to the offending vertices
+    pub surfaces: Vec<usize>,
+    /// The fluid particles with a NaN or infinite value or faster than the maximum speed
+    pub particles: Vec<usize>,
+    pub action: HealthPolicy,
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} broken and {} exploding vertices {:?}, on surfaces {:?}, {} broken particles, \
+             applied {:?}",
+            self.broken_vertices.len(),
+            self.fast_vertices.len(),
+            self.broken_vertices
+                .iter()
+                .chain(self.fast_vertices.iter())
+                .collect::<Vec<_>>(),
+            self.surfaces,
+            self.particles.len(),
+            self.action
+        )
+    }
+}
+
+/// Watches the world after every step for values which blew up
+pub struct HealthMonitor {
+    /// Off by default, the policies change the world behind the user's back
+    pub enabled: bool,
+    pub policy: HealthPolicy,
+    /// Vertices going faster than this are considered exploding
+    pub max_speed: f64,
+    /// How many times in a row the same healthy step is gone back to
+    /// before freezing the offenders instead, a deterministic world would blow up the same way
+    pub max_rollbacks: u32,
+    /// How many healthy steps apart the snapshots to go back to are taken,
+    /// a snapshot copies the whole world
+    pub snapshot_interval: u64,
+    /// The last problem found
+    pub report: Option<HealthReport>,
+
+    /// The state of a recent healthy step
+    last_good: Option<Snapshot>,
+    /// The healthy steps since `last_good`
+    healthy_steps: u64,
+    /// How many times `last_good` was gone back to
+    rollbacks: u32,
+    /// The last finite position of each vertex, where the broken ones are put back
+    last_positions: Vec<Vector>,
+}
+
+fn finite(vector: &Vector) -> bool {
+    vector.x.is_finite() && vector.y.is_finite()
+}
+
+impl Default for HealthMonitor {
+    fn default() -> HealthMonitor {
+        HealthMonitor::new()
+    }
+}
+
+impl HealthMonitor {
+    pub fn new() -> HealthMonitor {
+        HealthMonitor {
+            enabled: false,
+            policy: HealthPolicy::Rollback,
+            max_speed: 1000.0,
+            max_rollbacks: 3,
+            snapshot_interval: 30,
+            report: None,
+            last_good: None,
+            healthy_steps: 0,
+            rollbacks: 0,
+            last_positions: Vec::new(),
+        }
+    }
+
+    /// Remembers the finite positions of the vertices, call before every step
+    pub fn track(&mut self, verts: &[RefCell<Vertex>]) {
+        self.last_positions.truncate(verts.len());
+        for (index, vertex) in verts.iter().enumerate() {
+            let position = vertex.borrow().position;
+            if index >= self.last_positions.len() {
+                self.last_positions.push(position);
+            } else if finite(&position) {
+                self.last_positions[index] = position;
+            }
+        }
+    }
+
+    /// Looks for broken or exploding vertices and particles,
+    /// returning what's wrong and what to do about it
+    pub fn inspect(
+        &self,
+        verts: &[RefCell<Vertex>],
+        surfaces: &[Surface],
+        particles: &[Particle],
+    ) -> Option<HealthReport> {
+        let mut broken_vertices = Vec::new();
+        let mut fast_vertices = Vec::new();
+        for (index, vertex) in verts.iter().enumerate() {
+            let vertex = vertex.borrow();
+            if !finite(&vertex.position) || !finite(&vertex.velocity) {
+                broken_vertices.push(index);
+            } else if vertex.velocity.norm() > self.max_speed {
+                fast_vertices.push(index);
+            }
+        }
+
+        let broken_particles: Vec<usize> = particles
+            .iter()
+            .enumerate()
+            .filter(|&(_, particle)| {
+                !finite(&particle.position) || !finite(&particle.velocity)
+                    || particle.velocity.norm() > self.max_speed
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        if broken_vertices.is_empty() && fast_vertices.is_empty() && broken_particles.is_empty() {
+            return None;
+        }
+
+        let offender = |index: usize| {
+            broken_vertices.contains(&index) || fast_vertices.contains(&index)
+        };
+        let offending_surfaces = surfaces
+            .iter()
+            .enumerate()
+            .filter(|&(_, surface)| offender(surface.index_a) || offender(surface.index_b))
+            .map(|(index, _)| index)
+            .collect();
+
+        // Going back would undo the vertices and surfaces added or removed since,
+        // and going back too many times only blows up again
+        let can_rollback = self.rollbacks < self.max_rollbacks
+            && self.last_good.as_ref().map_or(false, |snapshot| {
+                snapshot.verts.len() == verts.len() && snapshot.surfaces.len() == surfaces.len()
+            });
+        let action = if self.policy == HealthPolicy::Rollback && !can_rollback {
+            HealthPolicy::FreezeOffenders
+        } else {
+            self.policy
+        };
+
+        Some(HealthReport {
+            broken_vertices,
+            fast_vertices,
+            surfaces: offending_surfaces,
+            particles: broken_particles,
+            action,
+        })
+    }
+
+    /// Clamps or freezes the offending vertices, putting the broken ones at their last finite position,
+    /// the broken particles are removed and the fast ones slowed down
+    pub fn repair(
+        &self,
+        verts: &[RefCell<Vertex>],
+        particles: &mut Vec<Particle>,
+        report: &HealthReport,
+    ) {
+        for &index in report.broken_vertices.iter().chain(report.fast_vertices.iter()) {
+            let mut vertex = verts[index].borrow_mut();
+            vertex.acceleration = Vector::new(0.0, 0.0);
+
+            if !finite(&vertex.position) || !finite(&vertex.velocity) {
+                if !finite(&vertex.position) {
+                    // A vertex never seen finite has nowhere to go back to, it's parked at the origin
+                    vertex.position = match self.last_positions.get(index) {
+                        Some(position) if finite(position) => *position,
+                        _ => Vector::new(0.0, 0.0),
+                    };
+                }
+                vertex.velocity = Vector::new(0.0, 0.0);
+            } else {
+                let speed = vertex.velocity.norm();
+                vertex.velocity *= self.max_speed / speed;
+            }
+
+            if report.action == HealthPolicy::FreezeOffenders {
+                vertex.velocity = Vector::new(0.0, 0.0);
+                vertex.is_static = true;
+            }
+        }
+
+        // Going from the last index keeps the others where they are
+        for &index in report.particles.iter().rev() {
+            let speed = particles[index].velocity.norm();
+            if finite(&particles[index].position) && speed.is_finite() {
+                particles[index].velocity *= self.max_speed / speed;
+            } else {
+                particles.remove(index);
+            }
+        }
+    }
+
+    pub fn last_good(&self) -> Option<&Snapshot> {
+        self.last_good.as_ref()
+    }
+
+    /// Counts a healthy step, returning whether a new snapshot should be taken after it
+    pub fn healthy(&mut self, verts: &[RefCell<Vertex>], surfaces: &[Surface]) -> bool {
+        self.healthy_steps += 1;
+
+        // A snapshot with other vertices or surfaces can't be gone back to
+        let outdated = self.last_good.as_ref().map_or(true, |snapshot| {
+            snapshot.verts.len() != verts.len() || snapshot.surfaces.len() != surfaces.len()
+        });
+        outdated || self.healthy_steps >= self.snapshot_interval
+    }
+
+    pub fn set_last_good(&mut self, snapshot: Snapshot) {
+        self.last_good = Some(snapshot);
+        self.healthy_steps = 0;
+        self.rollbacks = 0;
+    }
+
+    /// Counts going back to the last healthy step
+    pub fn rolled_back(&mut self) {
+        self.rollbacks += 1;
+    }
+}
@@ -0,0 +1,136 @@
+use std::cell::RefCell;
+
+use physics::quadtree::{Point, QuadTree, Source};
+use physics::simulation::Vertex;
+use Vector;
+
+/// Forces acting between every couple of vertices, not only the ones joined by surfaces
+pub struct Interactions {
+    /// Charged vertices attract or repel each other
+    pub coulomb: bool,
+    pub coulomb_constant: f64,
+
+    /// Vertices attract each other because of their masses
+    pub gravity: bool,
+    pub gravitational_constant: f64,
+
+    /// Cohesive vertices attract each other when close and repel when too close
+    pub lennard_jones: bool,
+    /// The distance at which the Lennard-Jones potential is zero
+    pub cohesion_distance: f64,
+
+    /// How small a group of vertices must look to be taken as a single one, 0 makes it exact
+    pub theta: f64,
+    /// Keeps the forces finite when two vertices get very close
+    pub softening: f64,
+}
+
+impl Default for Interactions {
+    fn default() -> Interactions {
+        Interactions::new()
+    }
+}
+
+impl Interactions {
+    pub fn new() -> Interactions {
+        Interactions {
+            coulomb: false,
+            coulomb_constant: 1.0,
+            gravity: false,
+            gravitational_constant: 1.0,
+            lennard_jones: false,
+            cohesion_distance: 0.5,
+            theta: 0.5,
+            softening: 0.05,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.coulomb || self.gravity || self.lennard_jones
+    }
+
+    pub fn apply_forces(&self, verts: &[RefCell<Vertex>]) {
+        if !self.enabled() || verts.is_empty() {
+            return;
+        }
+
+        let points = verts
+            .iter()
+            .map(|vertex| {
+                let vertex = vertex.borrow();
+                Point {
+                    position: vertex.position,
+                    mass: vertex.mass as f64,
+                    charge: vertex.charge as f64,
+                }
+            })
+            .collect();
+        let tree = QuadTree::new(points);
+
+        for (index, vertex) in verts.iter().enumerate() {
+            let mut force = Vector::new(0.0, 0.0);
+            let point = *tree.point(index);
+
+            if self.coulomb || self.gravity {
+                tree.far_field(index, self.theta, |source| {
+                    let (mass, mass_center, charge, charge_center) = match source {
+                        Source::Node {
+                            mass,
+                            mass_center,
+                            charge,
+                            charge_center,
+                        } => (mass, mass_center, charge, charge_center),
+                        Source::Point(other) => {
+                            let other = tree.point(other);
+                            (other.mass, other.position, other.charge, other.position)
+                        }
+                    };
+
+                    if self.gravity {
+                        force += self.inverse_square(point.position, mass_center)
+                            * self.gravitational_constant * point.mass * mass;
+                    }
+                    if self.coulomb && point.charge != 0.0 {
+                        // Charges with the same sign push away
+                        force -= self.inverse_square(point.position, charge_center)
+                            * self.coulomb_constant * point.charge * charge;
+                    }
+                });
+            }
+
+            let cohesion = verts[index].borrow().cohesion as f64;
+            if self.lennard_jones && cohesion > 0.0 {
+                // The potential is negligible past a few times its distance
+                let cutoff = self.cohesion_distance * 2.5;
+                tree.near(&point.position, cutoff, |other| {
+                    if other == index {
+                        return;
+                    }
+
+                    let other_cohesion = verts[other].borrow().cohesion as f64;
+                    if other_cohesion <= 0.0 {
+                        return;
+                    }
+
+                    let epsilon = (cohesion * other_cohesion).sqrt();
+                    let delta = point.position - tree.point(other).position;
+                    let distance = f64::max(delta.norm(), self.softening);
+                    let ratio = (self.cohesion_distance / distance).powi(6);
+
+                    // F = 24 epsilon (2 (sigma / r)^12 - (sigma / r)^6) / r, positive when pushing
+                    let magnitude = 24.0 * epsilon * (2.0 * ratio * ratio - ratio) / distance;
+                    force += delta / distance * magnitude;
+                });
+            }
+
+            vertex.borrow_mut().apply_force(force);
+        }
+    }
+
+    /// Returns (to - from) / |to - from|^3, softened for close points
+    fn inverse_square(&self, from: Vector, to: Vector) -> Vector {
+        let delta = to - from;
+        let distance_squared = delta.norm_squared() + self.softening * self.softening;
+        delta / (distance_squared * distance_squared.sqrt())
+    }
+}
@@ -0,0 +1,294 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use physics::simulation::Vertex;
+use physics::surface::Surface;
+
+/// How the properties of two touching things are mixed together
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CombineRule {
+    Average,
+    Min,
+    Max,
+    Multiply,
+}
+
+impl CombineRule {
+    pub const ALL: [CombineRule; 4] = [
+        CombineRule::Average,
+        CombineRule::Min,
+        CombineRule::Max,
+        CombineRule::Multiply,
+    ];
+
+    pub fn combine(&self, a: f32, b: f32) -> f32 {
+        match *self {
+            CombineRule::Average => (a + b) / 2.0,
+            CombineRule::Min => f32::min(a, b),
+            CombineRule::Max => f32::max(a, b),
+            CombineRule::Multiply => a * b,
+        }
+    }
+}
+
+/// The properties of a contact between a vertex and a surface
+pub struct ContactMaterial {
+    pub static_friction: f64,
+    pub dynamic_friction: f64,
+    pub restitution: f64,
+}
+
+impl ContactMaterial {
+    pub fn new(
+        vertex: &Vertex,
+        surface: &Surface,
+        friction_rule: CombineRule,
+        restitution_rule: CombineRule,
+    ) -> ContactMaterial {
+        let static_friction = friction_rule.combine(vertex.static_friction, surface.static_friction);
+        let dynamic_friction =
+            friction_rule.combine(vertex.dynamic_friction, surface.dynamic_friction);
+
+        ContactMaterial {
+            static_friction: static_friction as f64,
+            // Sliding can never be harder than starting to slide
+            dynamic_friction: f32::min(dynamic_friction, static_friction) as f64,
+            restitution: restitution_rule.combine(vertex.restitution, surface.restitution) as f64,
+        }
+    }
+}
+
+/// A named set of physical properties which can be given to surfaces and vertices
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    pub name: String,
+
+    // Surface properties
+    pub damping_ratio: f32,
+    pub strength: f32,
+
+    // Vertex properties
+    pub mass: f32,
+
+    // Shared properties
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    pub restitution: f32,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material {
+            name: String::from("default"),
+            damping_ratio: 0.5,
+            strength: 30.0,
+            mass: 0.05,
+            static_friction: 0.6,
+            dynamic_friction: 0.5,
+            restitution: 1.0,
+        }
+    }
+}
+
+impl Material {
+    fn new(
+        name: &str,
+        damping_ratio: f32,
+        strength: f32,
+        mass: f32,
+        static_friction: f32,
+        dynamic_friction: f32,
+        restitution: f32,
+    ) -> Material {
+        Material {
+            name: String::from(name),
+            damping_ratio,
+            strength,
+            mass,
+            static_friction,
+            dynamic_friction,
+            restitution,
+        }
+    }
+
+    pub fn apply_to_surface(&self, surface: &mut Surface) {
+        surface.material = Some(self.name.clone());
+        surface.damping_ratio = self.damping_ratio;
+        surface.strength = self.strength;
+        surface.static_friction = self.static_friction;
+        surface.dynamic_friction = self.dynamic_friction;
+        surface.restitution = self.restitution;
+    }
+
+    pub fn apply_to_vertex(&self, vertex: &mut Vertex) {
+        vertex.material = Some(self.name.clone());
+        vertex.mass = self.mass;
+        vertex.static_friction = self.static_friction;
+        vertex.dynamic_friction = self.dynamic_friction;
+        vertex.restitution = self.restitution;
+    }
+
+    /// Sets the property called `key` from its text `value`
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value: f32 = value
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", value))?;
+        check_property(key, value)?;
+
+        match key {
+            "damping_ratio" => self.damping_ratio = value,
+            "strength" => self.strength = value,
+            "mass" => self.mass = value,
+            "static_friction" => self.static_friction = value,
+            "dynamic_friction" => self.dynamic_friction = value,
+            "restitution" => self.restitution = value,
+            _ => return Err(format!("unknown property '{}'", key)),
+        }
+
+        Ok(())
+    }
+
+    /// Checks that all the properties make sense, like for a material read from a file
+    pub fn validate(&self) -> Result<(), String> {
+        let properties = [
+            ("damping_ratio", self.damping_ratio),
+            ("strength", self.strength),
+            ("mass", self.mass),
+            ("static_friction", self.static_friction),
+            ("dynamic_friction", self.dynamic_friction),
+            ("restitution", self.restitution),
+        ];
+        for &(key, value) in &properties {
+            check_property(key, value)
+                .map_err(|error| format!("material '{}': {}", self.name, error))?;
+        }
+        Ok(())
+    }
+}
+
+/// Rejects the values a material property can't take
+fn check_property(key: &str, value: f32) -> Result<(), String> {
+    if !value.is_finite() {
+        return Err(format!("'{}' is not a finite number", value));
+    }
+
+    match key {
+        "mass" if value <= 0.0 => Err(format!("the mass must be positive, not {}", value)),
+        "static_friction" | "dynamic_friction" if value < 0.0 => {
+            Err(format!("the friction can't be negative, not {}", value))
+        }
+        "restitution" if value < 0.0 || value > 1.0 => Err(format!(
+            "the restitution must be between 0 and 1, not {}",
+            value
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The materials available in a world, found by name
+pub struct MaterialLibrary {
+    pub materials: Vec<Material>,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> MaterialLibrary {
+        MaterialLibrary::new()
+    }
+}
+
+impl MaterialLibrary {
+    /// Creates a library with the built-in materials
+    pub fn new() -> MaterialLibrary {
+        MaterialLibrary {
+            materials: vec![
+                Material::default(),
+                Material::new("rubber", 0.3, 20.0, 0.05, 1.0, 0.8, 0.8),
+                Material::new("steel", 0.9, 500.0, 0.4, 0.4, 0.3, 0.3),
+                Material::new("jelly", 0.2, 8.0, 0.03, 0.7, 0.6, 0.1),
+                Material::new("ice", 0.6, 200.0, 0.045, 0.05, 0.02, 0.1),
+                Material::new("wood", 0.7, 150.0, 0.03, 0.5, 0.4, 0.4),
+            ],
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    /// The materials added or changed since the library was created, the ones worth saving
+    pub fn custom(&self) -> Vec<Material> {
+        let built_in = MaterialLibrary::new();
+        self.materials
+            .iter()
+            .filter(|&material| !built_in.materials.contains(material))
+            .cloned()
+            .collect()
+    }
+
+    /// Adds a material, replacing the one with the same name if any
+    pub fn insert(&mut self, material: Material) {
+        match self.materials
+            .iter()
+            .position(|other| other.name == material.name)
+        {
+            Some(index) => self.materials[index] = material,
+            None => self.materials.push(material),
+        }
+    }
+
+    /// Adds the materials defined in a file, returning how many were read
+    pub fn load(&mut self, path: &Path) -> io::Result<usize> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        self.parse(&text)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Reads materials written as
+    /// ```text
+    /// [name]
+    /// property = value
+    /// ```
+    /// where the properties not given are the ones of the default material
+    pub fn parse(&mut self, text: &str) -> Result<usize, String> {
+        let mut materials: Vec<Material> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                materials.push(Material {
+                    name: line[1..line.len() - 1].trim().to_string(),
+                    ..Material::default()
+                });
+            } else {
+                let material = materials
+                    .last_mut()
+                    .ok_or_else(|| format!("line {}: property outside of a material", number + 1))?;
+
+                let mut parts = line.splitn(2, '=');
+                let key = parts.next().unwrap_or("").trim();
+                let value = parts
+                    .next()
+                    .ok_or_else(|| format!("line {}: expected 'property = value'", number + 1))?
+                    .trim();
+
+                material
+                    .set(key, value)
+                    .map_err(|error| format!("line {}: {}", number + 1, error))?;
+            }
+        }
+
+        let count = materials.len();
+        for material in materials {
+            self.insert(material);
+        }
+        Ok(count)
+    }
+}
@@ -1,3 +1,14 @@
 pub mod collisions;
+pub mod contacts;
+pub mod determinism;
+pub mod error;
+pub mod fluid;
+pub mod health;
+pub mod interactions;
+pub mod material;
+pub mod quadtree;
+pub mod regions;
 pub mod simulation;
+pub mod snapshot;
 pub mod surface;
+pub mod validation;
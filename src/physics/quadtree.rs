@@ -0,0 +1,246 @@
+use Vector;
+
+/// Beyond this depth points in the same spot stay together in a leaf
+const MAX_DEPTH: usize = 24;
+
+/// A point stored in the tree with the quantities summed up by the nodes
+#[derive(Clone, Copy)]
+pub struct Point {
+    pub position: Vector,
+    pub mass: f64,
+    pub charge: f64,
+}
+
+struct Node {
+    center: Vector,
+    half_size: f64,
+    children: Option<[usize; 4]>,
+    points: Vec<usize>,
+
+    mass: f64,
+    /// The center of the masses
+    mass_center: Vector,
+    charge: f64,
+    /// The sum of the magnitudes of the charges
+    charge_weight: f64,
+    /// The center of the charges, weighted by their magnitude
+    charge_center: Vector,
+}
+
+impl Node {
+    fn new(center: Vector, half_size: f64) -> Node {
+        Node {
+            center,
+            half_size,
+            children: None,
+            points: Vec::new(),
+            mass: 0.0,
+            mass_center: Vector::new(0.0, 0.0),
+            charge: 0.0,
+            charge_weight: 0.0,
+            charge_center: Vector::new(0.0, 0.0),
+        }
+    }
+
+    fn quadrant(&self, position: &Vector) -> usize {
+        let right = if position.x >= self.center.x { 1 } else { 0 };
+        let top = if position.y >= self.center.y { 2 } else { 0 };
+        right + top
+    }
+}
+
+/// What a point feels from a group of points, either a single far away node or an exact point
+pub enum Source {
+    Node {
+        mass: f64,
+        mass_center: Vector,
+        charge: f64,
+        charge_center: Vector,
+    },
+    Point(usize),
+}
+
+/// A Barnes–Hut quadtree, which lets far away groups of points act as a single one
+pub struct QuadTree {
+    points: Vec<Point>,
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(points: Vec<Point>) -> QuadTree {
+        let mut tree = QuadTree {
+            points,
+            nodes: Vec::new(),
+        };
+
+        if tree.points.is_empty() {
+            return tree;
+        }
+
+        // Make the root big enough to hold all the points
+        let mut min = tree.points[0].position;
+        let mut max = tree.points[0].position;
+        for point in &tree.points {
+            min.x = f64::min(min.x, point.position.x);
+            min.y = f64::min(min.y, point.position.y);
+            max.x = f64::max(max.x, point.position.x);
+            max.y = f64::max(max.y, point.position.y);
+        }
+        let half_size = f64::max(max.x - min.x, max.y - min.y) / 2.0 + 1e-6;
+        tree.nodes.push(Node::new((min + max) / 2.0, half_size));
+
+        for index in 0..tree.points.len() {
+            tree.insert(0, index, 0);
+        }
+        tree.summarize(0);
+
+        tree
+    }
+
+    pub fn point(&self, index: usize) -> &Point {
+        &self.points[index]
+    }
+
+    fn insert(&mut self, node: usize, index: usize, depth: usize) {
+        if let Some(children) = self.nodes[node].children {
+            let quadrant = self.nodes[node].quadrant(&self.points[index].position);
+            self.insert(children[quadrant], index, depth + 1);
+            return;
+        }
+
+        self.nodes[node].points.push(index);
+        if self.nodes[node].points.len() == 1 || depth >= MAX_DEPTH {
+            return;
+        }
+
+        // Split the leaf and move its points down
+        let center = self.nodes[node].center;
+        let quarter = self.nodes[node].half_size / 2.0;
+        let mut children = [0; 4];
+        for (quadrant, child) in children.iter_mut().enumerate() {
+            let x = if quadrant & 1 == 1 { quarter } else { -quarter };
+            let y = if quadrant & 2 == 2 { quarter } else { -quarter };
+            *child = self.nodes.len();
+            self.nodes
+                .push(Node::new(center + Vector::new(x, y), quarter));
+        }
+        self.nodes[node].children = Some(children);
+
+        let points: Vec<usize> = self.nodes[node].points.drain(..).collect();
+        for point in points {
+            self.insert(node, point, depth);
+        }
+    }
+
+    /// Sums up the mass and charge of every node from its children
+    fn summarize(&mut self, node: usize) {
+        let mut mass = 0.0;
+        let mut mass_center = Vector::new(0.0, 0.0);
+        let mut charge = 0.0;
+        let mut charge_weight = 0.0;
+        let mut charge_center = Vector::new(0.0, 0.0);
+
+        if let Some(children) = self.nodes[node].children {
+            for &child in &children {
+                self.summarize(child);
+
+                let child = &self.nodes[child];
+                mass += child.mass;
+                mass_center += child.mass_center * child.mass;
+                charge += child.charge;
+                charge_weight += child.charge_weight;
+                charge_center += child.charge_center * child.charge_weight;
+            }
+        } else {
+            for &index in &self.nodes[node].points {
+                let point = &self.points[index];
+                mass += point.mass;
+                mass_center += point.position * point.mass;
+                charge += point.charge;
+                charge_weight += point.charge.abs();
+                charge_center += point.position * point.charge.abs();
+            }
+        }
+
+        let node = &mut self.nodes[node];
+        node.mass = mass;
+        node.mass_center = if mass != 0.0 {
+            mass_center / mass
+        } else {
+            node.center
+        };
+        node.charge = charge;
+        node.charge_weight = charge_weight;
+        node.charge_center = if charge_weight != 0.0 {
+            charge_center / charge_weight
+        } else {
+            node.center
+        };
+    }
+
+    /// Calls `visit` with what the point at `index` feels from all the others,
+    /// nodes seen under an angle smaller than `theta` are taken as a whole
+    pub fn far_field<F: FnMut(Source)>(&self, index: usize, theta: f64, mut visit: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let position = self.points[index].position;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+
+            match node.children {
+                Some(children) => {
+                    let distance = (node.mass_center - position).norm();
+                    let contains = (position.x - node.center.x).abs() <= node.half_size
+                        && (position.y - node.center.y).abs() <= node.half_size;
+
+                    if !contains && node.half_size * 2.0 < theta * distance {
+                        visit(Source::Node {
+                            mass: node.mass,
+                            mass_center: node.mass_center,
+                            charge: node.charge,
+                            charge_center: node.charge_center,
+                        });
+                    } else {
+                        stack.extend(children.iter());
+                    }
+                }
+                None => for &other in &node.points {
+                    if other != index {
+                        visit(Source::Point(other));
+                    }
+                },
+            }
+        }
+    }
+
+    /// Calls `visit` with every point closer than `radius` to `position`
+    pub fn near<F: FnMut(usize)>(&self, position: &Vector, radius: f64, mut visit: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+
+            // Skip the nodes whose square is too far from the circle
+            if (position.x - node.center.x).abs() > node.half_size + radius
+                || (position.y - node.center.y).abs() > node.half_size + radius
+            {
+                continue;
+            }
+
+            match node.children {
+                Some(children) => stack.extend(children.iter()),
+                None => for &index in &node.points {
+                    if (self.points[index].position - position).norm() < radius {
+                        visit(index);
+                    }
+                },
+            }
+        }
+    }
+}
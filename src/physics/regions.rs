@@ -0,0 +1,132 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use physics::simulation::{Vertex, GRAVITY};
+use Vector;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum RegionShape {
+    /// Everything below a height
+    Level(f64),
+    Polygon(Vec<Vector>),
+}
+
+/// A still volume of liquid which makes the bodies inside it float and slow down
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FluidRegion {
+    pub shape: RegionShape,
+    pub density: f64,
+    pub linear_drag: f64,
+    pub quadratic_drag: f64,
+}
+
+impl Default for FluidRegion {
+    fn default() -> FluidRegion {
+        FluidRegion::level(0.0)
+    }
+}
+
+impl FluidRegion {
+    pub fn level(height: f64) -> FluidRegion {
+        FluidRegion::new(RegionShape::Level(height))
+    }
+
+    pub fn polygon(points: Vec<Vector>) -> FluidRegion {
+        FluidRegion::new(RegionShape::Polygon(points))
+    }
+
+    fn new(shape: RegionShape) -> FluidRegion {
+        FluidRegion {
+            shape,
+            density: 0.02,
+            linear_drag: 0.05,
+            quadratic_drag: 0.02,
+        }
+    }
+
+    pub fn contains(&self, point: &Vector) -> bool {
+        match self.shape {
+            RegionShape::Level(height) => point.y < height,
+            RegionShape::Polygon(ref points) => {
+                // Count how many edges a ray going right from the point crosses
+                let mut inside = false;
+                for i in 0..points.len() {
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+                    if (a.y > point.y) != (b.y > point.y)
+                        && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+                    {
+                        inside = !inside;
+                    }
+                }
+                inside
+            }
+        }
+    }
+
+    /// Pushes up the vertices inside the region and slows them down
+    pub fn apply_forces(&self, verts: &Vec<RefCell<Vertex>>, bodies: &BTreeMap<usize, (f64, usize)>) {
+        for vertex in verts {
+            let mut vertex = vertex.borrow_mut();
+            if vertex.is_static || !self.contains(&vertex.position) {
+                continue;
+            }
+
+            // Each vertex carries an equal share of the area of its body,
+            // so the force grows with how much of the body is submerged
+            let (area, count) = bodies[&vertex.body];
+            let buoyancy = Vector::new(0.0, self.density * GRAVITY * area / count as f64);
+
+            let velocity = vertex.velocity;
+            let drag = -velocity * (self.linear_drag + self.quadratic_drag * velocity.norm());
+
+            vertex.apply_force(buoyancy + drag);
+        }
+    }
+}
+
+/// Returns the area of the convex hull around `points`
+pub fn hull_area(points: &mut Vec<Vector>) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+
+    points.sort_by(|a, b| {
+        (a.x, a.y)
+            .partial_cmp(&(b.x, b.y))
+            .unwrap_or(::std::cmp::Ordering::Equal)
+    });
+
+    let turn = |o: &Vector, a: &Vector, b: &Vector| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+
+    // Andrew's monotone chain, first the lower then the upper half
+    let mut hull: Vec<Vector> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        let ordered: Vec<&Vector> = if pass == 0 {
+            points.iter().collect()
+        } else {
+            points.iter().rev().collect()
+        };
+
+        for point in ordered {
+            while hull.len() >= start + 2
+                && turn(&hull[hull.len() - 2], &hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+    }
+
+    // Shoelace formula
+    let mut area = 0.0;
+    for i in 0..hull.len() {
+        let a = hull[i];
+        let b = hull[(i + 1) % hull.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area.abs() / 2.0
+}
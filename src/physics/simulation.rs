@@ -1,34 +1,93 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use Vector;
 
 use physics::collisions;
+use physics::contacts::ContactSolver;
+use physics::determinism::{self, Rng};
+use physics::error::WorldError;
+use physics::fluid::Fluid;
+use physics::health::{HealthMonitor, HealthPolicy};
+use physics::interactions::Interactions;
+use physics::material::{ContactMaterial, Material, MaterialLibrary};
+use physics::regions::{self, FluidRegion};
+use physics::snapshot::Snapshot;
 use physics::surface::Surface;
+use physics::validation::{self, ValidationReport};
+
+/// The acceleration of gravity, pointing down
+pub const GRAVITY: f64 = 9.8;
+
+/// The maximum number of impacts resolved in a single step
+const MAX_IMPACTS: usize = 32;
 
 pub struct DebugView {
     pub vectors: Vec<(Vector, Vector)>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Vertex {
     pub mass: f32,
     pub position: Vector,
     pub velocity: Vector,
+    #[serde(skip)]
     pub acceleration: Vector,
     pub is_static: bool,
+
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    pub restitution: f32,
+    /// The name of the material the properties come from, if any
+    pub material: Option<String>,
+
+    /// The electric charge, used when Coulomb interactions are enabled
+    pub charge: f32,
+    /// The depth of the Lennard-Jones potential well, 0 for vertices which don't stick
+    pub cohesion: f32,
+
+    /// The index of the body (group of connected vertices) this vertex belongs to
+    #[serde(skip)]
+    pub body: usize,
+}
+
+impl Default for Vertex {
+    fn default() -> Vertex {
+        Vertex::new(Vector::new(0.0, 0.0))
+    }
 }
 
 impl Vertex {
     pub fn new(position: Vector) -> Vertex {
+        let material = Material::default();
+
         Vertex {
-            mass: 0.05,
+            mass: material.mass,
             position,
             velocity: Vector::new(0.0, 0.0),
             acceleration: Vector::new(0.0, 0.0),
             is_static: false,
+            static_friction: material.static_friction,
+            dynamic_friction: material.dynamic_friction,
+            restitution: material.restitution,
+            material: None,
+            charge: 0.0,
+            cohesion: 0.0,
+            body: 0,
+        }
+    }
+
+    /// Static vertices have an infinite mass, so an inverse mass of 0
+    pub fn inverse_mass(&self) -> f64 {
+        if self.is_static {
+            0.0
+        } else {
+            1.0 / self.mass as f64
         }
     }
 
     pub fn apply_force(&mut self, force: Vector) {
-        self.acceleration += force / self.mass as f64;
+        self.acceleration += force * self.inverse_mass();
     }
 
     pub fn force_to_velocity(&mut self, dt: f64) {
@@ -39,19 +98,102 @@ impl Vertex {
         self.position + self.velocity * dt
     }
 
-    pub fn update(&mut self, dt: f64) {
+    /// Moves the vertex along its velocity without ending the step
+    pub fn advance(&mut self, dt: f64) {
         if !self.is_static {
             self.position += self.velocity * dt;
         }
+    }
+
+    pub fn update(&mut self, dt: f64) {
+        self.advance(dt);
 
         self.acceleration.x = 0.0;
         self.acceleration.y = 0.0;
     }
 }
 
+/// The velocity of `vertex` away from the segment `ab` along `normal`, negative when approaching
+fn relative_normal_velocity(vertex: &Vertex, a: &Vertex, b: &Vertex, normal: Vector) -> f64 {
+    let ratio = collisions::segment_ratio(&vertex.position, &a.position, &b.position);
+    let segment_velocity = a.velocity * (1.0 - ratio) + b.velocity * ratio;
+    (vertex.velocity - segment_velocity).dot(&normal)
+}
+
+/// Picks the number of substeps of each step from how fast and how stiff the world is
+pub struct AdaptiveSteps {
+    pub enabled: bool,
+    pub min: u32,
+    pub max: u32,
+    /// The fraction of the shortest surface a vertex can travel in a substep
+    pub courant: f64,
+    /// The fraction of the fastest spring oscillation (in radians) a substep can last
+    pub oscillation: f64,
+}
+
+impl Default for AdaptiveSteps {
+    fn default() -> AdaptiveSteps {
+        AdaptiveSteps::new()
+    }
+}
+
+impl AdaptiveSteps {
+    pub fn new() -> AdaptiveSteps {
+        AdaptiveSteps {
+            enabled: false,
+            min: 1,
+            max: 64,
+            courant: 0.5,
+            oscillation: 0.5,
+        }
+    }
+}
+
+/// The mechanical energy of the world, the gravitational one is measured from a height of 0
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Energy {
+    pub kinetic: f64,
+    pub gravitational: f64,
+    /// Stored in the stretched and compressed surfaces
+    pub elastic: f64,
+}
+
+impl Energy {
+    pub fn total(&self) -> f64 {
+        self.kinetic + self.gravitational + self.elastic
+    }
+}
+
 pub struct World {
     pub verts: Vec<RefCell<Vertex>>,
     pub surfaces: Vec<Surface>,
+    pub solver: ContactSolver,
+    pub materials: MaterialLibrary,
+    pub fluid: Fluid,
+    pub regions: Vec<FluidRegion>,
+    /// The density of the air, 0 disables the aerodynamic forces
+    pub air_density: f64,
+    pub wind: Vector,
+    pub interactions: Interactions,
+    pub adaptive_steps: AdaptiveSteps,
+    /// The number of substeps used by the last update
+    pub substeps: u32,
+    /// The contacts solved over all the substeps of the last update
+    pub contacts: usize,
+    pub health: HealthMonitor,
+
+    /// Steps by `fixed_dt` whatever dt is passed to update,
+    /// so that the same inputs always give bit-identical results
+    pub deterministic: bool,
+    pub fixed_dt: f64,
+    /// The source of all the randomness in the world
+    pub rng: Rng,
+    /// The simulated time and number of steps since the start
+    pub time: f64,
+    pub step: u64,
+    /// The state hash after the last step
+    pub last_hash: u64,
+
     pub debug: DebugView,
 }
 
@@ -60,6 +202,23 @@ impl World {
         World {
             verts: Vec::new(),
             surfaces: Vec::new(),
+            solver: ContactSolver::new(),
+            materials: MaterialLibrary::new(),
+            fluid: Fluid::new(),
+            regions: Vec::new(),
+            air_density: 0.0,
+            wind: Vector::new(0.0, 0.0),
+            interactions: Interactions::new(),
+            adaptive_steps: AdaptiveSteps::new(),
+            substeps: 0,
+            contacts: 0,
+            health: HealthMonitor::new(),
+            deterministic: false,
+            fixed_dt: 1.0 / 120.0,
+            rng: Rng::new(0),
+            time: 0.0,
+            step: 0,
+            last_hash: 0,
             debug: DebugView {
                 vectors: Vec::new(),
             },
@@ -77,10 +236,16 @@ impl World {
 
     pub fn add_vertex(&mut self, vertex: Vertex) {
         self.verts.push(RefCell::new(vertex));
+        self.update_bodies();
     }
 
-    pub fn remove_vertex(&mut self, index: usize) {
+    pub fn remove_vertex(&mut self, index: usize) -> Result<(), WorldError> {
+        if index >= self.verts.len() {
+            return Err(WorldError::UnknownVertex(index));
+        }
+
         self.verts.remove(index);
+        self.solver.clear_cache();
 
         // If there is a surface with the vertex remove it
         let mut i = 0;
@@ -99,6 +264,34 @@ impl World {
                 i += 1;
             }
         }
+
+        self.update_bodies();
+        Ok(())
+    }
+
+    /// Moves a vertex, changing the target distance of its surfaces so they don't pull it back
+    pub fn drag_vertex(&mut self, index: usize, delta: Vector) -> Result<(), WorldError> {
+        if index >= self.verts.len() {
+            return Err(WorldError::UnknownVertex(index));
+        }
+
+        let surfaces = self.get_vertex_surfaces(index);
+        let mut vertex = self.verts[index].borrow_mut();
+        vertex.position += delta;
+
+        for i in surfaces {
+            let surface = &mut self.surfaces[i];
+
+            let other_vertex = if surface.index_a == index {
+                self.verts[surface.index_b].borrow()
+            } else {
+                self.verts[surface.index_a].borrow()
+            };
+
+            surface.target_distance = (vertex.position - other_vertex.position).norm();
+        }
+
+        Ok(())
     }
 
     pub fn get_vertex_at(&mut self, position: &Vector, radius: f64) -> Option<usize> {
@@ -155,27 +348,182 @@ impl World {
         None
     }
 
-    pub fn create_surface(&mut self, index_a: usize, index_b: usize) {
+    /// Connects two vertices with a surface, returning its index
+    pub fn create_surface(&mut self, index_a: usize, index_b: usize) -> Result<usize, WorldError> {
         use std::usize;
 
-        if index_a == index_b {
-            return;
-        }
-
         // Make the first index always the smaller one
         let ord_a = usize::min(index_a, index_b);
         let ord_b = usize::max(index_a, index_b);
 
-        // If the surface is not already present
+        // If the surface is already present
         for surface in &self.surfaces {
             if surface.index_a == ord_a && surface.index_b == ord_b {
-                return;
+                return Err(WorldError::DuplicateSurface(ord_a, ord_b));
             }
         }
 
         // Add the surface to the surfaces
-        self.surfaces
-            .push(Surface::new(ord_a, ord_b, &mut self.verts));
+        let surface = Surface::new(ord_a, ord_b, &self.verts)?;
+        self.surfaces.push(surface);
+        self.update_bodies();
+        Ok(self.surfaces.len() - 1)
+    }
+
+    pub fn remove_surface(&mut self, index: usize) -> Result<(), WorldError> {
+        if index >= self.surfaces.len() {
+            return Err(WorldError::UnknownSurface(index));
+        }
+
+        self.surfaces.remove(index);
+        self.solver.clear_cache();
+        self.update_bodies();
+        Ok(())
+    }
+
+    /*
+     #####   ####  #####  # ######  ####  
+     #    # #    # #    # # #      #      
+     #####  #    # #    # # #####   ####  
+     #    # #    # #    # # #           # 
+     #    # #    # #    # # #      #    # 
+     #####   ####  #####  # ######  ####  
+    */
+
+    /// Assigns to every vertex the index of its body,
+    /// the smallest index of the vertices connected to it by surfaces
+    pub fn update_bodies(&mut self) {
+        for (index, vertex) in self.verts.iter().enumerate() {
+            vertex.borrow_mut().body = index;
+        }
+
+        // Spread the smallest index through the surfaces until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for surface in &self.surfaces {
+                let mut vertex_a = self.verts[surface.index_a].borrow_mut();
+                let mut vertex_b = self.verts[surface.index_b].borrow_mut();
+
+                let body = usize::min(vertex_a.body, vertex_b.body);
+                if vertex_a.body != body || vertex_b.body != body {
+                    vertex_a.body = body;
+                    vertex_b.body = body;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Checks every invariant the simulation relies on
+    pub fn validate(&self) -> ValidationReport {
+        validation::validate(&self.verts, &self.surfaces)
+    }
+
+    /// Returns the area and number of vertices of each body
+    pub fn body_areas(&self) -> BTreeMap<usize, (f64, usize)> {
+        let mut points: BTreeMap<usize, Vec<Vector>> = BTreeMap::new();
+        for vertex in &self.verts {
+            let vertex = vertex.borrow();
+            points
+                .entry(vertex.body)
+                .or_insert_with(Vec::new)
+                .push(vertex.position);
+        }
+
+        points
+            .into_iter()
+            .map(|(body, mut points)| {
+                let count = points.len();
+                (body, (regions::hull_area(&mut points), count))
+            })
+            .collect()
+    }
+
+    /*
+     #    #   ##   ##### ###### #####  #   ##   #       ####  
+     ##  ##  #  #    #   #      #    # #  #  #  #      #      
+     # ## # #    #   #   #####  #    # # #    # #       ####  
+     #    # ######   #   #      #####  # ###### #           # 
+     #    # #    #   #   #      #   #  # #    # #      #    # 
+     #    # #    #   #   ###### #    # # #    # ######  ####  
+    */
+
+    /// Gives the material called `name` to a vertex
+    pub fn set_vertex_material(&mut self, index: usize, name: &str) -> Result<(), WorldError> {
+        let vertex = self.verts.get(index).ok_or(WorldError::UnknownVertex(index))?;
+        let material = self.materials
+            .get(name)
+            .ok_or_else(|| WorldError::UnknownMaterial(name.to_string()))?;
+
+        material.apply_to_vertex(&mut vertex.borrow_mut());
+        Ok(())
+    }
+
+    /// Gives the material called `name` to a surface
+    pub fn set_surface_material(&mut self, index: usize, name: &str) -> Result<(), WorldError> {
+        let surface = self.surfaces
+            .get_mut(index)
+            .ok_or(WorldError::UnknownSurface(index))?;
+        let material = self.materials
+            .get(name)
+            .ok_or_else(|| WorldError::UnknownMaterial(name.to_string()))?;
+
+        material.apply_to_surface(surface);
+        Ok(())
+    }
+
+    /// Gives the material called `name` to all the vertices and surfaces of a body
+    pub fn set_body_material(&mut self, body: usize, name: &str) -> Result<(), WorldError> {
+        let material = self.materials
+            .get(name)
+            .ok_or_else(|| WorldError::UnknownMaterial(name.to_string()))?;
+
+        for vertex in &self.verts {
+            let mut vertex = vertex.borrow_mut();
+            if vertex.body == body {
+                material.apply_to_vertex(&mut vertex);
+            }
+        }
+
+        for surface in &mut self.surfaces {
+            if self.verts[surface.index_a].borrow().body == body {
+                material.apply_to_surface(surface);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies again the materials to everything using them,
+    /// so that changes to the library show up in the world
+    pub fn reapply_materials(&mut self) {
+        let materials = &self.materials;
+
+        for vertex in &self.verts {
+            let mut vertex = vertex.borrow_mut();
+            let material = vertex
+                .material
+                .as_ref()
+                .and_then(|name| materials.get(name))
+                .cloned();
+
+            if let Some(material) = material {
+                material.apply_to_vertex(&mut vertex);
+            }
+        }
+
+        for surface in &mut self.surfaces {
+            let material = surface
+                .material
+                .as_ref()
+                .and_then(|name| materials.get(name))
+                .cloned();
+
+            if let Some(material) = material {
+                material.apply_to_surface(surface);
+            }
+        }
     }
 
     /*
@@ -187,38 +535,227 @@ impl World {
       ####  # #    #  ####  ###### #    #   #   #  ####  #    # 
     */
 
-    pub fn resolve_collisions(&mut self, dt: f64) {
+    pub fn resolve_collisions(&mut self, dt: f64, iterations: u32) {
+        let mut contacts = self.solver.find_contacts(&self.verts, &self.surfaces, dt);
+        self.solver
+            .solve(&mut contacts, &self.verts, &self.surfaces, iterations);
+        self.contacts += contacts.len();
+
+        // Separate the edges of different bodies crossing each other
+        for i in 0..self.surfaces.len() {
+            for j in (i + 1)..self.surfaces.len() {
+                let surface_ab = &self.surfaces[i];
+                let surface_cd = &self.surfaces[j];
+
+                if self.verts[surface_ab.index_a].borrow().body
+                    == self.verts[surface_cd.index_a].borrow().body
+                {
+                    continue;
+                }
+
+                let mut a = self.verts[surface_ab.index_a].borrow_mut();
+                let mut b = self.verts[surface_ab.index_b].borrow_mut();
+                let mut c = self.verts[surface_cd.index_a].borrow_mut();
+                let mut d = self.verts[surface_cd.index_b].borrow_mut();
+
+                if collisions::edges_colliding(&a, &b, &c, &d, dt) {
+                    let restitution = self.solver
+                        .restitution_rule
+                        .combine(surface_ab.restitution, surface_cd.restitution);
+                    collisions::resolve_crossing(
+                        [&mut a, &mut b],
+                        [&mut c, &mut d],
+                        surface_ab,
+                        surface_cd,
+                        restitution as f64,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the earliest impact within `dt` as (time, vertex index, surface index),
+    /// skipping the `resolved` impacts of this step (vertex, surface, normal) which are separating
+    fn next_impact(
+        &self,
+        dt: f64,
+        resolved: &[(usize, usize, Vector)],
+    ) -> Option<(f64, usize, usize)> {
+        let mut earliest: Option<(f64, usize, usize)> = None;
+
         for vertex_i in 0..self.verts.len() {
-            for surface in &self.surfaces {
-                if surface.index_a != vertex_i && surface.index_b != vertex_i {
-                    let mut vertex = self.verts[vertex_i].borrow_mut();
-                    let mut segment_a = self.verts[surface.index_a].borrow_mut();
-                    let mut segment_b = self.verts[surface.index_b].borrow_mut();
-
-                    if collisions::colliding(&vertex, &segment_a, &segment_b, dt) {
-                        collisions::resolve_impulses(
-                            &mut vertex,
-                            &mut segment_a,
-                            &mut segment_b,
-                            surface,
-                        );
+            let vertex = self.verts[vertex_i].borrow();
+            for (surface_i, surface) in self.surfaces.iter().enumerate() {
+                if surface.index_a == vertex_i || surface.index_b == vertex_i {
+                    continue;
+                }
+
+                let segment_a = self.verts[surface.index_a].borrow();
+                let segment_b = self.verts[surface.index_b].borrow();
+
+                if let Some(t) = collisions::time_of_impact(&vertex, &segment_a, &segment_b, dt) {
+                    let separating = resolved.iter().any(|&(v, s, normal)| {
+                        v == vertex_i && s == surface_i
+                            && relative_normal_velocity(&vertex, &segment_a, &segment_b, normal)
+                                >= 0.0
+                    });
+                    if !separating && earliest.map_or(true, |(earliest_t, _, _)| t < earliest_t) {
+                        earliest = Some((t, vertex_i, surface_i));
                     }
                 }
             }
         }
+
+        earliest
+    }
+
+    /// Moves the vertices through the step stopping at every impact to resolve it,
+    /// so that no vertex can tunnel through a surface
+    pub fn advance(&mut self, dt: f64) {
+        let mut remaining = dt;
+        // The impacts already resolved, with the normal they were resolved with
+        let mut resolved: Vec<(usize, usize, Vector)> = Vec::new();
+
+        for _ in 0..MAX_IMPACTS {
+            let (t, vertex_i, surface_i) = match self.next_impact(remaining, &resolved) {
+                Some(impact) => impact,
+                None => break,
+            };
+
+            let surface = &self.surfaces[surface_i];
+            let material = ContactMaterial::new(
+                &self.verts[vertex_i].borrow(),
+                surface,
+                self.solver.friction_rule,
+                self.solver.restitution_rule,
+            );
+
+            // Take the side the vertex comes from before it reaches the surface,
+            // once resolved the vertex lies on the line and its side can't be told anymore
+            let previous = resolved
+                .iter()
+                .find(|&&(v, s, _)| v == vertex_i && s == surface_i)
+                .map(|&(_, _, normal)| normal);
+            let normal = previous.unwrap_or_else(|| {
+                collisions::normal(
+                    &self.verts[vertex_i].borrow().position,
+                    &self.verts[surface.index_a].borrow().position,
+                    &self.verts[surface.index_b].borrow().position,
+                )
+            });
+            if previous.is_none() {
+                resolved.push((vertex_i, surface_i, normal));
+            }
+
+            for vertex in &self.verts {
+                vertex.borrow_mut().advance(t);
+            }
+            remaining -= t;
+
+            let mut vertex = self.verts[vertex_i].borrow_mut();
+            let mut segment_a = self.verts[surface.index_a].borrow_mut();
+            let mut segment_b = self.verts[surface.index_b].borrow_mut();
+            collisions::resolve_impact(
+                &mut vertex,
+                &mut segment_a,
+                &mut segment_b,
+                surface,
+                &material,
+                normal,
+            );
+        }
+
+        for vertex in &self.verts {
+            vertex.borrow_mut().update(remaining);
+        }
+    }
+
+    /// Returns how many substeps are needed so that within each one no vertex
+    /// moves further than a fraction of the shortest surface and no spring oscillates too much
+    pub fn adaptive_substeps(&self, dt: f64) -> u32 {
+        let settings = &self.adaptive_steps;
+
+        let mut max_speed: f64 = 0.0;
+        for vertex in &self.verts {
+            let speed = vertex.borrow().velocity.norm();
+            // max skips NaN, a blown up world takes as many substeps as allowed
+            if !speed.is_finite() {
+                return settings.max;
+            }
+            max_speed = max_speed.max(speed);
+        }
+
+        let mut min_length = f64::MAX;
+        let mut max_frequency: f64 = 0.0;
+        for surface in &self.surfaces {
+            let vertex_a = self.verts[surface.index_a].borrow();
+            let vertex_b = self.verts[surface.index_b].borrow();
+
+            let length = (vertex_a.position - vertex_b.position).norm();
+            if length > 0.0 {
+                min_length = min_length.min(length);
+            }
+
+            // omega = sqrt(k / m), with the reduced mass of the couple
+            let inverse_mass = vertex_a.inverse_mass() + vertex_b.inverse_mass();
+            max_frequency = max_frequency.max((surface.strength as f64 * inverse_mass).sqrt());
+        }
+
+        let mut substeps = 1.0;
+        if min_length != f64::MAX {
+            substeps = f64::max(substeps, max_speed * dt / (settings.courant * min_length));
+        }
+        substeps = f64::max(substeps, max_frequency * dt / settings.oscillation);
+
+        if !substeps.is_finite() {
+            return settings.max;
+        }
+        u32::max(settings.min, u32::min(settings.max, substeps.ceil() as u32))
     }
 
     pub fn update(&mut self, dt: f64, iterations: u32, collisions: bool) {
-        let dt = dt / iterations as f64;
-        for _ in 0..iterations {
+        let dt = if self.deterministic { self.fixed_dt } else { dt };
+
+        if self.health.enabled {
+            self.health.track(&self.verts);
+        }
+
+        let substeps = if self.adaptive_steps.enabled {
+            self.adaptive_substeps(dt)
+        } else {
+            iterations
+        };
+        self.substeps = substeps;
+        self.contacts = 0;
+
+        self.time += dt;
+        self.step += 1;
+
+        let dt = dt / substeps as f64;
+        for _ in 0..substeps {
             for surface in &self.surfaces {
                 surface.apply_force(&mut self.verts);
             }
 
+            self.interactions.apply_forces(&self.verts);
+
+            if self.air_density > 0.0 {
+                for surface in &self.surfaces {
+                    surface.apply_aerodynamics(&self.verts, self.air_density, self.wind);
+                }
+            }
+
+            if !self.regions.is_empty() {
+                let bodies = self.body_areas();
+                for region in &self.regions {
+                    region.apply_forces(&self.verts, &bodies);
+                }
+            }
+
             for i in 0..self.verts.len() {
                 let mut vertex = self.verts[i].borrow_mut();
                 if !vertex.is_static {
-                    vertex.acceleration.y -= 9.8;
+                    vertex.acceleration.y -= GRAVITY;
                     vertex.force_to_velocity(dt);
                 } else {
                     vertex.velocity.x = 0.0;
@@ -228,16 +765,145 @@ impl World {
                 }
             }
 
+            self.fluid.apply_forces(Vector::new(0.0, -GRAVITY));
+            self.fluid.force_to_velocity(dt);
+
             if collisions {
-                for _ in 0..iterations {
-                    self.resolve_collisions(dt);
+                self.resolve_collisions(dt, iterations);
+                self.fluid.resolve_collisions(
+                    &self.verts,
+                    &self.surfaces,
+                    self.solver.friction_rule,
+                    self.solver.restitution_rule,
+                    dt,
+                );
+                self.advance(dt);
+            } else {
+                for i in 0..self.verts.len() {
+                    let mut vertex = self.verts[i].borrow_mut();
+                    vertex.update(dt);
                 }
             }
 
-            for i in 0..self.verts.len() {
-                let mut vertex = self.verts[i].borrow_mut();
-                vertex.update(dt);
+            self.fluid.update(dt);
+        }
+
+        self.last_hash = self.state_hash();
+        self.check_health();
+    }
+
+    /// Looks for values which blew up during the step and recovers with the health policy
+    fn check_health(&mut self) {
+        if !self.health.enabled {
+            return;
+        }
+
+        match self.health
+            .inspect(&self.verts, &self.surfaces, &self.fluid.particles)
+        {
+            None => {
+                if self.health.healthy(&self.verts, &self.surfaces) {
+                    let snapshot = self.snapshot();
+                    self.health.set_last_good(snapshot);
+                }
+            }
+            Some(report) => {
+                let last_good = self.health.last_good().cloned();
+                match (report.action, last_good) {
+                    (HealthPolicy::Rollback, Some(snapshot)) => {
+                        self.restore(&snapshot);
+                        self.health.rolled_back();
+                    }
+                    _ => {
+                        self.health
+                            .repair(&self.verts, &mut self.fluid.particles, &report);
+                        self.last_hash = self.state_hash();
+                    }
+                }
+                self.health.report = Some(report);
             }
         }
     }
+
+    /// A hash of the exact state of the world, equal only if two runs are bit-identical
+    pub fn state_hash(&self) -> u64 {
+        determinism::hash_state(self)
+    }
+
+    /// The energy of the vertices, the fluid particles and the surfaces, static vertices have none
+    pub fn energy(&self) -> Energy {
+        let mut energy = Energy::default();
+
+        for vertex in &self.verts {
+            let vertex = vertex.borrow();
+            if vertex.is_static {
+                continue;
+            }
+            let mass = vertex.mass as f64;
+            energy.kinetic += 0.5 * mass * vertex.velocity.norm_squared();
+            energy.gravitational += mass * GRAVITY * vertex.position.y;
+        }
+
+        let mass = self.fluid.particle_mass;
+        for particle in &self.fluid.particles {
+            energy.kinetic += 0.5 * mass * particle.velocity.norm_squared();
+            energy.gravitational += mass * GRAVITY * particle.position.y;
+        }
+
+        for surface in &self.surfaces {
+            let a = self.verts[surface.index_a].borrow();
+            let b = self.verts[surface.index_b].borrow();
+            let extension = (a.position - b.position).norm() - surface.target_distance;
+            energy.elastic += 0.5 * surface.strength as f64 * extension * extension;
+        }
+
+        energy
+    }
+
+    /// Fills a circle with fluid particles, using the world random number generator
+    pub fn emit_fluid(&mut self, center: Vector, radius: f64) {
+        self.fluid.emit(center, radius, &mut self.rng);
+    }
+
+    /// Copies the state of the world, to go back to it later with `restore`
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            verts: self.verts
+                .iter()
+                .map(|vertex| vertex.borrow().clone())
+                .collect(),
+            surfaces: self.surfaces.clone(),
+            particles: self.fluid.particles.clone(),
+            regions: self.regions.clone(),
+            contacts: self.solver.cache().clone(),
+            rng: self.rng.clone(),
+            time: self.time,
+            step: self.step,
+            last_hash: self.last_hash,
+        }
+    }
+
+    /// Puts the world back in the state of a snapshot
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.verts = snapshot
+            .verts
+            .iter()
+            .map(|vertex| RefCell::new(vertex.clone()))
+            .collect();
+        self.surfaces = snapshot.surfaces.clone();
+        self.fluid.particles = snapshot.particles.clone();
+        self.regions = snapshot.regions.clone();
+        self.solver.set_cache(snapshot.contacts.clone());
+        self.rng = snapshot.rng.clone();
+        self.time = snapshot.time;
+        self.step = snapshot.step;
+        self.last_hash = snapshot.last_hash;
+        // The bodies aren't kept in the compact snapshots, they are found again
+        self.update_bodies();
+    }
+
+    /// Restarts the random number generator from a seed
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
 }
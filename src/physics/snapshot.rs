@@ -0,0 +1,22 @@
+use physics::contacts::ContactCache;
+use physics::determinism::Rng;
+use physics::fluid::Particle;
+use physics::regions::FluidRegion;
+use physics::simulation::Vertex;
+use physics::surface::Surface;
+
+/// A copy of everything that changes while the world runs,
+/// the parameters (gravity, air, solver settings...) are left out
+#[derive(Clone)]
+pub struct Snapshot {
+    pub verts: Vec<Vertex>,
+    pub surfaces: Vec<Surface>,
+    pub particles: Vec<Particle>,
+    pub regions: Vec<FluidRegion>,
+    /// The impulses used to warm start the next step
+    pub contacts: ContactCache,
+    pub rng: Rng,
+    pub time: f64,
+    pub step: u64,
+    pub last_hash: u64,
+}
@@ -1,7 +1,11 @@
 use std::cell::RefCell;
+use physics::error::WorldError;
+use physics::material::Material;
 use physics::simulation::Vertex;
 use Vector;
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Surface {
     pub index_a: usize,
     pub index_b: usize,
@@ -9,48 +13,168 @@ pub struct Surface {
     pub damping_ratio: f32,
     pub strength: f32,
     pub target_distance: f64,
+    /// The width of the capsule used for collisions
+    pub thickness: f64,
 
-    pub friction: f32,
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
     pub restitution: f32,
+
+    // How much the air pushes against and across the surface
+    pub drag_coefficient: f32,
+    pub lift_coefficient: f32,
+
+    /// The name of the material the properties come from, if any
+    pub material: Option<String>,
 }
 
-impl Surface {
-    pub fn new(index_a: usize, index_b: usize, verts: &Vec<RefCell<Vertex>>) -> Surface {
-        let vertex_a = verts[index_a].borrow();
-        let vertex_b = verts[index_b].borrow();
+impl Default for Surface {
+    /// A surface between the first vertex and itself, only used to fill in the missing fields
+    /// other than the indices, which scenes must always have
+    fn default() -> Surface {
+        let material = Material::default();
 
         Surface {
+            index_a: 0,
+            index_b: 0,
+            damping_ratio: material.damping_ratio,
+            strength: material.strength,
+            target_distance: 0.0,
+            thickness: 0.01,
+            static_friction: material.static_friction,
+            dynamic_friction: material.dynamic_friction,
+            restitution: material.restitution,
+            drag_coefficient: 1.0,
+            lift_coefficient: 0.5,
+            material: None,
+        }
+    }
+}
+
+impl Surface {
+    pub fn new(
+        index_a: usize,
+        index_b: usize,
+        verts: &[RefCell<Vertex>],
+    ) -> Result<Surface, WorldError> {
+        if index_a == index_b {
+            return Err(WorldError::SelfLoop(index_a));
+        }
+        let vertex_a = verts
+            .get(index_a)
+            .ok_or(WorldError::UnknownVertex(index_a))?
+            .borrow();
+        let vertex_b = verts
+            .get(index_b)
+            .ok_or(WorldError::UnknownVertex(index_b))?
+            .borrow();
+
+        let target_distance = (vertex_a.position - vertex_b.position).norm();
+        if target_distance == 0.0 {
+            return Err(WorldError::DegenerateSurface(index_a, index_b));
+        }
+
+        let material = Material::default();
+
+        Ok(Surface {
             index_a,
             index_b,
-            damping_ratio: 0.5,
-            strength: 30.0,
-            target_distance: (vertex_a.position - vertex_b.position).norm(),
-            friction: 0.5,
-            restitution: 1.0,
+            damping_ratio: material.damping_ratio,
+            strength: material.strength,
+            target_distance,
+            thickness: 0.01,
+            static_friction: material.static_friction,
+            dynamic_friction: material.dynamic_friction,
+            restitution: material.restitution,
+            drag_coefficient: 1.0,
+            lift_coefficient: 0.5,
+            material: None,
+        })
+    }
+
+    /// How much longer the surface is than its target distance, negative when compressed
+    pub fn extension(&self, verts: &[RefCell<Vertex>]) -> f64 {
+        let vertex_a = verts[self.index_a].borrow();
+        let vertex_b = verts[self.index_b].borrow();
+        (vertex_a.position - vertex_b.position).norm() - self.target_distance
+    }
+
+    /// The spring and damping force pulling the two ends together, negative when pushing apart
+    pub fn tension(&self, verts: &[RefCell<Vertex>]) -> f64 {
+        let vertex_a = verts[self.index_a].borrow();
+        let vertex_b = verts[self.index_b].borrow();
+
+        let inverse_mass = vertex_a.inverse_mass() + vertex_b.inverse_mass();
+        let delta = vertex_a.position - vertex_b.position;
+        // Two vertices in the same spot give no direction to push along
+        if inverse_mass == 0.0 || delta.norm() == 0.0 {
+            return 0.0;
         }
+
+        // c = 2 * damping_ratio * sqrt(m * k), where m is the reduced mass of the couple
+        let c = 2.0 * self.damping_ratio as f64 * (self.strength as f64 / inverse_mass).sqrt();
+        let extension = delta.norm() - self.target_distance;
+        let separation_velocity = (vertex_a.velocity - vertex_b.velocity).dot(&delta.normalize());
+        extension * self.strength as f64 + separation_velocity * c
     }
 
     pub fn apply_force(&self, verts: &Vec<RefCell<Vertex>>) {
+        let tension = self.tension(verts);
+        if tension == 0.0 {
+            return;
+        }
+
         let mut vertex_a = verts[self.index_a].borrow_mut();
         let mut vertex_b = verts[self.index_b].borrow_mut();
 
-        let mut force = Vector::new(0.0, 0.0);
-        // c = 2 * damping_ratio * sqrt(m * k)
-        let c = 2.0 * self.damping_ratio * ((vertex_a.mass + vertex_b.mass) * self.strength).sqrt();
-
-        let delta = vertex_a.position - vertex_b.position;
-        let relative_velocity = vertex_a.velocity - vertex_b.velocity;
-        let extention = delta.norm() - self.target_distance;
-        // The velocity of the bodies in the direction of each other
-        // How fast they are approaching
-        let approach_velocity = delta.normalize() * relative_velocity.dot(&delta.normalize());
-
-        // F = -kx - cv
-        force +=
-            delta.normalize() * extention * -self.strength as f64 - approach_velocity * c as f64;
+        // F = -kx - cv, along the direction from b to a
+        let force = (vertex_a.position - vertex_b.position).normalize() * -tension;
 
         // Apply the forces to the couple of bodies
         vertex_a.apply_force(force);
         vertex_b.apply_force(-force);
     }
+
+    /// Applies the drag and lift of the air flowing over the surface,
+    /// `wind` is the velocity of the air
+    pub fn apply_aerodynamics(&self, verts: &[RefCell<Vertex>], air_density: f64, wind: Vector) {
+        let mut vertex_a = verts[self.index_a].borrow_mut();
+        let mut vertex_b = verts[self.index_b].borrow_mut();
+
+        let delta = vertex_b.position - vertex_a.position;
+        let length = delta.norm();
+        if length == 0.0 {
+            return;
+        }
+
+        let normal = Vector::new(-delta.y, delta.x) / length;
+        let tangent = delta / length;
+
+        // The velocity of the air as seen from the surface
+        let air_velocity = wind - (vertex_a.velocity + vertex_b.velocity) / 2.0;
+        let normal_velocity = air_velocity.dot(&normal);
+        let tangent_velocity = air_velocity.dot(&tangent);
+
+        let pressure = 0.5 * air_density * length;
+
+        // The drag pushes the surface along with the air hitting it
+        let drag = normal * pressure * self.drag_coefficient as f64 * normal_velocity.abs()
+            * normal_velocity;
+
+        // The lift is perpendicular to the air flow, on the side the air pushes to
+        let mut lift_direction = Vector::new(-air_velocity.y, air_velocity.x);
+        if lift_direction.norm() > 0.0 {
+            lift_direction = lift_direction.normalize();
+        }
+        if lift_direction.dot(&normal) * normal_velocity < 0.0 {
+            lift_direction = -lift_direction;
+        }
+        let lift = lift_direction * pressure * self.lift_coefficient as f64
+            * (normal_velocity * tangent_velocity).abs();
+
+        // Half of the force goes to each end of the surface
+        let force = (drag + lift) / 2.0;
+        vertex_a.apply_force(force);
+        vertex_b.apply_force(force);
+    }
 }
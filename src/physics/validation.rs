@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use physics::simulation::Vertex;
+use physics::surface::Surface;
+
+/// A broken invariant of the world
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// The surface points to a vertex which doesn't exist
+    SurfaceOutOfRange { surface: usize, vertex: usize },
+    /// The surface connects a vertex to itself
+    SelfSurface(usize),
+    /// The second surface connects the same vertices as the first
+    DuplicateSurface { first: usize, second: usize },
+    /// The surface rest length is zero, negative or not a number
+    BadTargetDistance(usize),
+    /// The vertex mass is zero, negative or not a number
+    BadMass(usize),
+    /// The vertex position or velocity is NaN or infinite
+    NonFinite(usize),
+    /// The body of the vertex isn't the smallest index it's connected to
+    WrongBody {
+        vertex: usize,
+        body: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::SurfaceOutOfRange { surface, vertex } => {
+                write!(f, "surface {} uses missing vertex {}", surface, vertex)
+            }
+            Violation::SelfSurface(surface) => {
+                write!(f, "surface {} connects a vertex to itself", surface)
+            }
+            Violation::DuplicateSurface { first, second } => {
+                write!(f, "surface {} duplicates surface {}", second, first)
+            }
+            Violation::BadTargetDistance(surface) => {
+                write!(f, "surface {} has an invalid target distance", surface)
+            }
+            Violation::BadMass(vertex) => write!(f, "vertex {} has an invalid mass", vertex),
+            Violation::NonFinite(vertex) => {
+                write!(f, "vertex {} has a non finite position or velocity", vertex)
+            }
+            Violation::WrongBody {
+                vertex,
+                body,
+                expected,
+            } => write!(
+                f,
+                "vertex {} is in body {} instead of {}",
+                vertex, body, expected
+            ),
+        }
+    }
+}
+
+/// Everything wrong with a world
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return write!(f, "The world is valid");
+        }
+
+        write!(f, "{} problems found", self.violations.len())?;
+        for violation in &self.violations {
+            write!(f, "\n{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every invariant the simulation relies on
+pub fn validate(verts: &[RefCell<Vertex>], surfaces: &[Surface]) -> ValidationReport {
+    let mut violations = Vec::new();
+
+    for (index, vertex) in verts.iter().enumerate() {
+        let vertex = vertex.borrow();
+        if !vertex.mass.is_finite() || vertex.mass <= 0.0 {
+            violations.push(Violation::BadMass(index));
+        }
+        if !vertex.position.x.is_finite() || !vertex.position.y.is_finite()
+            || !vertex.velocity.x.is_finite() || !vertex.velocity.y.is_finite()
+        {
+            violations.push(Violation::NonFinite(index));
+        }
+    }
+
+    // The first surface found between each pair of vertices
+    let mut pairs = BTreeMap::new();
+    for (index, surface) in surfaces.iter().enumerate() {
+        for &vertex in &[surface.index_a, surface.index_b] {
+            if vertex >= verts.len() {
+                violations.push(Violation::SurfaceOutOfRange {
+                    surface: index,
+                    vertex,
+                });
+            }
+        }
+
+        if surface.index_a == surface.index_b {
+            violations.push(Violation::SelfSurface(index));
+        }
+
+        let pair = (
+            usize::min(surface.index_a, surface.index_b),
+            usize::max(surface.index_a, surface.index_b),
+        );
+        if let Some(&first) = pairs.get(&pair) {
+            violations.push(Violation::DuplicateSurface {
+                first,
+                second: index,
+            });
+        } else {
+            pairs.insert(pair, index);
+        }
+
+        if !surface.target_distance.is_finite() || surface.target_distance <= 0.0 {
+            violations.push(Violation::BadTargetDistance(index));
+        }
+    }
+
+    // Find the bodies again, independently from World::update_bodies
+    let mut parents: Vec<usize> = (0..verts.len()).collect();
+    fn root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+    for surface in surfaces {
+        if surface.index_a < verts.len() && surface.index_b < verts.len() {
+            let root_a = root(&mut parents, surface.index_a);
+            let root_b = root(&mut parents, surface.index_b);
+            // Keep the smallest index as the root, so it's the body id
+            parents[usize::max(root_a, root_b)] = usize::min(root_a, root_b);
+        }
+    }
+    for (index, vertex) in verts.iter().enumerate() {
+        let body = vertex.borrow().body;
+        let expected = root(&mut parents, index);
+        if body != expected {
+            violations.push(Violation::WrongBody {
+                vertex: index,
+                body,
+                expected,
+            });
+        }
+    }
+
+    ValidationReport { violations }
+}
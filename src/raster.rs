@@ -0,0 +1,374 @@
+//! Draws the world on the CPU, the same way the viewer draws it with OpenGL,
+//! so that frames can be rendered headlessly and saved as PNG or GIF
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use gif;
+use png;
+
+use physics::regions::RegionShape;
+use physics::simulation::World;
+use scene::ViewSettings;
+use Vector;
+
+pub type Color = [f32; 4];
+
+pub const BACKGROUND_COLOR: Color = [1.0; 4];
+pub const REGION_COLOR: Color = [0.2, 0.4, 1.0, 0.25];
+pub const SURFACE_COLOR: Color = [1.0, 0.0, 0.0, 1.0];
+pub const VERTEX_COLOR: Color = [0.0, 0.0, 1.0, 1.0];
+/// The surfaces and vertices involved in the last blow-up
+pub const BROKEN_SURFACE_COLOR: Color = [1.0, 0.6, 0.0, 1.0];
+pub const BROKEN_VERTEX_COLOR: Color = [1.0, 0.0, 1.0, 1.0];
+pub const PARTICLE_COLOR: Color = [0.2, 0.5, 1.0, 0.8];
+pub const DEBUG_COLOR: Color = [0.0, 1.0, 1.0, 1.0];
+
+/// An RGBA image, 8 bits per channel, rows from the top
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        let color = [
+            to_byte(color[0]),
+            to_byte(color[1]),
+            to_byte(color[2]),
+            to_byte(color[3]),
+        ];
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Paints over a pixel, `coverage` is the fraction of the pixel covered by the shape
+    fn blend(&mut self, x: i64, y: i64, color: Color, coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+
+        let alpha = color[3] * coverage;
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        let pixel = &mut self.pixels[index..index + 4];
+        for i in 0..3 {
+            let old = pixel[i] as f32 / 255.0;
+            pixel[i] = to_byte(color[i] * alpha + old * (1.0 - alpha));
+        }
+        let old = pixel[3] as f32 / 255.0;
+        pixel[3] = to_byte(alpha + old * (1.0 - alpha));
+    }
+
+    /// Fills the pixels with their center inside the rectangle
+    pub fn fill_rect(&mut self, min: Vector, max: Vector, color: Color) {
+        let (x0, x1) = self.columns(min.x, max.x);
+        let (y0, y1) = self.rows(min.y, max.y);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    /// Fills the pixels with their center inside the polygon, with the even-odd rule
+    pub fn fill_polygon(&mut self, points: &[Vector], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points
+            .iter()
+            .map(|point| point.y)
+            .fold(::std::f64::INFINITY, f64::min);
+        let max_y = points
+            .iter()
+            .map(|point| point.y)
+            .fold(::std::f64::NEG_INFINITY, f64::max);
+        let (y0, y1) = self.rows(min_y, max_y);
+
+        let mut crossings = Vec::new();
+        for y in y0..y1 {
+            let center = y as f64 + 0.5;
+
+            crossings.clear();
+            for i in 0..points.len() {
+                let a = points[i];
+                let b = points[(i + 1) % points.len()];
+                if (a.y <= center) != (b.y <= center) {
+                    crossings.push(a.x + (center - a.y) / (b.y - a.y) * (b.x - a.x));
+                }
+            }
+            crossings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+            for pair in crossings.chunks(2) {
+                if pair.len() == 2 {
+                    let (x0, x1) = self.columns(pair[0], pair[1]);
+                    for x in x0..x1 {
+                        self.blend(x, y, color, 1.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws an anti-aliased segment with round ends, `radius` being half of its width
+    pub fn draw_capsule(&mut self, a: Vector, b: Vector, radius: f64, color: Color) {
+        let reach = radius + 1.0;
+        let (x0, x1) = self.columns(a.x.min(b.x) - reach, a.x.max(b.x) + reach);
+        let (y0, y1) = self.rows(a.y.min(b.y) - reach, a.y.max(b.y) + reach);
+
+        let delta = b - a;
+        let length_squared = delta.norm_squared();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let point = Vector::new(x as f64 + 0.5, y as f64 + 0.5);
+                let t = if length_squared > 0.0 {
+                    ((point - a).dot(&delta) / length_squared).max(0.0).min(1.0)
+                } else {
+                    0.0
+                };
+                let distance = (point - (a + delta * t)).norm();
+
+                let coverage = (radius + 0.5 - distance).max(0.0).min(1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage as f32);
+                }
+            }
+        }
+    }
+
+    pub fn fill_circle(&mut self, center: Vector, radius: f64, color: Color) {
+        self.draw_capsule(center, center, radius, color);
+    }
+
+    /// The range of columns with their center between two x coordinates
+    fn columns(&self, min: f64, max: f64) -> (i64, i64) {
+        let start = (min - 0.5).ceil().max(0.0);
+        let end = (max - 0.5).ceil().min(self.width as f64);
+        (start as i64, end as i64)
+    }
+
+    fn rows(&self, min: f64, max: f64) -> (i64, i64) {
+        let start = (min - 0.5).ceil().max(0.0);
+        let end = (max - 0.5).ceil().min(self.height as f64);
+        (start as i64, end as i64)
+    }
+
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        // Both crates have a `set` method for their parameters, they are imported where used
+        use png::HasParameters;
+
+        let mut encoder =
+            png::Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// How the world is looked at, the same as in the viewer
+#[derive(Clone)]
+pub struct Camera {
+    pub width: u32,
+    pub height: u32,
+    /// Pixels per meter
+    pub scale: f64,
+    /// The point of the world at the center of the image
+    pub offset: Vector,
+    /// The radius of the vertices, in meters
+    pub vertex_scale: f64,
+}
+
+impl Camera {
+    pub fn new(width: u32, height: u32) -> Camera {
+        Camera::from_settings(&ViewSettings::default(), width, height)
+    }
+
+    /// Looks at the world like the viewer with these settings
+    pub fn from_settings(settings: &ViewSettings, width: u32, height: u32) -> Camera {
+        Camera {
+            width,
+            height,
+            scale: settings.scale,
+            offset: settings.offset,
+            vertex_scale: settings.vertex_scale,
+        }
+    }
+
+    pub fn to_screen_point(&self, point: &Vector) -> Vector {
+        Vector::new(
+            (point.x - self.offset.x) * self.scale + self.width as f64 / 2.0,
+            -(point.y - self.offset.y) * self.scale + self.height as f64 / 2.0,
+        )
+    }
+}
+
+/// Draws the regions, surfaces, vertices, fluid particles and debug vectors of the world
+pub fn render(world: &World, camera: &Camera) -> Canvas {
+    let mut canvas = Canvas::new(camera.width, camera.height);
+    canvas.clear(BACKGROUND_COLOR);
+
+    for region in &world.regions {
+        match region.shape {
+            RegionShape::Level(height) => {
+                let top = camera.to_screen_point(&Vector::new(0.0, height)).y;
+                let bottom_right = Vector::new(camera.width as f64, camera.height as f64);
+                canvas.fill_rect(Vector::new(0.0, top), bottom_right, REGION_COLOR);
+            }
+            RegionShape::Polygon(ref points) => {
+                let points: Vec<Vector> = points
+                    .iter()
+                    .map(|point| camera.to_screen_point(point))
+                    .collect();
+                canvas.fill_polygon(&points, REGION_COLOR);
+            }
+        }
+    }
+
+    for (i, surface) in world.surfaces.iter().enumerate() {
+        let a = camera.to_screen_point(&world.verts[surface.index_a].borrow().position);
+        let b = camera.to_screen_point(&world.verts[surface.index_b].borrow().position);
+
+        let broken = world
+            .health
+            .report
+            .as_ref()
+            .map_or(false, |report| report.surfaces.contains(&i));
+        let color = if broken {
+            BROKEN_SURFACE_COLOR
+        } else {
+            SURFACE_COLOR
+        };
+
+        let radius = (surface.thickness / 2.0 * camera.scale).max(0.5);
+        canvas.draw_capsule(a, b, radius, color);
+    }
+
+    for (i, vertex) in world.verts.iter().enumerate() {
+        let broken = world.health.report.as_ref().map_or(false, |report| {
+            report.broken_vertices.contains(&i) || report.fast_vertices.contains(&i)
+        });
+        let color = if broken {
+            BROKEN_VERTEX_COLOR
+        } else {
+            VERTEX_COLOR
+        };
+
+        let position = camera.to_screen_point(&vertex.borrow().position);
+        canvas.fill_circle(position, camera.vertex_scale * camera.scale, color);
+    }
+
+    let particle_radius = world.fluid.spacing() / 2.0 * camera.scale;
+    for particle in &world.fluid.particles {
+        let position = camera.to_screen_point(&particle.position);
+        canvas.fill_circle(position, particle_radius, PARTICLE_COLOR);
+    }
+
+    for &(start, vector) in &world.debug.vectors {
+        let a = camera.to_screen_point(&start);
+        let b = camera.to_screen_point(&(start + vector));
+        canvas.draw_capsule(a, b, 1.0, DEBUG_COLOR);
+    }
+
+    canvas
+}
+
+/// The levels of each channel in the palette of the GIF frames
+const PALETTE_LEVELS: u32 = 6;
+
+/// Writes canvases as the frames of an animated GIF
+/// The frames share a fixed palette, so that writing them is fast and the colors don't flicker
+pub struct GifWriter {
+    pub width: u32,
+    pub height: u32,
+    /// The time each frame is shown, in hundredths of a second
+    pub delay: u16,
+    pub frames: u64,
+    encoder: gif::Encoder<BufWriter<File>>,
+}
+
+impl GifWriter {
+    pub fn create(path: &Path, width: u32, height: u32, delay: u16) -> io::Result<GifWriter> {
+        use gif::SetParameter;
+
+        if width > u16::max_value() as u32 || height > u16::max_value() as u32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the GIF is too large",
+            ));
+        }
+
+        let mut palette = Vec::new();
+        for r in 0..PALETTE_LEVELS {
+            for g in 0..PALETTE_LEVELS {
+                for b in 0..PALETTE_LEVELS {
+                    for &level in &[r, g, b] {
+                        palette.push((level * 255 / (PALETTE_LEVELS - 1)) as u8);
+                    }
+                }
+            }
+        }
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &palette)?;
+        encoder.set(gif::Repeat::Infinite)?;
+
+        Ok(GifWriter {
+            width,
+            height,
+            delay,
+            frames: 0,
+            encoder,
+        })
+    }
+
+    pub fn add_frame(&mut self, canvas: &Canvas) -> io::Result<()> {
+        if canvas.width != self.width || canvas.height != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the frame has a different size",
+            ));
+        }
+
+        let level = |value: u8| (value as u32 * (PALETTE_LEVELS - 1) + 127) / 255;
+        let indices: Vec<u8> = canvas
+            .pixels
+            .chunks(4)
+            .map(|pixel| {
+                let index = (level(pixel[0]) * PALETTE_LEVELS + level(pixel[1])) * PALETTE_LEVELS
+                    + level(pixel[2]);
+                index as u8
+            })
+            .collect();
+
+        let frame = gif::Frame {
+            width: self.width as u16,
+            height: self.height as u16,
+            delay: self.delay,
+            buffer: Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        self.encoder.write_frame(&frame)?;
+
+        self.frames += 1;
+        Ok(())
+    }
+}
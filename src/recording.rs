@@ -0,0 +1,336 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use binary::*;
+use compact::{self, Precision};
+use physics::determinism::Rng;
+use physics::fluid::Particle;
+use physics::simulation::World;
+use physics::snapshot::Snapshot;
+use Vector;
+
+const MAGIC: &[u8; 4] = b"SPRC";
+const VERSION: u64 = 2;
+
+// The kinds of records after the header
+const TOPOLOGY: u8 = 0;
+const KEYFRAME: u8 = 1;
+const DELTA: u8 = 2;
+
+/// The recent states of the world, used to scrub back in time and to play recordings
+pub struct History {
+    /// The maximum number of snapshots kept, 0 keeps all of them
+    pub capacity: usize,
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> History {
+        History {
+            capacity,
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Adds a snapshot, forgetting the oldest one if the history is full
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity != 0 && self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Snapshot> {
+        self.snapshots.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Forgets the snapshots after `len`, like when resuming from the past
+    pub fn truncate(&mut self, len: usize) {
+        self.snapshots.truncate(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+/// The parts of the world which change only when it's edited:
+/// the vertices without their position and velocity, the surfaces and the fluid regions
+struct Topology {
+    /// A snapshot with this topology, the frames fill in the vertices and particles
+    snapshot: Snapshot,
+}
+
+impl Topology {
+    /// Encodes the topology of a world, two worlds with the same bytes have the same topology
+    fn encode(world: &World) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        write_usize(&mut bytes, world.verts.len())?;
+        for vertex in &world.verts {
+            let mut vertex = vertex.borrow().clone();
+            vertex.position = Vector::new(0.0, 0.0);
+            vertex.velocity = Vector::new(0.0, 0.0);
+            vertex.body = 0;
+            compact::write_vertex(&mut bytes, &vertex, Precision::Double)?;
+        }
+
+        write_usize(&mut bytes, world.surfaces.len())?;
+        for surface in &world.surfaces {
+            compact::write_surface(&mut bytes, surface)?;
+        }
+
+        write_usize(&mut bytes, world.regions.len())?;
+        for region in &world.regions {
+            compact::write_region(&mut bytes, region)?;
+        }
+        Ok(bytes)
+    }
+
+    fn read<R: Read>(reader: &mut R) -> io::Result<Topology> {
+        let mut world = World::new();
+        for _ in 0..read_usize(reader)? {
+            let vertex = compact::read_vertex(reader, Precision::Double)?;
+            world.verts.push(RefCell::new(vertex));
+        }
+        for _ in 0..read_usize(reader)? {
+            let surface = compact::read_surface(reader, world.verts.len())?;
+            world.surfaces.push(surface);
+        }
+        for _ in 0..read_usize(reader)? {
+            world.regions.push(compact::read_region(reader)?);
+        }
+        // Built without the checks of the editing functions,
+        // the recorded surfaces may have their vertices in the same spot
+        world.update_bodies();
+
+        Ok(Topology {
+            snapshot: world.snapshot(),
+        })
+    }
+
+    /// A snapshot of a world with this topology and these quantized vertex and particle values,
+    /// the contact impulses are not recorded
+    fn snapshot(&self, values: &[[i64; 4]], precision: f64) -> Snapshot {
+        let dequantize = |x: i64, y: i64| Vector::new(x as f64 * precision, y as f64 * precision);
+
+        let mut snapshot = self.snapshot.clone();
+        for (vertex, current) in snapshot.verts.iter_mut().zip(values) {
+            vertex.position = dequantize(current[0], current[1]);
+            vertex.velocity = dequantize(current[2], current[3]);
+        }
+        snapshot.particles = values[self.snapshot.verts.len()..]
+            .iter()
+            .map(|current| {
+                let mut particle = Particle::new(dequantize(current[0], current[1]));
+                particle.velocity = dequantize(current[2], current[3]);
+                particle
+            })
+            .collect();
+        snapshot
+    }
+}
+
+/// Writes the positions and velocities of the vertices and fluid particles of every step to a file
+/// Each step is stored as the difference from the previous one, rounded to `precision`,
+/// with a full keyframe every `keyframe_interval` steps and whenever the topology
+/// or the number of particles changes
+pub struct Recorder {
+    pub precision: f64,
+    pub keyframe_interval: u64,
+    /// The number of steps written so far
+    pub frames: u64,
+
+    writer: BufWriter<File>,
+    /// The encoded topology of the last step
+    topology: Option<Vec<u8>>,
+    last_values: Vec<[i64; 4]>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Recorder> {
+        Recorder::with_precision(path, 1e-4)
+    }
+
+    pub fn with_precision(path: &Path, precision: f64) -> io::Result<Recorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        write_varint(&mut writer, VERSION)?;
+        write_f64(&mut writer, precision)?;
+
+        Ok(Recorder {
+            precision,
+            keyframe_interval: 120,
+            frames: 0,
+            writer,
+            topology: None,
+            last_values: Vec::new(),
+        })
+    }
+
+    /// Appends the current state of the world
+    pub fn record(&mut self, world: &World) -> io::Result<()> {
+        let topology = Topology::encode(world)?;
+        let changed = self.topology.as_ref() != Some(&topology);
+        if changed {
+            write_u8(&mut self.writer, TOPOLOGY)?;
+            self.writer.write_all(&topology)?;
+            self.topology = Some(topology);
+        }
+
+        let precision = self.precision;
+        let quantize = |value: f64| (value / precision).round() as i64;
+        let quantize_all = |position: Vector, velocity: Vector| {
+            [
+                quantize(position.x),
+                quantize(position.y),
+                quantize(velocity.x),
+                quantize(velocity.y),
+            ]
+        };
+        let values: Vec<[i64; 4]> = world
+            .verts
+            .iter()
+            .map(|vertex| {
+                let vertex = vertex.borrow();
+                quantize_all(vertex.position, vertex.velocity)
+            })
+            .chain(
+                world
+                    .fluid
+                    .particles
+                    .iter()
+                    .map(|particle| quantize_all(particle.position, particle.velocity)),
+            )
+            .collect();
+
+        let deltas = if changed || self.frames % u64::max(self.keyframe_interval, 1) == 0 {
+            None
+        } else {
+            deltas(&values, &self.last_values)
+        };
+        let kind = if deltas.is_none() { KEYFRAME } else { DELTA };
+        write_u8(&mut self.writer, kind)?;
+        write_varint(&mut self.writer, world.step)?;
+        write_f64(&mut self.writer, world.time)?;
+        write_u64(&mut self.writer, world.rng.state())?;
+        write_usize(&mut self.writer, world.fluid.particles.len())?;
+        for current in deltas.as_ref().unwrap_or(&values) {
+            for &value in current {
+                write_signed(&mut self.writer, value)?;
+            }
+        }
+
+        self.last_values = values;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Writes out everything still buffered
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// The change of each value since the last frame, none if there are new values
+/// or a change too large to be stored, like from a vertex which blew up
+fn deltas(values: &[[i64; 4]], last_values: &[[i64; 4]]) -> Option<Vec<[i64; 4]>> {
+    if values.len() != last_values.len() {
+        return None;
+    }
+    let mut deltas = Vec::with_capacity(values.len());
+    for (current, last) in values.iter().zip(last_values) {
+        let mut delta = [0; 4];
+        for i in 0..4 {
+            delta[i] = current[i].checked_sub(last[i])?;
+        }
+        deltas.push(delta);
+    }
+    Some(deltas)
+}
+
+/// Reads a recording into a history of snapshots which can be played back
+pub fn load(path: &Path) -> io::Result<History> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a recording"));
+    }
+    if read_varint(&mut reader)? != VERSION {
+        return Err(invalid_data("unsupported recording version"));
+    }
+    let precision = read_f64(&mut reader)?;
+    if !(precision > 0.0 && precision.is_finite()) {
+        return Err(invalid_data("invalid precision"));
+    }
+
+    let mut history = History::new(0);
+    let mut topology: Option<Topology> = None;
+    let mut values: Vec<[i64; 4]> = Vec::new();
+    loop {
+        let kind = match read_u8(&mut reader) {
+            Ok(kind) => kind,
+            Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(error) => return Err(error),
+        };
+
+        match kind {
+            TOPOLOGY => topology = Some(Topology::read(&mut reader)?),
+            KEYFRAME | DELTA => {
+                let topology = match topology {
+                    Some(ref topology) => topology,
+                    None => return Err(invalid_data("frame before the topology")),
+                };
+                let step = read_varint(&mut reader)?;
+                let time = read_f64(&mut reader)?;
+                let rng = read_u64(&mut reader)?;
+                let particles = read_usize(&mut reader)?;
+
+                let count = topology.snapshot.verts.len() + particles;
+                if kind == KEYFRAME {
+                    values.clear();
+                    for _ in 0..count {
+                        let mut current = [0; 4];
+                        for value in current.iter_mut() {
+                            *value = read_signed(&mut reader)?;
+                        }
+                        values.push(current);
+                    }
+                } else {
+                    if values.len() != count {
+                        return Err(invalid_data("delta frame without a keyframe"));
+                    }
+                    for current in values.iter_mut() {
+                        for value in current.iter_mut() {
+                            *value = value
+                                .checked_add(read_signed(&mut reader)?)
+                                .ok_or_else(|| invalid_data("delta out of range"))?;
+                        }
+                    }
+                }
+
+                let mut snapshot = topology.snapshot(&values, precision);
+                snapshot.rng = Rng::new(rng);
+                snapshot.step = step;
+                snapshot.time = time;
+                history.push(snapshot);
+            }
+            _ => return Err(invalid_data("unknown record")),
+        }
+    }
+
+    Ok(history)
+}
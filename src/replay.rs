@@ -0,0 +1,726 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use physics::determinism::Rng;
+use physics::error::WorldError;
+use physics::health::HealthPolicy;
+use physics::material::CombineRule;
+use physics::regions::{FluidRegion, RegionShape};
+use physics::simulation::{Vertex, World};
+use Vector;
+
+/// The names of the parameters of the world itself
+pub const WORLD_PARAMETERS: &[&str] = &[
+    "fixed_dt",
+    "air_density",
+    "wind_x",
+    "wind_y",
+    "adaptive_steps",
+    "min_substeps",
+    "max_substeps",
+    "courant",
+    "oscillation",
+    "restitution_threshold",
+    "warm_starting",
+    "friction_rule",
+    "restitution_rule",
+    "coulomb",
+    "coulomb_constant",
+    "gravity",
+    "gravitational_constant",
+    "lennard_jones",
+    "cohesion_distance",
+    "theta",
+    "softening",
+    "smoothing_radius",
+    "particle_mass",
+    "rest_density",
+    "fluid_stiffness",
+    "fluid_viscosity",
+    "fluid_friction",
+    "fluid_restitution",
+    "health",
+    "health_policy",
+    "max_speed",
+];
+
+const VERTEX_PARAMETERS: &[&str] = &[
+    "mass",
+    "static",
+    "static_friction",
+    "dynamic_friction",
+    "restitution",
+    "charge",
+    "cohesion",
+];
+
+const SURFACE_PARAMETERS: &[&str] = &[
+    "damping_ratio",
+    "strength",
+    "target_distance",
+    "thickness",
+    "static_friction",
+    "dynamic_friction",
+    "restitution",
+    "drag_coefficient",
+    "lift_coefficient",
+];
+
+const REGION_PARAMETERS: &[&str] = &["level", "density", "linear_drag", "quadratic_drag"];
+
+/// A number the user can change, of the world or of one of its parts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Parameter {
+    World(&'static str),
+    Vertex(usize, &'static str),
+    Surface(usize, &'static str),
+    Region(usize, &'static str),
+}
+
+/// Something done to the world between two steps
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Sets the step and time the replay starts from
+    Start(f64),
+    /// Restarts the random number generator from a state
+    Seed(u64),
+    /// The physics iterations and whether collisions are enabled
+    Settings(u32, bool),
+    AddVertex(Vector),
+    RemoveVertex(usize),
+    CreateSurface(usize, usize),
+    RemoveSurface(usize),
+    SetVelocity(usize, Vector),
+    AddParticle(Vector, Vector),
+    EmitFluid(Vector, f64),
+    ClearFluid,
+    AddLevel(f64),
+    AddRegion(Vec<Vector>),
+    RemoveRegion(usize),
+    /// Applies a force to a vertex
+    Pull(usize, Vector),
+    /// Moves a vertex, like when it is moved with the simulation paused
+    Drag(usize, Vector),
+    Set(Parameter, f64),
+    /// The last step of the replay
+    End,
+}
+
+fn bool_value(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn rule_value(rule: CombineRule) -> f64 {
+    CombineRule::ALL
+        .iter()
+        .position(|&other| other == rule)
+        .unwrap_or(0) as f64
+}
+
+fn policy_value(policy: HealthPolicy) -> f64 {
+    HealthPolicy::ALL
+        .iter()
+        .position(|&other| other == policy)
+        .unwrap_or(0) as f64
+}
+
+impl Parameter {
+    /// The world parameter with this name, if there is one
+    pub fn world(name: &str) -> Option<Parameter> {
+        WORLD_PARAMETERS
+            .iter()
+            .find(|&&other| other == name)
+            .map(|&name| Parameter::World(name))
+    }
+
+    /// The value of the parameter, None if the part of the world it belongs to doesn't exist
+    pub fn get(&self, world: &World) -> Option<f64> {
+        match *self {
+            Parameter::World(name) => Some(match name {
+                "fixed_dt" => world.fixed_dt,
+                "air_density" => world.air_density,
+                "wind_x" => world.wind.x,
+                "wind_y" => world.wind.y,
+                "adaptive_steps" => bool_value(world.adaptive_steps.enabled),
+                "min_substeps" => world.adaptive_steps.min as f64,
+                "max_substeps" => world.adaptive_steps.max as f64,
+                "courant" => world.adaptive_steps.courant,
+                "oscillation" => world.adaptive_steps.oscillation,
+                "restitution_threshold" => world.solver.restitution_threshold,
+                "warm_starting" => bool_value(world.solver.warm_starting),
+                "friction_rule" => rule_value(world.solver.friction_rule),
+                "restitution_rule" => rule_value(world.solver.restitution_rule),
+                "coulomb" => bool_value(world.interactions.coulomb),
+                "coulomb_constant" => world.interactions.coulomb_constant,
+                "gravity" => bool_value(world.interactions.gravity),
+                "gravitational_constant" => world.interactions.gravitational_constant,
+                "lennard_jones" => bool_value(world.interactions.lennard_jones),
+                "cohesion_distance" => world.interactions.cohesion_distance,
+                "theta" => world.interactions.theta,
+                "softening" => world.interactions.softening,
+                "smoothing_radius" => world.fluid.smoothing_radius,
+                "particle_mass" => world.fluid.particle_mass,
+                "rest_density" => world.fluid.rest_density,
+                "fluid_stiffness" => world.fluid.stiffness,
+                "fluid_viscosity" => world.fluid.viscosity,
+                "fluid_friction" => world.fluid.friction as f64,
+                "fluid_restitution" => world.fluid.restitution as f64,
+                "health" => bool_value(world.health.enabled),
+                "health_policy" => policy_value(world.health.policy),
+                "max_speed" => world.health.max_speed,
+                _ => return None,
+            }),
+            Parameter::Vertex(index, name) => {
+                let vertex = world.verts.get(index)?.borrow();
+                Some(match name {
+                    "mass" => vertex.mass as f64,
+                    "static" => bool_value(vertex.is_static),
+                    "static_friction" => vertex.static_friction as f64,
+                    "dynamic_friction" => vertex.dynamic_friction as f64,
+                    "restitution" => vertex.restitution as f64,
+                    "charge" => vertex.charge as f64,
+                    "cohesion" => vertex.cohesion as f64,
+                    _ => return None,
+                })
+            }
+            Parameter::Surface(index, name) => {
+                let surface = world.surfaces.get(index)?;
+                Some(match name {
+                    "damping_ratio" => surface.damping_ratio as f64,
+                    "strength" => surface.strength as f64,
+                    "target_distance" => surface.target_distance,
+                    "thickness" => surface.thickness,
+                    "static_friction" => surface.static_friction as f64,
+                    "dynamic_friction" => surface.dynamic_friction as f64,
+                    "restitution" => surface.restitution as f64,
+                    "drag_coefficient" => surface.drag_coefficient as f64,
+                    "lift_coefficient" => surface.lift_coefficient as f64,
+                    _ => return None,
+                })
+            }
+            Parameter::Region(index, name) => {
+                let region = world.regions.get(index)?;
+                Some(match (name, &region.shape) {
+                    ("level", &RegionShape::Level(height)) => height,
+                    ("density", _) => region.density,
+                    ("linear_drag", _) => region.linear_drag,
+                    ("quadratic_drag", _) => region.quadratic_drag,
+                    _ => return None,
+                })
+            }
+        }
+    }
+
+    pub fn set(&self, world: &mut World, value: f64) -> Result<(), WorldError> {
+        let rule = || CombineRule::ALL[usize::min(value as usize, CombineRule::ALL.len() - 1)];
+        let policy = || HealthPolicy::ALL[usize::min(value as usize, HealthPolicy::ALL.len() - 1)];
+
+        match *self {
+            Parameter::World(name) => match name {
+                "fixed_dt" => world.fixed_dt = value,
+                "air_density" => world.air_density = value,
+                "wind_x" => world.wind.x = value,
+                "wind_y" => world.wind.y = value,
+                "adaptive_steps" => world.adaptive_steps.enabled = value != 0.0,
+                "min_substeps" => world.adaptive_steps.min = value as u32,
+                "max_substeps" => world.adaptive_steps.max = value as u32,
+                "courant" => world.adaptive_steps.courant = value,
+                "oscillation" => world.adaptive_steps.oscillation = value,
+                "restitution_threshold" => world.solver.restitution_threshold = value,
+                "warm_starting" => world.solver.warm_starting = value != 0.0,
+                "friction_rule" => world.solver.friction_rule = rule(),
+                "restitution_rule" => world.solver.restitution_rule = rule(),
+                "coulomb" => world.interactions.coulomb = value != 0.0,
+                "coulomb_constant" => world.interactions.coulomb_constant = value,
+                "gravity" => world.interactions.gravity = value != 0.0,
+                "gravitational_constant" => world.interactions.gravitational_constant = value,
+                "lennard_jones" => world.interactions.lennard_jones = value != 0.0,
+                "cohesion_distance" => world.interactions.cohesion_distance = value,
+                "theta" => world.interactions.theta = value,
+                "softening" => world.interactions.softening = value,
+                "smoothing_radius" => world.fluid.smoothing_radius = value,
+                "particle_mass" => world.fluid.particle_mass = value,
+                "rest_density" => world.fluid.rest_density = value,
+                "fluid_stiffness" => world.fluid.stiffness = value,
+                "fluid_viscosity" => world.fluid.viscosity = value,
+                "fluid_friction" => world.fluid.friction = value as f32,
+                "fluid_restitution" => world.fluid.restitution = value as f32,
+                "health" => world.health.enabled = value != 0.0,
+                "health_policy" => world.health.policy = policy(),
+                "max_speed" => world.health.max_speed = value,
+                _ => {}
+            },
+            Parameter::Vertex(index, name) => {
+                let mut vertex = world
+                    .verts
+                    .get(index)
+                    .ok_or(WorldError::UnknownVertex(index))?
+                    .borrow_mut();
+                match name {
+                    "mass" => vertex.mass = value as f32,
+                    "static" => vertex.is_static = value != 0.0,
+                    "static_friction" => vertex.static_friction = value as f32,
+                    "dynamic_friction" => vertex.dynamic_friction = value as f32,
+                    "restitution" => vertex.restitution = value as f32,
+                    "charge" => vertex.charge = value as f32,
+                    "cohesion" => vertex.cohesion = value as f32,
+                    _ => {}
+                }
+            }
+            Parameter::Surface(index, name) => {
+                let surface = world
+                    .surfaces
+                    .get_mut(index)
+                    .ok_or(WorldError::UnknownSurface(index))?;
+                match name {
+                    "damping_ratio" => surface.damping_ratio = value as f32,
+                    "strength" => surface.strength = value as f32,
+                    "target_distance" => surface.target_distance = value,
+                    "thickness" => surface.thickness = value,
+                    "static_friction" => surface.static_friction = value as f32,
+                    "dynamic_friction" => surface.dynamic_friction = value as f32,
+                    "restitution" => surface.restitution = value as f32,
+                    "drag_coefficient" => surface.drag_coefficient = value as f32,
+                    "lift_coefficient" => surface.lift_coefficient = value as f32,
+                    _ => {}
+                }
+            }
+            Parameter::Region(index, name) => {
+                // Regions have no error of their own, a missing one is just skipped
+                if let Some(region) = world.regions.get_mut(index) {
+                    match name {
+                        "level" => if let RegionShape::Level(ref mut height) = region.shape {
+                            *height = value;
+                        },
+                        "density" => region.density = value,
+                        "linear_drag" => region.linear_drag = value,
+                        "quadratic_drag" => region.quadratic_drag = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Every parameter of the world with its value
+fn parameters(world: &World) -> BTreeMap<Parameter, f64> {
+    let mut parameters = Vec::new();
+    for &name in WORLD_PARAMETERS {
+        parameters.push(Parameter::World(name));
+    }
+    for index in 0..world.verts.len() {
+        for &name in VERTEX_PARAMETERS {
+            parameters.push(Parameter::Vertex(index, name));
+        }
+    }
+    for index in 0..world.surfaces.len() {
+        for &name in SURFACE_PARAMETERS {
+            parameters.push(Parameter::Surface(index, name));
+        }
+    }
+    for index in 0..world.regions.len() {
+        for &name in REGION_PARAMETERS {
+            parameters.push(Parameter::Region(index, name));
+        }
+    }
+
+    parameters
+        .into_iter()
+        .filter_map(|parameter| parameter.get(world).map(|value| (parameter, value)))
+        .collect()
+}
+
+impl Action {
+    /// Does the action to the world, `Start`, `Settings` and `End` are left to the replay
+    pub fn apply(&self, world: &mut World) -> Result<(), WorldError> {
+        match *self {
+            Action::Start(_) | Action::Settings(_, _) | Action::End => {}
+            Action::Seed(state) => world.rng = Rng::new(state),
+            Action::AddVertex(position) => world.add_vertex(Vertex::new(position)),
+            Action::RemoveVertex(index) => world.remove_vertex(index)?,
+            Action::CreateSurface(index_a, index_b) => {
+                world.create_surface(index_a, index_b)?;
+            }
+            Action::RemoveSurface(index) => world.remove_surface(index)?,
+            Action::SetVelocity(index, velocity) => {
+                world
+                    .verts
+                    .get(index)
+                    .ok_or(WorldError::UnknownVertex(index))?
+                    .borrow_mut()
+                    .velocity = velocity;
+            }
+            Action::AddParticle(position, velocity) => {
+                world.fluid.add_particle(position);
+                if let Some(particle) = world.fluid.particles.last_mut() {
+                    particle.velocity = velocity;
+                }
+            }
+            Action::EmitFluid(center, radius) => world.emit_fluid(center, radius),
+            Action::ClearFluid => world.fluid.particles.clear(),
+            Action::AddLevel(height) => world.regions.push(FluidRegion::level(height)),
+            Action::AddRegion(ref points) => {
+                world.regions.push(FluidRegion::polygon(points.clone()))
+            }
+            Action::RemoveRegion(index) => if index < world.regions.len() {
+                world.regions.remove(index);
+            },
+            Action::Pull(index, force) => {
+                world
+                    .verts
+                    .get(index)
+                    .ok_or(WorldError::UnknownVertex(index))?
+                    .borrow_mut()
+                    .apply_force(force);
+            }
+            Action::Drag(index, delta) => world.drag_vertex(index, delta)?,
+            Action::Set(parameter, value) => parameter.set(world, value)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Action::Start(time) => write!(f, "start {}", time),
+            Action::Seed(state) => write!(f, "seed {}", state),
+            Action::Settings(iterations, collisions) => {
+                write!(f, "settings {} {}", iterations, collisions as u8)
+            }
+            Action::AddVertex(position) => write!(f, "vertex {} {}", position.x, position.y),
+            Action::RemoveVertex(index) => write!(f, "remove_vertex {}", index),
+            Action::CreateSurface(index_a, index_b) => {
+                write!(f, "surface {} {}", index_a, index_b)
+            }
+            Action::RemoveSurface(index) => write!(f, "remove_surface {}", index),
+            Action::SetVelocity(index, velocity) => {
+                write!(f, "velocity {} {} {}", index, velocity.x, velocity.y)
+            }
+            Action::AddParticle(position, velocity) => write!(
+                f,
+                "particle {} {} {} {}",
+                position.x, position.y, velocity.x, velocity.y
+            ),
+            Action::EmitFluid(center, radius) => {
+                write!(f, "emit {} {} {}", center.x, center.y, radius)
+            }
+            Action::ClearFluid => write!(f, "clear_fluid"),
+            Action::AddLevel(height) => write!(f, "level {}", height),
+            Action::AddRegion(ref points) => {
+                write!(f, "region")?;
+                for point in points {
+                    write!(f, " {} {}", point.x, point.y)?;
+                }
+                Ok(())
+            }
+            Action::RemoveRegion(index) => write!(f, "remove_region {}", index),
+            Action::Pull(index, force) => write!(f, "pull {} {} {}", index, force.x, force.y),
+            Action::Drag(index, delta) => write!(f, "drag {} {} {}", index, delta.x, delta.y),
+            Action::Set(parameter, value) => match parameter {
+                Parameter::World(name) => write!(f, "set world {} {}", name, value),
+                Parameter::Vertex(index, name) => {
+                    write!(f, "set vertex {} {} {}", index, name, value)
+                }
+                Parameter::Surface(index, name) => {
+                    write!(f, "set surface {} {} {}", index, name, value)
+                }
+                Parameter::Region(index, name) => {
+                    write!(f, "set region {} {} {}", index, name, value)
+                }
+            },
+            Action::End => write!(f, "end"),
+        }
+    }
+}
+
+fn number<T: FromStr>(word: Option<&str>) -> Result<T, String> {
+    let word = word.ok_or_else(|| "missing value".to_string())?;
+    word.parse()
+        .map_err(|_| format!("'{}' is not a valid number", word))
+}
+
+fn vector<'a, I: Iterator<Item = &'a str>>(words: &mut I) -> Result<Vector, String> {
+    Ok(Vector::new(number(words.next())?, number(words.next())?))
+}
+
+fn parameter_name(names: &[&'static str], name: Option<&str>) -> Result<&'static str, String> {
+    let name = name.ok_or_else(|| "missing parameter name".to_string())?;
+    names
+        .iter()
+        .find(|&&other| other == name)
+        .cloned()
+        .ok_or_else(|| format!("unknown parameter '{}'", name))
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Action, String> {
+        let mut words = text.split_whitespace();
+        let command = words.next().ok_or_else(|| "missing action".to_string())?;
+
+        let action = match command {
+            "start" => Action::Start(number(words.next())?),
+            "seed" => Action::Seed(number(words.next())?),
+            "settings" => Action::Settings(
+                number(words.next())?,
+                number::<u8>(words.next())? != 0,
+            ),
+            "vertex" => Action::AddVertex(vector(&mut words)?),
+            "remove_vertex" => Action::RemoveVertex(number(words.next())?),
+            "surface" => Action::CreateSurface(number(words.next())?, number(words.next())?),
+            "remove_surface" => Action::RemoveSurface(number(words.next())?),
+            "velocity" => Action::SetVelocity(number(words.next())?, vector(&mut words)?),
+            "particle" => Action::AddParticle(vector(&mut words)?, vector(&mut words)?),
+            "emit" => Action::EmitFluid(vector(&mut words)?, number(words.next())?),
+            "clear_fluid" => Action::ClearFluid,
+            "level" => Action::AddLevel(number(words.next())?),
+            "region" => {
+                let mut points = Vec::new();
+                let coordinates: Vec<&str> = words.by_ref().collect();
+                for pair in coordinates.chunks(2) {
+                    if pair.len() != 2 {
+                        return Err("odd number of coordinates".to_string());
+                    }
+                    points.push(Vector::new(number(Some(pair[0]))?, number(Some(pair[1]))?));
+                }
+                Action::AddRegion(points)
+            }
+            "remove_region" => Action::RemoveRegion(number(words.next())?),
+            "pull" => Action::Pull(number(words.next())?, vector(&mut words)?),
+            "drag" => Action::Drag(number(words.next())?, vector(&mut words)?),
+            "set" => {
+                let parameter = match words.next() {
+                    Some("world") => {
+                        let name = parameter_name(WORLD_PARAMETERS, words.next())?;
+                        Parameter::World(name)
+                    }
+                    Some("vertex") => {
+                        let index = number(words.next())?;
+                        let name = parameter_name(VERTEX_PARAMETERS, words.next())?;
+                        Parameter::Vertex(index, name)
+                    }
+                    Some("surface") => {
+                        let index = number(words.next())?;
+                        let name = parameter_name(SURFACE_PARAMETERS, words.next())?;
+                        Parameter::Surface(index, name)
+                    }
+                    Some("region") => {
+                        let index = number(words.next())?;
+                        let name = parameter_name(REGION_PARAMETERS, words.next())?;
+                        Parameter::Region(index, name)
+                    }
+                    _ => return Err("expected world, vertex, surface or region".to_string()),
+                };
+                Action::Set(parameter, number(words.next())?)
+            }
+            "end" => Action::End,
+            _ => return Err(format!("unknown action '{}'", command)),
+        };
+
+        if let Some(word) = words.next() {
+            return Err(format!("unexpected '{}'", word));
+        }
+        Ok(action)
+    }
+}
+
+/// A list of actions with the step they happened before
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    pub actions: Vec<(u64, Action)>,
+}
+
+impl Replay {
+    pub fn new() -> Replay {
+        Replay {
+            actions: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        Replay::parse(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Reads actions written one per line as
+    /// ```text
+    /// step action arguments...
+    /// ```
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut actions = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let step = parts
+                .next()
+                .unwrap_or("")
+                .parse()
+                .map_err(|_| format!("line {}: expected a step number", number + 1))?;
+            let action = parts
+                .next()
+                .unwrap_or("")
+                .parse()
+                .map_err(|error| format!("line {}: {}", number + 1, error))?;
+
+            actions.push((step, action));
+        }
+
+        Ok(Replay { actions })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# spring replay, one 'step action' per line")?;
+        for &(step, ref action) in &self.actions {
+            writeln!(file, "{} {}", step, action)?;
+        }
+        Ok(())
+    }
+
+    /// Plays the actions on a new deterministic world, calling `on_step` after every step
+    pub fn run<F: FnMut(&World)>(&self, mut on_step: F) -> Result<World, String> {
+        let mut world = World::new();
+        world.deterministic = true;
+        let mut iterations = 8;
+        let mut collisions = true;
+
+        for &(step, ref action) in &self.actions {
+            if let Action::Start(time) = *action {
+                world.step = step;
+                world.time = time;
+                continue;
+            }
+
+            if step < world.step {
+                return Err(format!("step {} comes after step {}", step, world.step));
+            }
+            while world.step < step {
+                world.update(world.fixed_dt, iterations, collisions);
+                on_step(&world);
+            }
+
+            match *action {
+                Action::Settings(new_iterations, new_collisions) => {
+                    iterations = new_iterations;
+                    collisions = new_collisions;
+                }
+                Action::End => break,
+                _ => action
+                    .apply(&mut world)
+                    .map_err(|error| format!("step {}: {}: {}", step, action, error))?,
+            }
+        }
+
+        Ok(world)
+    }
+}
+
+/// Records what the user does to the world, so that it can be replayed
+pub struct InputRecorder {
+    pub replay: Replay,
+    /// The parameters after the last step or action, to find what the user changed
+    parameters: BTreeMap<Parameter, f64>,
+    settings: (u32, bool),
+}
+
+impl InputRecorder {
+    /// Starts recording, writing out the current state of the world as the first actions
+    pub fn start(world: &World, iterations: u32, collisions: bool) -> InputRecorder {
+        let step = world.step;
+        let mut actions = vec![
+            (step, Action::Start(world.time)),
+            (step, Action::Seed(world.rng.state())),
+            (step, Action::Settings(iterations, collisions)),
+        ];
+
+        for (index, vertex) in world.verts.iter().enumerate() {
+            let vertex = vertex.borrow();
+            actions.push((step, Action::AddVertex(vertex.position)));
+            actions.push((step, Action::SetVelocity(index, vertex.velocity)));
+        }
+        for surface in &world.surfaces {
+            actions.push((step, Action::CreateSurface(surface.index_a, surface.index_b)));
+        }
+        for particle in &world.fluid.particles {
+            actions.push((step, Action::AddParticle(particle.position, particle.velocity)));
+        }
+        for region in &world.regions {
+            let action = match region.shape {
+                RegionShape::Level(height) => Action::AddLevel(height),
+                RegionShape::Polygon(ref points) => Action::AddRegion(points.clone()),
+            };
+            actions.push((step, action));
+        }
+
+        let parameters = parameters(world);
+        for (&parameter, &value) in &parameters {
+            actions.push((step, Action::Set(parameter, value)));
+        }
+
+        InputRecorder {
+            replay: Replay { actions },
+            parameters,
+            settings: (iterations, collisions),
+        }
+    }
+
+    /// Records an action which was just done to the world,
+    /// `flush` must be called right before doing it so that earlier changes come first
+    pub fn record(&mut self, world: &World, action: Action) {
+        self.replay.actions.push((world.step, action));
+        self.parameters = parameters(world);
+    }
+
+    /// Records the settings and parameters changed since the last step or action,
+    /// called right before stepping or doing an action
+    pub fn flush(&mut self, world: &World, iterations: u32, collisions: bool) {
+        if self.settings != (iterations, collisions) {
+            self.settings = (iterations, collisions);
+            self.replay
+                .actions
+                .push((world.step, Action::Settings(iterations, collisions)));
+        }
+
+        let parameters = parameters(world);
+        for (&parameter, &value) in &parameters {
+            if self.parameters.get(&parameter) != Some(&value) {
+                self.replay.actions.push((world.step, Action::Set(parameter, value)));
+            }
+        }
+        self.parameters = parameters;
+    }
+
+    /// Forgets the changes made by the step itself
+    pub fn after_step(&mut self, world: &World) {
+        self.parameters = parameters(world);
+    }
+
+    /// Stops recording, ending the replay at the current step
+    pub fn finish(mut self, world: &World) -> Replay {
+        let (iterations, collisions) = self.settings;
+        self.flush(world, iterations, collisions);
+        self.replay.actions.push((world.step, Action::End));
+        self.replay
+    }
+}
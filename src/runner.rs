@@ -0,0 +1,611 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use compact::{self, Precision};
+use export::{self, Exporter, Format, Quantity};
+use migration::MigrationReport;
+use physics::health::HealthReport;
+use physics::simulation::{Energy, World};
+use physics::validation::ValidationReport;
+use raster::{self, Camera, GifWriter};
+use recording::Recorder;
+use replay::Replay;
+use scene::{Scene, ViewSettings};
+use Vector;
+
+pub const USAGE: &str = "Usage: spring run <scene> [options]
+
+Simulates a scene (.json) or a compact world (.sprw) without opening a window.
+Stops and exits with 2 if the world becomes invalid, or if the health monitor
+is enabled in the scene and steps in.
+
+Options:
+    --steps <n>          Number of steps to run (default 1000)
+    --dt <seconds>       Length of a step, as a number or a fraction like 1/240
+                         (default the fixed timestep of the world)
+    --iterations <n>     Substeps of each step (default 8)
+    --no-collisions      Let the bodies pass through each other
+    --report <n>         Print the statistics every n steps, 0 only at the end (default 100)
+    --trajectory <file>  Record every step, the recording can be played back in the viewer
+    --output <file>      Write the final state, as a scene (.json) or a compact world (.sprw)
+    --export <file>      Write the chosen quantities, as JSON lines if the file ends with
+                         .jsonl or .json, otherwise as CSV
+    --format <format>    Override the export format, csv or jsonl
+    --quantities <list>  The quantities to export separated by commas (default position,velocity)
+                         from position, velocity, acceleration, extension, force,
+                         body_position, body_velocity
+    --export-every <n>   Export every n steps (default 1)
+    --png <file>         Render the final state, or every rendered step if the file name
+                         has a {} in it, which is replaced by the step
+    --gif <file>         Render the run as an animated GIF
+    --frame-every <n>    Render every n steps (default 4)
+    --size <w>x<h>       Size of the rendered images (default 1280x720)
+    --scale <pixels>     Pixels per meter (default the one saved in the scene)
+    --center <x>,<y>     The point at the center of the images (default the saved one)";
+
+pub const REPLAY_USAGE: &str = "Usage: spring replay <file> [options]
+
+Plays the actions of a replay file on a new world, without opening a window,
+and prints the final step and state hash.
+
+Options:
+    --hashes             Print the state hash after every step
+    --output <file>      Write the final state, as a scene (.json) or a compact world (.sprw)";
+
+/// What a headless run does, parsed from the command line
+pub struct RunOptions {
+    pub scene: PathBuf,
+    pub steps: u64,
+    /// The length of a step, `None` keeps the fixed timestep of the world
+    pub dt: Option<f64>,
+    pub iterations: u32,
+    pub collisions: bool,
+    /// Print the statistics every this many steps, 0 only prints the summary
+    pub report_interval: u64,
+    pub trajectory: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+    /// The format of the export, `None` picks it from the extension
+    pub export_format: Option<Format>,
+    pub quantities: Vec<Quantity>,
+    pub export_interval: u64,
+    /// The file name of the rendered images, a {} in it is replaced by the step
+    pub png: Option<String>,
+    pub gif: Option<PathBuf>,
+    pub frame_interval: u64,
+    pub size: (u32, u32),
+    /// The camera scale and center, `None` keeps the ones of the scene
+    pub scale: Option<f64>,
+    pub center: Option<Vector>,
+}
+
+impl RunOptions {
+    pub fn new(scene: PathBuf) -> RunOptions {
+        RunOptions {
+            scene,
+            steps: 1000,
+            dt: None,
+            iterations: 8,
+            collisions: true,
+            report_interval: 100,
+            trajectory: None,
+            output: None,
+            export: None,
+            export_format: None,
+            quantities: vec![Quantity::Position, Quantity::Velocity],
+            export_interval: 1,
+            png: None,
+            gif: None,
+            frame_interval: 4,
+            size: (1280, 720),
+            scale: None,
+            center: None,
+        }
+    }
+
+    /// Parses the arguments after `run`
+    pub fn parse(args: &[String]) -> Result<RunOptions, String> {
+        let mut scene = None;
+        let mut options = RunOptions::new(PathBuf::new());
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value =
+                |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+
+            match arg.as_str() {
+                "--steps" => options.steps = parse_number(value(arg)?, arg)?,
+                "--dt" => options.dt = Some(parse_duration(value(arg)?)?),
+                "--iterations" => options.iterations = parse_number(value(arg)?, arg)?,
+                "--no-collisions" => options.collisions = false,
+                "--report" => options.report_interval = parse_number(value(arg)?, arg)?,
+                "--trajectory" => options.trajectory = Some(PathBuf::from(value(arg)?)),
+                "--output" => options.output = Some(PathBuf::from(value(arg)?)),
+                "--export" => options.export = Some(PathBuf::from(value(arg)?)),
+                "--format" => options.export_format = Some(value(arg)?.parse()?),
+                "--quantities" => options.quantities = export::parse_quantities(value(arg)?)?,
+                "--export-every" => options.export_interval = parse_number(value(arg)?, arg)?,
+                "--png" => options.png = Some(value(arg)?.clone()),
+                "--gif" => options.gif = Some(PathBuf::from(value(arg)?)),
+                "--frame-every" => options.frame_interval = parse_number(value(arg)?, arg)?,
+                "--size" => {
+                    let (width, height) = parse_pair(value(arg)?, 'x', arg)?;
+                    options.size = (width, height);
+                }
+                "--scale" => options.scale = Some(parse_number(value(arg)?, arg)?),
+                "--center" => {
+                    let (x, y) = parse_pair(value(arg)?, ',', arg)?;
+                    options.center = Some(Vector::new(x, y));
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if scene.is_none() => scene = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        if options.iterations == 0 {
+            return Err(String::from("--iterations must be at least 1"));
+        }
+        if options.size.0 == 0 || options.size.1 == 0 {
+            return Err(String::from("--size must not be empty"));
+        }
+        options.scene = scene.ok_or_else(|| String::from("Missing the scene to run"))?;
+        WorldFile::from_path(&options.scene)?;
+        if let Some(ref output) = options.output {
+            WorldFile::from_path(output)?;
+        }
+        Ok(options)
+    }
+}
+
+/// What a headless replay does, parsed from the command line
+pub struct ReplayOptions {
+    pub replay: PathBuf,
+    /// Print the hash of every step
+    pub hashes: bool,
+    pub output: Option<PathBuf>,
+}
+
+impl ReplayOptions {
+    /// Parses the arguments after `replay`
+    pub fn parse(args: &[String]) -> Result<ReplayOptions, String> {
+        let mut replay = None;
+        let mut hashes = false;
+        let mut output = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--hashes" => hashes = true,
+                "--output" => {
+                    let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                    output = Some(PathBuf::from(value));
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ if replay.is_none() => replay = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        if let Some(ref output) = output {
+            WorldFile::from_path(output)?;
+        }
+        Ok(ReplayOptions {
+            replay: replay.ok_or_else(|| String::from("Missing the replay to run"))?,
+            hashes,
+            output,
+        })
+    }
+}
+
+fn parse_number<T: ::std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value {} for {}", value, name))
+}
+
+/// Reads two numbers separated by `separator`, like `1280x720`
+fn parse_pair<T: ::std::str::FromStr>(
+    value: &str,
+    separator: char,
+    name: &str,
+) -> Result<(T, T), String> {
+    let mut parts = value.splitn(2, separator);
+    match (parts.next(), parts.next()) {
+        (Some(first), Some(second)) => Ok((
+            parse_number(first.trim(), name)?,
+            parse_number(second.trim(), name)?,
+        )),
+        _ => Err(format!("Invalid value {} for {}", value, name)),
+    }
+}
+
+/// Reads a duration like `0.004` or `1/240`
+fn parse_duration(value: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid value {} for --dt", value);
+
+    let mut parts = value.splitn(2, '/');
+    let numerator: f64 = parts
+        .next()
+        .unwrap_or("")
+        .trim()
+        .parse()
+        .map_err(|_| invalid())?;
+    let dt = match parts.next() {
+        Some(denominator) => {
+            numerator / denominator.trim().parse::<f64>().map_err(|_| invalid())?
+        }
+        None => numerator,
+    };
+
+    if dt.is_finite() && dt > 0.0 {
+        Ok(dt)
+    } else {
+        Err(invalid())
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 * 1e-9
+}
+
+/// The files a world can be read from and written to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldFile {
+    Scene,
+    Compact,
+}
+
+impl WorldFile {
+    /// Scenes end with .json and compact worlds with .sprw, anything else is an error
+    pub fn from_path(path: &Path) -> Result<WorldFile, String> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Ok(WorldFile::Scene),
+            Some("sprw") => Ok(WorldFile::Compact),
+            _ => Err(format!(
+                "Unknown world file {}, use .json for a scene or .sprw for a compact world",
+                path.display()
+            )),
+        }
+    }
+}
+
+fn world_file(path: &Path) -> io::Result<WorldFile> {
+    WorldFile::from_path(path).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+}
+
+/// Reads a scene or a compact world file, with what was migrated in old scenes
+/// Only scenes have the settings of the viewer
+pub fn load_world(path: &Path) -> io::Result<(World, Option<ViewSettings>, MigrationReport)> {
+    match world_file(path)? {
+        WorldFile::Scene => {
+            let (scene, report) = Scene::load(path)?;
+            let world = scene
+                .to_world()
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            Ok((world, scene.view, report))
+        }
+        WorldFile::Compact => Ok((compact::load(path)?, None, MigrationReport::default())),
+    }
+}
+
+/// Writes a scene or a compact world file, depending on the extension
+pub fn save_world(path: &Path, world: &World) -> io::Result<()> {
+    match world_file(path)? {
+        WorldFile::Scene => Scene::new(world, None).save(path),
+        WorldFile::Compact => compact::save(path, world, Precision::Double),
+    }
+}
+
+/// How a run went
+pub struct RunStatistics {
+    pub steps: u64,
+    pub elapsed: Duration,
+    /// The slowest step
+    pub max_step: Duration,
+    pub initial_energy: Energy,
+    pub final_energy: Energy,
+    /// The total energy furthest from the initial one
+    pub max_drift: f64,
+    /// The contacts solved over all the steps
+    pub total_contacts: usize,
+    pub max_contacts: usize,
+    /// The report of the first invalid state, which stopped the run
+    pub invalid: Option<ValidationReport>,
+    /// The first problem the health monitor had to fix, which stopped the run
+    pub unhealthy: Option<HealthReport>,
+}
+
+impl RunStatistics {
+    fn new(energy: Energy) -> RunStatistics {
+        RunStatistics {
+            steps: 0,
+            elapsed: Duration::new(0, 0),
+            max_step: Duration::new(0, 0),
+            initial_energy: energy,
+            final_energy: energy,
+            max_drift: 0.0,
+            total_contacts: 0,
+            max_contacts: 0,
+            invalid: None,
+            unhealthy: None,
+        }
+    }
+
+    /// True if the run was stopped by an invalid world or a health problem
+    pub fn failed(&self) -> bool {
+        self.invalid.is_some() || self.unhealthy.is_some()
+    }
+}
+
+impl fmt::Display for RunStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let elapsed = seconds(self.elapsed);
+        let steps = self.steps.max(1) as f64;
+
+        writeln!(f, "Steps: {}", self.steps)?;
+        writeln!(
+            f,
+            "Time: {:.3} s, {:.3} ms per step, slowest {:.3} ms",
+            elapsed,
+            elapsed * 1000.0 / steps,
+            seconds(self.max_step) * 1000.0
+        )?;
+        writeln!(
+            f,
+            "Energy: {:.4} -> {:.4} (kinetic {:.4}, gravitational {:.4}, elastic {:.4})",
+            self.initial_energy.total(),
+            self.final_energy.total(),
+            self.final_energy.kinetic,
+            self.final_energy.gravitational,
+            self.final_energy.elastic
+        )?;
+        writeln!(f, "Largest energy drift: {:.4}", self.max_drift)?;
+        write!(
+            f,
+            "Contacts: {:.2} per step, at most {}",
+            self.total_contacts as f64 / steps,
+            self.max_contacts
+        )?;
+        if let Some(ref report) = self.invalid {
+            write!(f, "\nThe world became invalid:\n{}", report)?;
+        }
+        if let Some(ref report) = self.unhealthy {
+            write!(f, "\nThe simulation blew up:\n{}", report)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the images asked for on the command line
+struct FrameWriter {
+    camera: Camera,
+    interval: u64,
+    png: Option<String>,
+    gif: Option<GifWriter>,
+    /// The number of times `record` was called
+    steps: u64,
+}
+
+impl FrameWriter {
+    fn new(options: &RunOptions, view: &ViewSettings, dt: f64) -> io::Result<FrameWriter> {
+        let (width, height) = options.size;
+        let mut camera = Camera::from_settings(view, width, height);
+        if let Some(scale) = options.scale {
+            camera.scale = scale;
+        }
+        if let Some(center) = options.center {
+            camera.offset = center;
+        }
+
+        let interval = u64::max(options.frame_interval, 1);
+        let gif = match options.gif {
+            Some(ref path) => {
+                // The GIF delays are in hundredths of a second
+                let delay = (dt * interval as f64 * 100.0).round().max(1.0).min(65535.0);
+                Some(GifWriter::create(path, width, height, delay as u16)?)
+            }
+            None => None,
+        };
+
+        Ok(FrameWriter {
+            camera,
+            interval,
+            png: options.png.clone(),
+            gif,
+            steps: 0,
+        })
+    }
+
+    /// The images of every step are only written if the PNG file name has a {} in it
+    fn every_step(&self) -> bool {
+        self.png.as_ref().map_or(false, |png| png.contains("{}"))
+    }
+
+    /// Call with the initial state and after every step
+    fn record(&mut self, world: &World) -> io::Result<()> {
+        if self.steps % self.interval == 0 && (self.every_step() || self.gif.is_some()) {
+            let canvas = raster::render(world, &self.camera);
+            if self.every_step() {
+                let path = self
+                    .png
+                    .as_ref()
+                    .unwrap()
+                    .replace("{}", &format!("{:06}", world.step));
+                canvas.save_png(Path::new(&path))?;
+            }
+            if let Some(ref mut gif) = self.gif {
+                gif.add_frame(&canvas)?;
+            }
+        }
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// Renders the final state if only that was asked, the GIF is completed when dropped
+    fn finish(self, world: &World) -> io::Result<()> {
+        if !self.every_step() {
+            if let Some(ref png) = self.png {
+                raster::render(world, &self.camera).save_png(Path::new(png))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs a world without a window, printing a line of statistics every report interval
+/// Stops early if the world becomes invalid or the health monitor has to step in
+/// `view` places the camera of the rendered images, like in the viewer
+pub fn run<W: Write>(
+    world: &mut World,
+    view: &ViewSettings,
+    options: &RunOptions,
+    output: &mut W,
+) -> io::Result<RunStatistics> {
+    if let Some(dt) = options.dt {
+        world.fixed_dt = dt;
+    }
+    let dt = world.fixed_dt;
+
+    let mut recorder = match options.trajectory {
+        Some(ref path) => Some(Recorder::create(path)?),
+        None => None,
+    };
+    let mut exporter = match options.export {
+        Some(ref path) => {
+            let format = options
+                .export_format
+                .unwrap_or_else(|| Format::from_path(path));
+            let quantities = options.quantities.clone();
+            Some(Exporter::create(
+                path,
+                format,
+                quantities,
+                options.export_interval,
+            )?)
+        }
+        None => None,
+    };
+    let mut frames = if options.png.is_some() || options.gif.is_some() {
+        Some(FrameWriter::new(options, view, dt)?)
+    } else {
+        None
+    };
+
+    let mut statistics = RunStatistics::new(world.energy());
+    world.health.report = None;
+    let report = world.validate();
+    if !report.is_valid() {
+        statistics.invalid = Some(report);
+        return Ok(statistics);
+    }
+
+    if options.report_interval != 0 {
+        writeln!(
+            output,
+            "step\ttime\tkinetic\tgravitational\telastic\ttotal\tcontacts\tsubsteps\tms"
+        )?;
+    }
+
+    // The export and the frames start from the initial state
+    if let Some(ref mut exporter) = exporter {
+        exporter.record(world)?;
+    }
+    if let Some(ref mut frames) = frames {
+        frames.record(world)?;
+    }
+
+    let start = Instant::now();
+    for i in 0..options.steps {
+        // Like in the viewer, the debug vectors only show the last step
+        world.debug.vectors.clear();
+
+        let step_start = Instant::now();
+        world.update(dt, options.iterations, options.collisions);
+        let step_time = step_start.elapsed();
+
+        if let Some(ref mut recorder) = recorder {
+            recorder.record(world)?;
+        }
+        if let Some(ref mut exporter) = exporter {
+            exporter.record(world)?;
+        }
+        if let Some(ref mut frames) = frames {
+            frames.record(world)?;
+        }
+
+        let energy = world.energy();
+        let contacts = world.contacts;
+        statistics.steps += 1;
+        statistics.max_step = statistics.max_step.max(step_time);
+        statistics.final_energy = energy;
+        let drift = (energy.total() - statistics.initial_energy.total()).abs();
+        statistics.max_drift = statistics.max_drift.max(drift);
+        statistics.total_contacts += contacts;
+        statistics.max_contacts = statistics.max_contacts.max(contacts);
+
+        if options.report_interval != 0 && (i + 1) % options.report_interval == 0 {
+            writeln!(
+                output,
+                "{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{}\t{}\t{:.3}",
+                world.step,
+                world.time,
+                energy.kinetic,
+                energy.gravitational,
+                energy.elastic,
+                energy.total(),
+                contacts,
+                world.substeps,
+                seconds(step_time) * 1000.0
+            )?;
+        }
+
+        let report = world.validate();
+        if !report.is_valid() {
+            statistics.invalid = Some(report);
+            break;
+        }
+        if let Some(ref report) = world.health.report {
+            statistics.unhealthy = Some(report.clone());
+            break;
+        }
+    }
+    statistics.elapsed = start.elapsed();
+
+    if let Some(recorder) = recorder {
+        recorder.finish()?;
+    }
+    if let Some(exporter) = exporter {
+        exporter.finish()?;
+    }
+    if let Some(frames) = frames {
+        frames.finish(world)?;
+    }
+    if let Some(ref path) = options.output {
+        save_world(path, world)?;
+    }
+
+    Ok(statistics)
+}
+
+/// Plays a replay without a window, writing the hash of every step if asked
+pub fn replay<W: Write>(
+    replay: &Replay,
+    options: &ReplayOptions,
+    output: &mut W,
+) -> Result<World, String> {
+    let mut result = Ok(());
+    let world = replay.run(|world| {
+        if options.hashes && result.is_ok() {
+            result = writeln!(output, "{}\t{:016x}", world.step, world.last_hash);
+        }
+    })?;
+    result.map_err(|error| error.to_string())?;
+
+    if let Some(ref path) = options.output {
+        save_world(path, &world).map_err(|error| error.to_string())?;
+    }
+    Ok(world)
+}
@@ -0,0 +1,225 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use serde_json;
+
+use migration::{self, MigrationReport};
+use physics::determinism::Rng;
+use physics::error::WorldError;
+use physics::fluid::Particle;
+use physics::health::HealthPolicy;
+use physics::material::{CombineRule, Material};
+use physics::regions::FluidRegion;
+use physics::simulation::{Vertex, World};
+use physics::surface::Surface;
+use replay::{Parameter, WORLD_PARAMETERS};
+use Vector;
+
+/// The version of the scenes written by this build
+pub const SCENE_VERSION: u32 = 2;
+
+/// The world parameters written by name next to the settings, instead of as numbers in them
+pub const NAMED_PARAMETERS: &[&str] = &["friction_rule", "restitution_rule", "health_policy"];
+
+/// How the viewer was looking at the scene and running it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub scale: f64,
+    pub offset: Vector,
+    pub sim_speed: f64,
+    pub iterations: u32,
+    pub collisions: bool,
+    pub vertex_scale: f64,
+    pub pull_force: f32,
+    pub emitter_radius: f32,
+}
+
+impl Default for ViewSettings {
+    fn default() -> ViewSettings {
+        ViewSettings {
+            scale: 60.0,
+            offset: Vector::new(0.0, 0.0),
+            sim_speed: 1.0,
+            iterations: 8,
+            collisions: true,
+            vertex_scale: 0.25,
+            pull_force: 250.0,
+            emitter_radius: 0.3,
+        }
+    }
+}
+
+/// Everything needed to rebuild a world, as it's written in scene files
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    /// The world parameters by name, the same used by the replays
+    #[serde(default)]
+    pub settings: BTreeMap<String, f64>,
+    /// The world's default is kept when missing
+    #[serde(default)]
+    pub friction_rule: Option<CombineRule>,
+    #[serde(default)]
+    pub restitution_rule: Option<CombineRule>,
+    #[serde(default)]
+    pub health_policy: Option<HealthPolicy>,
+    #[serde(default)]
+    pub deterministic: bool,
+    /// The state of the random number generator
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub time: f64,
+    #[serde(default)]
+    pub step: u64,
+    #[serde(default)]
+    pub verts: Vec<Vertex>,
+    #[serde(default)]
+    pub surfaces: Vec<Surface>,
+    #[serde(default)]
+    pub particles: Vec<Particle>,
+    #[serde(default)]
+    pub regions: Vec<FluidRegion>,
+    /// The materials added to the library or changed, the built-in ones aren't written
+    #[serde(default)]
+    pub materials: Vec<Material>,
+    #[serde(default)]
+    pub view: Option<ViewSettings>,
+}
+
+impl Scene {
+    pub fn new(world: &World, view: Option<ViewSettings>) -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            settings: WORLD_PARAMETERS
+                .iter()
+                .filter(|name| !NAMED_PARAMETERS.contains(name))
+                .filter_map(|&name| {
+                    Parameter::World(name)
+                        .get(world)
+                        .map(|value| (name.to_string(), value))
+                })
+                .collect(),
+            friction_rule: Some(world.solver.friction_rule),
+            restitution_rule: Some(world.solver.restitution_rule),
+            health_policy: Some(world.health.policy),
+            deterministic: world.deterministic,
+            seed: world.rng.state(),
+            time: world.time,
+            step: world.step,
+            verts: world
+                .verts
+                .iter()
+                .map(|vertex| vertex.borrow().clone())
+                .collect(),
+            surfaces: world.surfaces.clone(),
+            particles: world.fluid.particles.clone(),
+            regions: world.regions.clone(),
+            materials: world.materials.custom(),
+            view,
+        }
+    }
+
+    /// Builds the world of the scene, the settings with unknown names are ignored
+    /// The surfaces are checked like when they are created, the rest is up to `World::validate`
+    pub fn to_world(&self) -> Result<World, WorldError> {
+        let mut world = World::new();
+
+        for (name, &value) in &self.settings {
+            match Parameter::world(name) {
+                Some(parameter) if !NAMED_PARAMETERS.contains(&name.as_str()) => {
+                    parameter.set(&mut world, value)?
+                }
+                _ => {}
+            }
+        }
+        if let Some(rule) = self.friction_rule {
+            world.solver.friction_rule = rule;
+        }
+        if let Some(rule) = self.restitution_rule {
+            world.solver.restitution_rule = rule;
+        }
+        if let Some(policy) = self.health_policy {
+            world.health.policy = policy;
+        }
+        for material in &self.materials {
+            material.validate().map_err(WorldError::InvalidMaterial)?;
+            world.materials.insert(material.clone());
+        }
+        world.deterministic = self.deterministic;
+        world.rng = Rng::new(self.seed);
+        world.time = self.time;
+        world.step = self.step;
+
+        let mut pairs = BTreeSet::new();
+        for surface in &self.surfaces {
+            for &index in &[surface.index_a, surface.index_b] {
+                if index >= self.verts.len() {
+                    return Err(WorldError::UnknownVertex(index));
+                }
+            }
+            if surface.index_a == surface.index_b {
+                return Err(WorldError::SelfLoop(surface.index_a));
+            }
+
+            let pair = (
+                usize::min(surface.index_a, surface.index_b),
+                usize::max(surface.index_a, surface.index_b),
+            );
+            if !pairs.insert(pair) {
+                return Err(WorldError::DuplicateSurface(pair.0, pair.1));
+            }
+        }
+
+        world.verts = self.verts
+            .iter()
+            .map(|vertex| RefCell::new(vertex.clone()))
+            .collect();
+        world.surfaces = self.surfaces.clone();
+        world.fluid.particles = self.particles.clone();
+        world.regions = self.regions.clone();
+        world.update_bodies();
+
+        Ok(world)
+    }
+
+    pub fn load(path: &Path) -> io::Result<(Scene, MigrationReport)> {
+        let mut text = String::new();
+        File::open(path)?.read_to_string(&mut text)?;
+
+        Scene::parse(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// Reads a scene, upgrading it if it was written by an older version
+    pub fn parse(text: &str) -> Result<(Scene, MigrationReport), String> {
+        let value = serde_json::from_str(text).map_err(|error| error.to_string())?;
+        migration::migrate(value)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        File::create(path)?.write_all(text.as_bytes())
+    }
+}
+
+/// Reads a scene file into a world, failing if the world is not valid
+pub fn load_world(path: &Path) -> io::Result<World> {
+    let world = Scene::load(path)?
+        .0
+        .to_world()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let report = world.validate();
+    if !report.is_valid() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            report.to_string(),
+        ));
+    }
+    Ok(world)
+}
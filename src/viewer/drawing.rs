@@ -5,6 +5,9 @@ use piston_window::*;
 use imgui;
 use viewer::imgui_piston::{Renderer, Shaders};
 use viewer::input::InputState;
+use physics::regions::RegionShape;
+use raster::{BACKGROUND_COLOR, BROKEN_SURFACE_COLOR, BROKEN_VERTEX_COLOR, DEBUG_COLOR,
+             PARTICLE_COLOR, REGION_COLOR, SURFACE_COLOR, VERTEX_COLOR};
 
 pub fn view_loop(mut view: ViewState) {
     let opengl = OpenGL::V3_2;
@@ -41,11 +44,7 @@ pub fn view_loop(mut view: ViewState) {
         view.physics_dt = get_elapsed(&time);
         time = SystemTime::now();
 
-        view.world.update(
-            view.physics_dt * view.sim_speed,
-            view.iterations,
-            view.collisions,
-        );
+        view.step();
 
         // When the window is resize the gui renderer must be regeretated
         // With the new window.factory containing the new height and width
@@ -62,7 +61,44 @@ pub fn view_loop(mut view: ViewState) {
                 view.window_size.x = window.size().width as f64;
 
                 window.draw_2d(&e, |c, g| {
-                    clear([1.0; 4], g);
+                    clear(BACKGROUND_COLOR, g);
+
+                    // Drawing the fluid regions
+                    for region in &view.world.regions {
+                        match region.shape {
+                            RegionShape::Level(height) => {
+                                let top = view.to_screen_point(&Vector::new(0.0, height)).y;
+                                if top < view.window_size.y {
+                                    let top = f64::max(top, 0.0);
+                                    let rect = [
+                                        0.0,
+                                        top,
+                                        view.window_size.x,
+                                        view.window_size.y - top,
+                                    ];
+                                    rectangle(REGION_COLOR, rect, c.transform, g);
+                                }
+                            }
+                            RegionShape::Polygon(ref points) => {
+                                let points: Vec<[f64; 2]> = points
+                                    .iter()
+                                    .map(|point| {
+                                        let point = view.to_screen_point(point);
+                                        [point.x, point.y]
+                                    })
+                                    .collect();
+                                polygon(REGION_COLOR, &points, c.transform, g);
+                            }
+                        }
+                    }
+
+                    // Drawing the region being edited
+                    for i in 1..view.region_points.len() {
+                        let start = view.to_screen_point(&view.region_points[i - 1]);
+                        let end = view.to_screen_point(&view.region_points[i]);
+                        let line_data = [start.x, start.y, end.x, end.y];
+                        line([0.2, 0.4, 1.0, 1.0], 1.0, line_data, c.transform, g);
+                    }
 
                     // Drawing the surfaces
                     for (i, surface) in view.world.surfaces.iter().enumerate() {
@@ -74,7 +110,14 @@ pub fn view_loop(mut view: ViewState) {
 
                         let line_data = [position_a.x, position_a.y, position_b.x, position_b.y];
 
-                        let mut color = [1.0, 0.0, 0.0, 1.0];
+                        let mut color = SURFACE_COLOR;
+
+                        // Surfaces involved in the last blow-up are orange
+                        if let Some(ref report) = view.world.health.report {
+                            if report.surfaces.contains(&i) {
+                                color = BROKEN_SURFACE_COLOR;
+                            }
+                        }
 
                         if let Some(sel_index) = view.sel_surface {
                             if sel_index == i {
@@ -82,13 +125,29 @@ pub fn view_loop(mut view: ViewState) {
                             }
                         }
 
-                        line(color, 1.0, line_data, c.transform, g);
+                        // Draw the surface as wide as its collision capsule
+                        let radius = (surface.thickness / 2.0 * view.scale).max(0.5);
+                        Line::new_round(color, radius).draw(
+                            line_data,
+                            &c.draw_state,
+                            c.transform,
+                            g,
+                        );
                     }
 
                     // Drawing the vertexes
                     for i in 0..view.world.verts.len() {
                         let vertex = view.world.verts[i].borrow();
-                        let mut color = [0.0, 0.0, 1.0, 1.0];
+                        let mut color = VERTEX_COLOR;
+
+                        // Vertices involved in the last blow-up are magenta
+                        if let Some(ref report) = view.world.health.report {
+                            if report.broken_vertices.contains(&i)
+                                || report.fast_vertices.contains(&i)
+                            {
+                                color = BROKEN_VERTEX_COLOR;
+                            }
+                        }
 
                         // If this is the selected vextex set the color to green
                         if let Some(sel_index) = view.sel_vertex {
@@ -103,6 +162,14 @@ pub fn view_loop(mut view: ViewState) {
                         ellipse(color, rect, c.transform, g);
                     }
 
+                    // Drawing the fluid particles
+                    let particle_radius = view.world.fluid.spacing() / 2.0 * view.scale;
+                    for particle in &view.world.fluid.particles {
+                        let position = view.to_screen_point(&particle.position);
+                        let rect = ellipse::circle(position.x, position.y, particle_radius);
+                        ellipse(PARTICLE_COLOR, rect, c.transform, g);
+                    }
+
                     // Drawing the debug vectors
                     for vector in &view.world.debug.vectors {
                         let start = vector.0;
@@ -115,7 +182,7 @@ pub fn view_loop(mut view: ViewState) {
                             view.to_screen_point(&(start + vec)).y,
                         ];
 
-                        line([0.0, 1.0, 1.0, 1.0], 1.0, line_data, c.transform, g);
+                        line(DEBUG_COLOR, 1.0, line_data, c.transform, g);
                     }
                 });
 
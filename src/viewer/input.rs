@@ -1,5 +1,6 @@
 use super::*;
 use Vector;
+use replay::Action;
 
 use piston_window::*;
 
@@ -111,6 +112,8 @@ pub fn handle_keyboard(view: &mut ViewState, input: &mut InputState, dt: f64) {
 
             Key::Q => view.edit_mode = EditMode::Select,
             Key::C => view.edit_mode = EditMode::Create,
+            Key::E => view.edit_mode = EditMode::Emit,
+            Key::R => view.edit_mode = EditMode::Region,
             _ => {}
         }
     }
@@ -121,8 +124,10 @@ pub fn handle_mouse(view: &mut ViewState, input: &mut InputState) {
     if let Some(button) = input.pressed_mouse {
         let mouse_position = view.to_world_point(&input.cursor);
 
-        if let EditMode::Create = view.edit_mode {
-            handle_edit(view, &input, &button);
+        match view.edit_mode {
+            EditMode::Create => handle_edit(view, input, &button),
+            EditMode::Region => handle_region(view, input, &button),
+            _ => {}
         }
 
         // Set the selected vertex to the vertex under the cursor
@@ -136,8 +141,10 @@ pub fn handle_mouse(view: &mut ViewState, input: &mut InputState) {
 
     // When the mouse button is being held
     if let Some(button) = input.held_mouse {
-        if let EditMode::Select = view.edit_mode {
-            handle_select(view, &input, &button);
+        match view.edit_mode {
+            EditMode::Select => handle_select(view, input, &button),
+            EditMode::Emit => handle_emit(view, input, &button),
+            _ => {}
         }
     }
 
@@ -155,34 +162,45 @@ fn handle_select(view: &mut ViewState, input: &InputState, button: &MouseButton)
         if let Some(index) = view.sel_vertex {
             if view.sim_speed != 0.0 {
                 // Move the selected vertex TOWARDS the cursor
-                let mut vertex = view.world.verts[index].borrow_mut();
-                let position = vertex.position;
+                let position = view.world.verts[index].borrow().position;
 
                 let mut force = mouse_position - position;
                 force = force.normalize() * view.pull_force as f64;
-                vertex.apply_force(force);
+                view.act(Action::Pull(index, force));
             } else {
-                // Move the selected vertex as much as the cursor has moved
-                let surfaces = view.world.get_vertex_surfaces(index);
-                let mut vertex = view.world.verts[index].borrow_mut();
-
+                // Move the selected vertex as much as the cursor has moved,
+                // adjusting the surface distances accordingly
                 let last_mouse = view.to_world_point(&input.last_cursor);
-                vertex.position += mouse_position - last_mouse;
-
-                // Adjust the surface distances accordingly
-                for i in surfaces {
-                    let surface = &mut view.world.surfaces[i];
+                view.act(Action::Drag(index, mouse_position - last_mouse));
+            }
+        }
+    }
+}
 
-                    let other_vertex = if surface.index_a == index {
-                        view.world.verts[surface.index_b].borrow()
-                    } else {
-                        view.world.verts[surface.index_a].borrow()
-                    };
+fn handle_emit(view: &mut ViewState, input: &InputState, button: &MouseButton) {
+    if let MouseButton::Left = *button {
+        let mouse_position = view.to_world_point(&input.cursor);
+        let radius = view.emitter_radius as f64;
+        view.act(Action::EmitFluid(mouse_position, radius));
+    }
+}
 
-                    surface.target_distance = (vertex.position - other_vertex.position).norm();
-                }
+fn handle_region(view: &mut ViewState, input: &InputState, button: &MouseButton) {
+    match *button {
+        // Add a corner to the region
+        MouseButton::Left => {
+            let mouse_position = view.to_world_point(&input.cursor);
+            view.region_points.push(mouse_position);
+        }
+        // Close the region if it has enough corners
+        MouseButton::Right => {
+            if view.region_points.len() >= 3 {
+                let points = view.region_points.clone();
+                view.act(Action::AddRegion(points));
             }
+            view.region_points.clear();
         }
+        _ => {}
     }
 }
 
@@ -197,13 +215,13 @@ fn handle_edit(view: &mut ViewState, input: &InputState, button: &MouseButton) {
                 // If there was an vertex already selected make a surface
                 if let Some(sel_index) = view.sel_vertex {
                     if sel_index != index {
-                        view.world.create_surface(index, sel_index);
+                        view.act(Action::CreateSurface(index, sel_index));
                     }
                 }
             }
             // If the user clicked on nothing create a new vertex
             else {
-                view.world.add_vertex(Vertex::new(mouse_position));
+                view.act(Action::AddVertex(mouse_position));
             }
         }
         MouseButton::Right => {
@@ -212,13 +230,13 @@ fn handle_edit(view: &mut ViewState, input: &InputState, button: &MouseButton) {
 
             // Remove the clicked vertex
             if let Some(vertex_index) = clicked_vertex {
-                view.world.remove_vertex(vertex_index);
+                view.act(Action::RemoveVertex(vertex_index));
                 view.sel_vertex = None;
             } else {
                 // Remove the clicked surface if any
                 let clicked_surface = view.world.get_surface_at(&mouse_position, 0.5);
                 if let Some(surface_index) = clicked_surface {
-                    view.world.surfaces.remove(surface_index);
+                    view.act(Action::RemoveSurface(surface_index));
                     view.sel_surface = None;
                 }
             }
@@ -3,12 +3,27 @@ pub mod input;
 pub mod imgui_piston;
 pub mod ui;
 
+use std::path::Path;
+
+use imgui::ImString;
 use Vector;
-use physics::simulation::{Vertex, World};
+use export::{Exporter, Quantity};
+use physics::error::WorldError;
+use physics::simulation::World;
+use physics::snapshot::Snapshot;
+use physics::validation::ValidationReport;
+use recording::{History, Recorder};
+use replay::{Action, InputRecorder};
+use scene::{Scene, ViewSettings};
+
+/// The number of steps kept to scrub back in time or after a replay
+const HISTORY_CAPACITY: usize = 900;
 
 pub enum EditMode {
     Select,
     Create,
+    Emit,
+    Region,
 }
 
 pub struct ViewState {
@@ -18,6 +33,7 @@ pub struct ViewState {
     iterations: u32,
     collisions: bool,
     pull_force: f32,
+    emitter_radius: f32,
 
     vertex_scale: f64,
     scale: f64,
@@ -27,10 +43,50 @@ pub struct ViewState {
     edit_mode: EditMode,
     sel_vertex: Option<usize>,
     sel_surface: Option<usize>,
+    /// The corners of the fluid region being drawn
+    region_points: Vec<Vector>,
+
+    material_path: ImString,
+    material_status: String,
+    /// The last error from editing the world
+    edit_error: Option<WorldError>,
+    /// The result of the last validation of the world
+    validation: ValidationReport,
+    /// The seed given to the world random number generator
+    seed: i32,
+    /// The state saved with the snapshot button
+    snapshot: Option<Snapshot>,
+
+    /// The last steps, to scrub back in time or play a recording
+    history: History,
+    /// The step of the history being shown, None when the simulation is live
+    playback: Option<usize>,
+    /// 1 when playing forward, -1 in reverse and 0 when paused
+    play_direction: i32,
+    recorder: Option<Recorder>,
+    recording_path: ImString,
+    recording_status: String,
+    /// Records the actions of the user, to replay them later
+    input_recorder: Option<InputRecorder>,
+    replay_path: ImString,
+    replay_status: String,
+    scene_path: ImString,
+    scene_status: String,
+    /// Writes the chosen quantities of every live step
+    exporter: Option<Exporter>,
+    export_path: ImString,
+    /// Whether each of `Quantity::ALL` is exported
+    export_quantities: Vec<bool>,
+    /// The position in `Format::ALL`
+    export_format: i32,
+    export_interval: i32,
+    export_status: String,
 }
 
 impl ViewState {
     pub fn new(world: World) -> ViewState {
+        let validation = world.validate();
+
         ViewState {
             world,
             sim_speed: 1.0,
@@ -38,6 +94,7 @@ impl ViewState {
             iterations: 8,
             collisions: true,
             pull_force: 250.0,
+            emitter_radius: 0.3,
             vertex_scale: 0.25,
             scale: 60.0,
             offset: Vector::new(0.0, 0.0),
@@ -45,7 +102,196 @@ impl ViewState {
             edit_mode: EditMode::Select,
             sel_vertex: None,
             sel_surface: None,
+            region_points: Vec::new(),
+            material_path: ImString::with_capacity(256),
+            material_status: String::new(),
+            edit_error: None,
+            validation,
+            seed: 0,
+            snapshot: None,
+            history: History::new(HISTORY_CAPACITY),
+            playback: None,
+            play_direction: 0,
+            recorder: None,
+            recording_path: ImString::with_capacity(256),
+            recording_status: String::new(),
+            input_recorder: None,
+            replay_path: ImString::with_capacity(256),
+            replay_status: String::new(),
+            scene_path: ImString::with_capacity(256),
+            scene_status: String::new(),
+            exporter: None,
+            export_path: ImString::with_capacity(256),
+            export_quantities: Quantity::ALL
+                .iter()
+                .map(|&quantity| quantity == Quantity::Position || quantity == Quantity::Velocity)
+                .collect(),
+            export_format: 0,
+            export_interval: 1,
+            export_status: String::new(),
+        }
+    }
+
+    pub fn settings(&self) -> ViewSettings {
+        ViewSettings {
+            scale: self.scale,
+            offset: self.offset,
+            sim_speed: self.sim_speed,
+            iterations: self.iterations,
+            collisions: self.collisions,
+            vertex_scale: self.vertex_scale,
+            pull_force: self.pull_force,
+            emitter_radius: self.emitter_radius,
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &ViewSettings) {
+        self.scale = settings.scale;
+        self.offset = settings.offset;
+        self.sim_speed = settings.sim_speed;
+        self.iterations = settings.iterations;
+        self.collisions = settings.collisions;
+        self.vertex_scale = settings.vertex_scale;
+        self.pull_force = settings.pull_force;
+        self.emitter_radius = settings.emitter_radius;
+    }
+
+    /// Replaces the world with the one of a scene, checking it right away
+    fn load_scene(&mut self, scene: &Scene) -> Result<(), WorldError> {
+        let world = scene.to_world()?;
+
+        self.stop_input_recording();
+        self.world = world;
+        if let Some(ref settings) = scene.view {
+            self.apply_settings(settings);
+        }
+        self.validation = self.world.validate();
+
+        self.history.clear();
+        self.playback = None;
+        self.play_direction = 0;
+        self.snapshot = None;
+        self.sel_vertex = None;
+        self.sel_surface = None;
+        self.region_points.clear();
+        Ok(())
+    }
+
+    /// Does something to the world, recording it if the input is being recorded
+    fn act(&mut self, action: Action) {
+        if let Some(ref mut recorder) = self.input_recorder {
+            recorder.flush(&self.world, self.iterations, self.collisions);
+        }
+
+        match action.apply(&mut self.world) {
+            Ok(()) => if let Some(ref mut recorder) = self.input_recorder {
+                recorder.record(&self.world, action);
+            },
+            Err(error) => self.edit_error = Some(error),
+        }
+    }
+
+    /// Starts recording the input, replays are always deterministic
+    fn start_input_recording(&mut self) {
+        self.world.deterministic = true;
+        // The replay can't know the impulses of the previous steps
+        self.world.solver.clear_cache();
+        self.input_recorder = Some(InputRecorder::start(
+            &self.world,
+            self.iterations,
+            self.collisions,
+        ));
+        self.replay_status = String::from("Recording input...");
+    }
+
+    /// Stops recording the input, saving the replay
+    fn stop_input_recording(&mut self) {
+        if let Some(recorder) = self.input_recorder.take() {
+            let replay = recorder.finish(&self.world);
+            let path = Path::new(self.replay_path.to_str()).to_owned();
+            self.replay_status = match replay.save(&path) {
+                Ok(()) => format!("Saved {} actions", replay.actions.len()),
+                Err(error) => format!("Error: {}", error),
+            };
+        }
+    }
+
+    /// Steps the world, or the playback when going through the history
+    fn step(&mut self) {
+        if let Some(step) = self.playback {
+            let last = self.history.len().saturating_sub(1) as i64;
+            let next = step as i64 + self.play_direction as i64;
+            if next < 0 || next > last {
+                self.play_direction = 0;
+            }
+            self.seek(i64::max(0, i64::min(next, last)) as usize);
+            return;
+        }
+
+        // In deterministic mode the world takes a fixed step every frame,
+        // the wall clock only decides whether it's paused
+        let paused = self.sim_speed == 0.0;
+        if self.world.deterministic && paused {
+            return;
+        }
+
+        if let Some(ref mut recorder) = self.input_recorder {
+            recorder.flush(&self.world, self.iterations, self.collisions);
+        }
+
+        self.world.update(
+            self.physics_dt * self.sim_speed,
+            self.iterations,
+            self.collisions,
+        );
+
+        if let Some(ref mut recorder) = self.input_recorder {
+            recorder.after_step(&self.world);
+        }
+
+        if !paused || self.world.deterministic {
+            self.history.push(self.world.snapshot());
+
+            let result = match self.recorder {
+                Some(ref mut recorder) => recorder.record(&self.world),
+                None => Ok(()),
+            };
+            if let Err(error) = result {
+                self.recorder = None;
+                self.recording_status = format!("Recording stopped: {}", error);
+            }
+
+            let result = match self.exporter {
+                Some(ref mut exporter) => exporter.record(&self.world),
+                None => Ok(()),
+            };
+            if let Err(error) = result {
+                self.exporter = None;
+                self.export_status = format!("Export stopped: {}", error);
+            }
+        }
+    }
+
+    /// Shows a step of the history, stopping the live simulation
+    fn seek(&mut self, step: usize) {
+        // Jumping in time can't be replayed
+        self.stop_input_recording();
+
+        if let Some(snapshot) = self.history.get(step) {
+            self.world.restore(snapshot);
+            self.playback = Some(step);
+            self.sel_vertex = None;
+            self.sel_surface = None;
+        }
+    }
+
+    /// Continues the simulation from the step being shown, forgetting the ones after it
+    fn resume(&mut self) {
+        if let Some(step) = self.playback {
+            self.history.truncate(step + 1);
         }
+        self.playback = None;
+        self.play_direction = 0;
     }
 
     fn to_screen_point(&self, point: &Vector) -> Vector {
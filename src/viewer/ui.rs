@@ -1,13 +1,26 @@
+use std::path::Path;
+
 use super::*;
 use super::input::InputState;
 
+use export::{Exporter, Format, Quantity};
 use imgui::*;
 use piston_window::*;
+use physics::health::HealthPolicy;
+use physics::material::{CombineRule, MaterialLibrary};
+use physics::regions::RegionShape;
+use recording::{self, History, Recorder};
+use replay::{Action, Replay};
+use scene::Scene;
 
 pub fn run_ui(ui: &mut Ui, view: &mut ViewState) -> (bool, bool) {
     let mut sim_speed = view.sim_speed as f32;
     let mut vertex_scale = view.vertex_scale as f32;
     let mut iterations = view.iterations as i32;
+    let mut restitution_threshold = view.world.solver.restitution_threshold as f32;
+    let mut restitution_threshold_edited = false;
+    let mut friction_rule = rule_index(view.world.solver.friction_rule);
+    let mut restitution_rule = rule_index(view.world.solver.restitution_rule);
 
     ui.window(im_str!("Simulation Settings"))
         .size((300.0, 100.0), ImGuiCond::FirstUseEver)
@@ -15,6 +28,21 @@ pub fn run_ui(ui: &mut Ui, view: &mut ViewState) -> (bool, bool) {
             ui.text(im_str!("Physics framerate: {}", 1.0 / view.physics_dt));
             ui.text(im_str!("Bodies: {}", view.world.verts.len()));
             ui.text(im_str!("Surfaces: {}", view.world.surfaces.len()));
+            ui.text(im_str!("Fluid particles: {}", view.world.fluid.particles.len()));
+
+            let mut dismiss_error = false;
+            if let Some(ref error) = view.edit_error {
+                ui.text_wrapped(im_str!("Edit error: {}", error));
+                dismiss_error = ui.button(im_str!("Dismiss##edit"), (0.0, 0.0));
+            }
+            if dismiss_error {
+                view.edit_error = None;
+            }
+
+            if ui.button(im_str!("Validate"), (0.0, 0.0)) {
+                view.validation = view.world.validate();
+            }
+            ui.text_wrapped(im_str!("{}", view.validation));
 
             ui.separator();
 
@@ -22,7 +50,240 @@ pub fn run_ui(ui: &mut Ui, view: &mut ViewState) -> (bool, bool) {
                 .build();
             ui.input_int(im_str!("Physics iterations"), &mut iterations)
                 .build();
+
+            {
+                let adaptive = &mut view.world.adaptive_steps;
+                let mut min_substeps = adaptive.min as i32;
+                let mut max_substeps = adaptive.max as i32;
+
+                ui.checkbox(im_str!("Adaptive substeps"), &mut adaptive.enabled);
+                ui.input_int(im_str!("Min substeps"), &mut min_substeps)
+                    .build();
+                ui.input_int(im_str!("Max substeps"), &mut max_substeps)
+                    .build();
+
+                adaptive.min = i32::max(min_substeps, 1) as u32;
+                adaptive.max = i32::max(max_substeps, adaptive.min as i32) as u32;
+            }
+            ui.text(im_str!("Substeps: {}", view.world.substeps));
+
+            {
+                let mut fixed_dt = view.world.fixed_dt as f32;
+                let mut seed = view.seed;
+
+                ui.checkbox(im_str!("Deterministic"), &mut view.world.deterministic);
+                // Only written back when edited, going through f32 would round it
+                if ui.input_float(im_str!("Fixed timestep"), &mut fixed_dt)
+                    .build()
+                {
+                    view.world.fixed_dt = f32::max(fixed_dt, 0.0001) as f64;
+                }
+                ui.input_int(im_str!("Seed"), &mut seed).build();
+                if seed != view.seed {
+                    view.seed = seed;
+                    view.act(Action::Seed(seed as u64));
+                }
+
+                ui.text(im_str!(
+                    "Step {} at {:.3}s, hash {:016x}",
+                    view.world.step,
+                    view.world.time,
+                    view.world.last_hash
+                ));
+            }
+
+            if ui.button(im_str!("Save snapshot"), (0.0, 0.0)) {
+                view.snapshot = Some(view.world.snapshot());
+            }
+            let mut restore_snapshot = false;
+            if let Some(ref snapshot) = view.snapshot {
+                ui.same_line(0.0);
+                restore_snapshot = ui.button(im_str!("Restore snapshot"), (0.0, 0.0));
+                ui.text(im_str!("Snapshot at step {}", snapshot.step));
+            }
+            if restore_snapshot {
+                // Jumping in time can't be replayed
+                view.stop_input_recording();
+                if let Some(ref snapshot) = view.snapshot {
+                    view.world.restore(snapshot);
+                }
+                view.sel_vertex = None;
+                view.sel_surface = None;
+            }
+
             ui.checkbox(im_str!("Collisions"), &mut view.collisions);
+            ui.checkbox(im_str!("Warm starting"), &mut view.world.solver.warm_starting);
+            restitution_threshold_edited = ui.input_float(
+                im_str!("Restitution threshold"),
+                &mut restitution_threshold,
+            ).build();
+
+            let rules = [
+                im_str!("Average"),
+                im_str!("Min"),
+                im_str!("Max"),
+                im_str!("Multiply"),
+            ];
+            ui.combo(im_str!("Friction combine"), &mut friction_rule, &rules, 4);
+            ui.combo(im_str!("Restitution combine"), &mut restitution_rule, &rules, 4);
+
+            ui.separator();
+
+            // Only written back when edited, going through f32 would round them
+            let mut air_density = view.world.air_density as f32;
+            let mut wind = [view.world.wind.x as f32, view.world.wind.y as f32];
+            if ui.input_float(im_str!("Air density"), &mut air_density)
+                .build()
+            {
+                view.world.air_density = f32::max(air_density, 0.0) as f64;
+            }
+            if ui.input_float2(im_str!("Wind"), &mut wind).build() {
+                view.world.wind = Vector::new(wind[0] as f64, wind[1] as f64);
+            }
+
+            ui.separator();
+
+            {
+                let health = &mut view.world.health;
+                let mut policy = HealthPolicy::ALL
+                    .iter()
+                    .position(|&other| other == health.policy)
+                    .unwrap_or(0) as i32;
+                let mut max_speed = health.max_speed as f32;
+
+                ui.checkbox(im_str!("Health monitor"), &mut health.enabled);
+                ui.combo(
+                    im_str!("Blow-up policy"),
+                    &mut policy,
+                    &[
+                        im_str!("Rollback"),
+                        im_str!("Clamp velocities"),
+                        im_str!("Freeze offenders"),
+                    ],
+                    3,
+                );
+                let max_speed_edited = ui.input_float(im_str!("Max speed"), &mut max_speed)
+                    .build();
+                let mut max_rollbacks = health.max_rollbacks as i32;
+                ui.input_int(im_str!("Max rollbacks"), &mut max_rollbacks)
+                    .build();
+                let mut snapshot_interval = health.snapshot_interval as i32;
+                ui.input_int(im_str!("Snapshot every n steps"), &mut snapshot_interval)
+                    .build();
+
+                health.policy = HealthPolicy::ALL[policy as usize];
+                // Only written back when edited, going through f32 would round it
+                if max_speed_edited {
+                    health.max_speed = f32::max(max_speed, 0.01) as f64;
+                }
+                health.max_rollbacks = i32::max(max_rollbacks, 0) as u32;
+                health.snapshot_interval = i32::max(snapshot_interval, 1) as u64;
+
+                if let Some(ref report) = health.report {
+                    ui.text_wrapped(im_str!("Blow-up: {}", report));
+                    if ui.button(im_str!("Dismiss"), (0.0, 0.0)) {
+                        health.report = None;
+                    }
+                }
+            }
+
+            ui.separator();
+
+            {
+                let interactions = &mut view.world.interactions;
+                let mut coulomb_constant = interactions.coulomb_constant as f32;
+                let mut gravitational_constant = interactions.gravitational_constant as f32;
+                let mut cohesion_distance = interactions.cohesion_distance as f32;
+                let mut theta = interactions.theta as f32;
+
+                // Only written back when edited, going through f32 would round them
+                ui.checkbox(im_str!("Coulomb"), &mut interactions.coulomb);
+                if ui.input_float(im_str!("Coulomb constant"), &mut coulomb_constant)
+                    .build()
+                {
+                    interactions.coulomb_constant = coulomb_constant as f64;
+                }
+                ui.checkbox(im_str!("Mutual gravity"), &mut interactions.gravity);
+                if ui.input_float(
+                    im_str!("Gravitational constant"),
+                    &mut gravitational_constant,
+                ).build()
+                {
+                    interactions.gravitational_constant = gravitational_constant as f64;
+                }
+                ui.checkbox(im_str!("Lennard-Jones"), &mut interactions.lennard_jones);
+                if ui.input_float(im_str!("Cohesion distance"), &mut cohesion_distance)
+                    .build()
+                {
+                    interactions.cohesion_distance = f32::max(cohesion_distance, 0.01) as f64;
+                }
+                if ui.slider_float(im_str!("Barnes-Hut theta"), &mut theta, 0.0, 1.5)
+                    .build()
+                {
+                    interactions.theta = theta as f64;
+                }
+            }
+
+            ui.separator();
+
+            // Only written back when edited, going through f32 would round them
+            let mut stiffness = view.world.fluid.stiffness as f32;
+            let mut viscosity = view.world.fluid.viscosity as f32;
+            if ui.input_float(im_str!("Fluid stiffness"), &mut stiffness)
+                .build()
+            {
+                view.world.fluid.stiffness = f32::max(stiffness, 0.0) as f64;
+            }
+            if ui.input_float(im_str!("Fluid viscosity"), &mut viscosity)
+                .build()
+            {
+                view.world.fluid.viscosity = f32::max(viscosity, 0.0) as f64;
+            }
+            ui.slider_float(im_str!("Emitter radius"), &mut view.emitter_radius, 0.0, 2.0)
+                .build();
+            if ui.button(im_str!("Clear fluid"), (0.0, 0.0)) {
+                view.act(Action::ClearFluid);
+            }
+
+            ui.separator();
+
+            ui.input_text(im_str!("Scene file"), &mut view.scene_path)
+                .build();
+            let scene_path = Path::new(view.scene_path.to_str()).to_owned();
+            if ui.button(im_str!("Save scene"), (0.0, 0.0)) {
+                let scene = Scene::new(&view.world, Some(view.settings()));
+                view.scene_status = match scene.save(&scene_path) {
+                    Ok(()) => String::from("Saved"),
+                    Err(error) => format!("Error: {}", error),
+                };
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Load scene"), (0.0, 0.0)) {
+                view.scene_status = match Scene::load(&scene_path) {
+                    Ok((scene, report)) => match view.load_scene(&scene) {
+                        Ok(()) => report.to_string(),
+                        Err(error) => format!("Error: {}", error),
+                    },
+                    Err(error) => format!("Error: {}", error),
+                };
+            }
+            ui.text(&view.scene_status);
+
+            ui.separator();
+
+            ui.input_text(im_str!("Materials file"), &mut view.material_path)
+                .build();
+            if ui.button(im_str!("Load materials"), (0.0, 0.0)) {
+                let path = Path::new(view.material_path.to_str()).to_owned();
+                match view.world.materials.load(&path) {
+                    Ok(count) => {
+                        view.world.reapply_materials();
+                        view.material_status = format!("Loaded {} materials", count);
+                    }
+                    Err(error) => view.material_status = format!("Error: {}", error),
+                }
+            }
+            ui.text(&view.material_status);
 
             ui.separator();
 
@@ -39,63 +300,419 @@ pub fn run_ui(ui: &mut Ui, view: &mut ViewState) -> (bool, bool) {
     view.sim_speed = sim_speed as f64;
     view.vertex_scale = vertex_scale as f64;
     view.iterations = if iterations < 0 { 0 } else { iterations as u32 };
+    // Only written back when edited, going through f32 would round it
+    if restitution_threshold_edited {
+        view.world.solver.restitution_threshold = f32::max(restitution_threshold, 0.0) as f64;
+    }
+    view.world.solver.friction_rule = CombineRule::ALL[friction_rule as usize];
+    view.world.solver.restitution_rule = CombineRule::ALL[restitution_rule as usize];
+
+    let mut removed_region = None;
+    let mut add_level = false;
+    ui.window(im_str!("Fluid regions"))
+        .size((300.0, 200.0), ImGuiCond::FirstUseEver)
+        .build(|| {
+            add_level = ui.button(im_str!("Add water level"), (0.0, 0.0));
+            ui.text(im_str!("Draw polygons with R, left click adds a corner, right click closes"));
+
+            for (i, region) in view.world.regions.iter_mut().enumerate() {
+                ui.with_id(i as i32, || {
+                    ui.separator();
+                    // Only written back when edited, going through f32 would round them
+                    if let RegionShape::Level(ref mut height) = region.shape {
+                        let mut input_height = *height as f32;
+                        if ui.input_float(im_str!("Level"), &mut input_height).build() {
+                            *height = input_height as f64;
+                        }
+                    } else {
+                        ui.text(im_str!("Polygon region {}", i));
+                    }
+
+                    let mut density = region.density as f32;
+                    let mut linear_drag = region.linear_drag as f32;
+                    let mut quadratic_drag = region.quadratic_drag as f32;
+                    if ui.input_float(im_str!("Density"), &mut density).build() {
+                        region.density = f32::max(density, 0.0) as f64;
+                    }
+                    if ui.input_float(im_str!("Linear drag"), &mut linear_drag)
+                        .build()
+                    {
+                        region.linear_drag = f32::max(linear_drag, 0.0) as f64;
+                    }
+                    if ui.input_float(im_str!("Quadratic drag"), &mut quadratic_drag)
+                        .build()
+                    {
+                        region.quadratic_drag = f32::max(quadratic_drag, 0.0) as f64;
+                    }
+
+                    if ui.button(im_str!("Remove"), (0.0, 0.0)) {
+                        removed_region = Some(i);
+                    }
+                });
+            }
+        });
+
+    if add_level {
+        view.act(Action::AddLevel(0.0));
+    }
+    if let Some(index) = removed_region {
+        view.act(Action::RemoveRegion(index));
+    }
+
+    ui.window(im_str!("Export"))
+        .size((300.0, 250.0), ImGuiCond::FirstUseEver)
+        .build(|| {
+            ui.input_text(im_str!("Export file"), &mut view.export_path)
+                .build();
+            ui.combo(
+                im_str!("Format"),
+                &mut view.export_format,
+                &[im_str!("CSV"), im_str!("JSON lines")],
+                2,
+            );
+            ui.input_int(im_str!("Every n steps"), &mut view.export_interval)
+                .build();
+            view.export_interval = i32::max(view.export_interval, 1);
+            let quantities = Quantity::ALL.iter().zip(view.export_quantities.iter_mut());
+            for (quantity, export) in quantities {
+                ui.checkbox(&ImString::new(quantity.name()), export);
+            }
+
+            if view.exporter.is_some() {
+                if ui.button(im_str!("Stop export"), (0.0, 0.0)) {
+                    let exporter = view.exporter.take().unwrap();
+                    let samples = exporter.samples;
+                    view.export_status = match exporter.finish() {
+                        Ok(()) => format!("Exported {} steps", samples),
+                        Err(error) => format!("Error: {}", error),
+                    };
+                }
+            } else if ui.button(im_str!("Start export"), (0.0, 0.0)) {
+                let path = Path::new(view.export_path.to_str()).to_owned();
+                let quantities = Quantity::ALL
+                    .iter()
+                    .zip(view.export_quantities.iter())
+                    .filter(|&(_, &export)| export)
+                    .map(|(&quantity, _)| quantity)
+                    .collect();
+                let format = Format::ALL[view.export_format as usize];
+                let interval = view.export_interval as u64;
+                view.export_status = match Exporter::create(&path, format, quantities, interval) {
+                    Ok(exporter) => {
+                        view.exporter = Some(exporter);
+                        String::from("Exporting...")
+                    }
+                    Err(error) => format!("Error: {}", error),
+                };
+            }
+            ui.text(&view.export_status);
+        });
+
+    // The first entry stands for properties not coming from any material
+    let mut material_names = vec![ImString::new("(custom)")];
+    for material in &view.world.materials.materials {
+        material_names.push(ImString::new(material.name.clone()));
+    }
+    let material_items: Vec<&ImStr> = material_names.iter().map(|name| name.as_ref()).collect();
 
     if let Some(index) = view.sel_vertex {
-        let mut vertex = view.world.verts[index].borrow_mut();
-        // Store the mass to later check for edge cases
-        let mut input_mass = vertex.mass;
-        ui.window(im_str!("Vertex"))
-            .size((300.0, 600.0), ImGuiCond::FirstUseEver)
-            .build(|| {
-                ui.text(im_str!("ID: {}", index));
-                ui.text(im_str!(
-                    "Position: {:.2}, {:.2}",
-                    vertex.position.x,
-                    vertex.position.y
-                ));
-                ui.text(im_str!(
-                    "Velocity: {:.2}, {:.2}",
-                    vertex.velocity.x,
-                    vertex.velocity.y
-                ));
+        let mut material;
+        // The material before the combo, the setters only run when it changes
+        let selected;
+        let mut apply_to_body = false;
+        let body;
+        {
+            let mut vertex = view.world.verts[index].borrow_mut();
+            material = material_index(&view.world.materials, &vertex.material);
+            selected = material;
+            body = vertex.body;
+            // Store the mass to later check for edge cases
+            let mut input_mass = vertex.mass;
+            let mut edited = false;
+            ui.window(im_str!("Vertex"))
+                .size((300.0, 600.0), ImGuiCond::FirstUseEver)
+                .build(|| {
+                    ui.text(im_str!("ID: {}", index));
+                    ui.text(im_str!("Body: {}", body));
+                    ui.text(im_str!(
+                        "Position: {:.2}, {:.2}",
+                        vertex.position.x,
+                        vertex.position.y
+                    ));
+                    ui.text(im_str!(
+                        "Velocity: {:.2}, {:.2}",
+                        vertex.velocity.x,
+                        vertex.velocity.y
+                    ));
+
+                    ui.combo(im_str!("Material"), &mut material, &material_items, 8);
+                    apply_to_body = ui.button(im_str!("Apply to body"), (0.0, 0.0));
+
+                    edited |= ui.input_float(im_str!("Mass"), &mut input_mass).build();
+                    ui.checkbox(im_str!("Static"), &mut vertex.is_static);
+
+                    edited |=
+                        ui.input_float(im_str!("Static friction"), &mut vertex.static_friction)
+                            .build();
+                    edited |=
+                        ui.input_float(im_str!("Dynamic friction"), &mut vertex.dynamic_friction)
+                            .build();
+                    edited |= ui.input_float(im_str!("Restitution"), &mut vertex.restitution)
+                        .build();
 
-                ui.input_float(im_str!("Mass"), &mut input_mass).build();
-                ui.checkbox(im_str!("Static"), &mut vertex.is_static);
-            });
+                    ui.input_float(im_str!("Charge"), &mut vertex.charge).build();
+                    ui.input_float(im_str!("Cohesion"), &mut vertex.cohesion)
+                        .build();
+                });
 
-        // Set the mass only if the input is not 0
-        vertex.mass = if input_mass != 0.0 {
-            input_mass
-        } else {
-            vertex.mass
-        };
+            // Set the mass only if the input is not 0
+            vertex.mass = if input_mass != 0.0 {
+                input_mass
+            } else {
+                vertex.mass
+            };
+
+            // Edited properties don't come from the material anymore
+            if edited {
+                vertex.material = None;
+                material = 0;
+            }
+        }
+
+        if material > 0 && (apply_to_body || material != selected) {
+            let name = material_names[material as usize].to_str().to_owned();
+            // The names come from the library and the vertex is selected, so this can't fail
+            let _ = if apply_to_body {
+                view.world.set_body_material(body, &name)
+            } else {
+                view.world.set_vertex_material(index, &name)
+            };
+        } else if material == 0 && material != selected {
+            view.world.verts[index].borrow_mut().material = None;
+        }
     }
 
     if let Some(index) = view.sel_surface {
-        let surface = &mut view.world.surfaces[index];
-        ui.window(im_str!("Surface"))
-            .size((300.0, 600.0), ImGuiCond::FirstUseEver)
-            .build(|| {
-                ui.text(im_str!("ID: {}", index));
-                ui.text(im_str!("Vertex A: {}", surface.index_a));
-                ui.text(im_str!("Vertex B: {}", surface.index_b));
+        let mut material;
+        let selected;
+        let mut apply_to_body = false;
+        {
+            let surface = &mut view.world.surfaces[index];
+            material = material_index(&view.world.materials, &surface.material);
+            selected = material;
+            let mut thickness = surface.thickness as f32;
+            let mut thickness_edited = false;
+            let mut edited = false;
+            ui.window(im_str!("Surface"))
+                .size((300.0, 600.0), ImGuiCond::FirstUseEver)
+                .build(|| {
+                    ui.text(im_str!("ID: {}", index));
+                    ui.text(im_str!("Vertex A: {}", surface.index_a));
+                    ui.text(im_str!("Vertex B: {}", surface.index_b));
 
-                ui.text(im_str!("Target distance: {}", surface.target_distance));
+                    ui.text(im_str!("Target distance: {}", surface.target_distance));
 
-                ui.input_float(im_str!("Damping ratio"), &mut surface.damping_ratio)
-                    .build();
-                ui.input_float(im_str!("Strength"), &mut surface.strength)
-                    .build();
-                ui.input_float(im_str!("Friction"), &mut surface.friction)
-                    .build();
-                ui.input_float(im_str!("Resitution"), &mut surface.restitution)
-                    .build();
-            });
+                    ui.combo(im_str!("Material"), &mut material, &material_items, 8);
+                    apply_to_body = ui.button(im_str!("Apply to body"), (0.0, 0.0));
+
+                    edited |= ui.input_float(im_str!("Damping ratio"), &mut surface.damping_ratio)
+                        .build();
+                    edited |= ui.input_float(im_str!("Strength"), &mut surface.strength)
+                        .build();
+                    edited |=
+                        ui.input_float(im_str!("Static friction"), &mut surface.static_friction)
+                            .build();
+                    edited |=
+                        ui.input_float(im_str!("Dynamic friction"), &mut surface.dynamic_friction)
+                            .build();
+                    edited |= ui.input_float(im_str!("Resitution"), &mut surface.restitution)
+                        .build();
+                    thickness_edited =
+                        ui.input_float(im_str!("Thickness"), &mut thickness).build();
+                    ui.input_float(im_str!("Drag coefficient"), &mut surface.drag_coefficient)
+                        .build();
+                    ui.input_float(im_str!("Lift coefficient"), &mut surface.lift_coefficient)
+                        .build();
+                });
+
+            // Only written back when edited, going through f32 would round it
+            if thickness_edited && thickness >= 0.0 {
+                surface.thickness = thickness as f64;
+            }
+
+            // Edited properties don't come from the material anymore
+            if edited {
+                surface.material = None;
+                material = 0;
+            }
+        }
+
+        if material > 0 && (apply_to_body || material != selected) {
+            let name = material_names[material as usize].to_str().to_owned();
+            // The names come from the library and the surface is selected, so this can't fail
+            let _ = if apply_to_body {
+                let body = view.world.verts[view.world.surfaces[index].index_a]
+                    .borrow()
+                    .body;
+                view.world.set_body_material(body, &name)
+            } else {
+                view.world.set_surface_material(index, &name)
+            };
+        } else if material == 0 && material != selected {
+            view.world.surfaces[index].material = None;
+        }
     }
 
+    ui.window(im_str!("Timeline"))
+        .size((300.0, 150.0), ImGuiCond::FirstUseEver)
+        .build(|| {
+            let len = view.history.len();
+            if len > 0 {
+                let last = len - 1;
+                let mut step = view.playback.unwrap_or(last) as i32;
+                if ui.slider_int(im_str!("Step"), &mut step, 0, last as i32)
+                    .build()
+                {
+                    view.seek(step as usize);
+                    view.play_direction = 0;
+                }
+                let step = step as usize;
+
+                if ui.button(im_str!("|<"), (0.0, 0.0)) {
+                    view.seek(step.saturating_sub(1));
+                    view.play_direction = 0;
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("<"), (0.0, 0.0)) {
+                    view.seek(step);
+                    view.play_direction = -1;
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!("||"), (0.0, 0.0)) {
+                    view.seek(step);
+                    view.play_direction = 0;
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!(">"), (0.0, 0.0)) {
+                    view.seek(step);
+                    view.play_direction = 1;
+                }
+                ui.same_line(0.0);
+                if ui.button(im_str!(">|"), (0.0, 0.0)) {
+                    view.seek(usize::min(step + 1, last));
+                    view.play_direction = 0;
+                }
+
+                if view.playback.is_some() {
+                    if ui.button(im_str!("Resume from here"), (0.0, 0.0)) {
+                        view.resume();
+                    }
+                    ui.same_line(0.0);
+                    if ui.button(im_str!("Back to live"), (0.0, 0.0)) {
+                        view.seek(last);
+                        view.resume();
+                    }
+                }
+            } else {
+                ui.text(im_str!("Run the simulation to fill the timeline"));
+            }
+
+            ui.separator();
+
+            ui.input_text(im_str!("Recording file"), &mut view.recording_path)
+                .build();
+            let path = Path::new(view.recording_path.to_str()).to_owned();
+            if view.recorder.is_some() {
+                if ui.button(im_str!("Stop recording"), (0.0, 0.0)) {
+                    let recorder = view.recorder.take().unwrap();
+                    let frames = recorder.frames;
+                    view.recording_status = match recorder.finish() {
+                        Ok(()) => format!("Recorded {} steps", frames),
+                        Err(error) => format!("Error: {}", error),
+                    };
+                }
+            } else if ui.button(im_str!("Record"), (0.0, 0.0)) {
+                match Recorder::create(&path) {
+                    Ok(recorder) => {
+                        view.recorder = Some(recorder);
+                        view.recording_status = String::from("Recording...");
+                    }
+                    Err(error) => view.recording_status = format!("Error: {}", error),
+                }
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Play recording"), (0.0, 0.0)) {
+                match recording::load(&path) {
+                    Ok(mut history) => {
+                        view.recording_status = format!("Loaded {} steps", history.len());
+                        history.capacity = usize::max(history.len(), view.history.capacity);
+                        view.history = history;
+                        view.playback = None;
+                        view.seek(0);
+                        view.play_direction = 1;
+                    }
+                    Err(error) => view.recording_status = format!("Error: {}", error),
+                }
+            }
+            ui.text(&view.recording_status);
+
+            ui.separator();
+
+            ui.input_text(im_str!("Replay file"), &mut view.replay_path)
+                .build();
+            if view.input_recorder.is_some() {
+                if ui.button(im_str!("Stop recording input"), (0.0, 0.0)) {
+                    view.stop_input_recording();
+                }
+            } else if ui.button(im_str!("Record input"), (0.0, 0.0)) {
+                view.start_input_recording();
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Run replay"), (0.0, 0.0)) {
+                view.stop_input_recording();
+                let path = Path::new(view.replay_path.to_str()).to_owned();
+                let mut history = History::new(HISTORY_CAPACITY);
+                let result = Replay::load(&path)
+                    .map_err(|error| error.to_string())
+                    .and_then(|replay| replay.run(|world| history.push(world.snapshot())));
+                match result {
+                    Ok(world) => {
+                        view.replay_status = format!("Replayed up to step {}", world.step);
+                        view.world = world;
+                        view.history = history;
+                        view.playback = None;
+                        view.play_direction = 0;
+                        view.sel_vertex = None;
+                        view.sel_surface = None;
+                    }
+                    Err(error) => view.replay_status = format!("Error: {}", error),
+                }
+            }
+            ui.text(&view.replay_status);
+        });
+
     (ui.want_capture_mouse(), ui.want_capture_keyboard())
 }
 
+/// The position of a material in the material combo, 0 being no material
+fn material_index(materials: &MaterialLibrary, name: &Option<String>) -> i32 {
+    name.as_ref()
+        .and_then(|name| {
+            materials
+                .materials
+                .iter()
+                .position(|material| &material.name == name)
+        })
+        .map_or(0, |index| index as i32 + 1)
+}
+
+fn rule_index(rule: CombineRule) -> i32 {
+    CombineRule::ALL
+        .iter()
+        .position(|&other| other == rule)
+        .unwrap_or(0) as i32
+}
+
 pub fn configure_keys(imgui: &mut ImGui) {
     use imgui::ImGuiKey;
 
@@ -0,0 +1,122 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World, GRAVITY};
+
+/// A plate of length 1 from `a` to `b`, moving at `velocity`
+fn plate(a: Vector2<f64>, b: Vector2<f64>, velocity: Vector2<f64>) -> World {
+    let mut world = World::new();
+    for &position in &[a, b] {
+        let mut vertex = Vertex::new(position);
+        vertex.velocity = velocity;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    world
+}
+
+/// The force the air puts on each end of the plate
+fn force(world: &World, air_density: f64, wind: Vector2<f64>) -> Vector2<f64> {
+    for vertex in &world.verts {
+        vertex.borrow_mut().acceleration = Vector2::new(0.0, 0.0);
+    }
+    world.surfaces[0].apply_aerodynamics(&world.verts, air_density, wind);
+
+    let a = world.verts[0].borrow();
+    let b = world.verts[1].borrow();
+    assert_eq!(a.acceleration, b.acceleration);
+    a.acceleration * a.mass as f64
+}
+
+#[test]
+fn drag_opposes_the_motion() {
+    let world = plate(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(0.0, -2.0),
+    );
+    assert_eq!(force(&world, 0.0, Vector2::new(0.0, 0.0)), Vector2::new(0.0, 0.0));
+
+    // 1/2 rho L Cd v^2, shared by the two ends
+    let drag = force(&world, 1.2, Vector2::new(0.0, 0.0));
+    let expected = 0.5 * 1.2 * 1.0 * 1.0 * 4.0 / 2.0;
+    assert!(drag.x.abs() < 1e-12);
+    assert!((drag.y - expected).abs() < 1e-9, "{} instead of {}", drag.y, expected);
+
+    // Only the relative velocity counts, going with the wind there is no drag
+    let along = force(&world, 1.2, Vector2::new(0.0, -2.0));
+    assert_eq!(along, Vector2::new(0.0, 0.0));
+}
+
+#[test]
+fn air_sliding_along_the_plate_does_nothing() {
+    let world = plate(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(3.0, 0.0),
+    );
+    assert_eq!(force(&world, 1.2, Vector2::new(0.0, 0.0)).norm(), 0.0);
+}
+
+#[test]
+fn tilted_plate_gets_lift() {
+    // A plate tilted by 15 degrees with its front end up, in a wind blowing from the front
+    let angle = 15f64.to_radians();
+    let world = plate(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(-angle.cos(), -angle.sin()),
+        Vector2::new(0.0, 0.0),
+    );
+    let wind = Vector2::new(-10.0, 0.0);
+    let with_lift = force(&world, 1.2, wind);
+    assert!(with_lift.y > 0.0, "pushed down by {}", with_lift.y);
+    assert!(with_lift.x < 0.0);
+
+    let mut without_lift = world;
+    without_lift.surfaces[0].lift_coefficient = 0.0;
+    let drag_only = force(&without_lift, 1.2, wind);
+    assert!(with_lift.y > drag_only.y);
+}
+
+#[test]
+fn falling_plate_reaches_terminal_velocity() {
+    let mut world = plate(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(1.0, 0.0),
+        Vector2::new(0.0, 0.0),
+    );
+    world.air_density = 1.0;
+
+    for _ in 0..600 {
+        world.update(1.0 / 60.0, 8, false);
+    }
+
+    // The drag carries the weight: 1/2 rho L Cd v^2 = 2 m g
+    let mass = world.verts[0].borrow().mass as f64;
+    let terminal = (4.0 * mass * GRAVITY / world.air_density).sqrt();
+    for vertex in &world.verts {
+        let velocity = vertex.borrow().velocity;
+        assert!((velocity.y + terminal).abs() < 1e-3, "{} instead of {}", velocity.y, -terminal);
+    }
+}
+
+#[test]
+fn wind_carries_the_plate() {
+    let mut world = plate(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(0.0, 1.0),
+        Vector2::new(0.0, 0.0),
+    );
+    world.air_density = 1.0;
+    world.wind = Vector2::new(4.0, 0.0);
+
+    for _ in 0..600 {
+        world.update(1.0 / 60.0, 8, false);
+    }
+    // Once it moves as fast as the wind there is no drag left to speed it up
+    for vertex in &world.verts {
+        let velocity = vertex.borrow().velocity;
+        assert!(velocity.x > 3.5 && velocity.x <= 4.0, "{}", velocity.x);
+    }
+}
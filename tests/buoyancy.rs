@@ -0,0 +1,92 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::regions::FluidRegion;
+use spring::physics::simulation::World;
+use spring::shapes;
+
+/// An octagon dropped in water of `density` with the surface at 0,
+/// returns its average height and how many of its vertices are under water once settled
+fn float(density: f64) -> (World, f64, usize) {
+    let mut world = World::new();
+    shapes::make_polygon(&mut world, Vector2::new(0.0, 0.0), 1.0, 8).unwrap();
+    let mut water = FluidRegion::level(0.0);
+    water.density = density;
+    world.regions.push(water);
+
+    for _ in 0..1200 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+
+    let count = world.verts.len();
+    let height = world
+        .verts
+        .iter()
+        .map(|vertex| vertex.borrow().position.y)
+        .sum::<f64>() / count as f64;
+    let submerged = world
+        .verts
+        .iter()
+        .filter(|vertex| world.regions[0].contains(&vertex.borrow().position))
+        .count();
+    (world, height, submerged)
+}
+
+#[test]
+fn floating_body_displaces_its_weight() {
+    for &density in &[0.2, 0.5, 1.0] {
+        let (world, height, submerged) = float(density);
+
+        // Each submerged vertex is pushed up by its share of the displaced water,
+        // so at rest enough of them are under water to carry the weight
+        let (area, count) = world.body_areas()[&0];
+        let mass: f64 = world.verts.iter().map(|vertex| vertex.borrow().mass as f64).sum();
+        let expected = mass * count as f64 / (density * area);
+        assert!(
+            (submerged as f64 - expected).abs() <= 1.5,
+            "density {}: {} submerged, expected {}",
+            density,
+            submerged,
+            expected
+        );
+        assert!(height.abs() < 1.0, "density {}: floats at {}", density, height);
+    }
+}
+
+#[test]
+fn denser_water_floats_bodies_higher() {
+    let heights: Vec<f64> = [0.2, 0.5, 1.0]
+        .iter()
+        .map(|&density| float(density).1)
+        .collect();
+    assert!(heights[0] < heights[1] && heights[1] < heights[2], "{:?}", heights);
+}
+
+#[test]
+fn dense_body_sinks() {
+    // Even fully submerged the water can't carry it
+    let (_, height, submerged) = float(0.1);
+    assert_eq!(submerged, 9);
+    assert!(height < -5.0, "stopped at {}", height);
+}
+
+#[test]
+fn drag_slows_bodies_in_water() {
+    let mut world = World::new();
+    shapes::make_polygon(&mut world, Vector2::new(0.0, 0.0), 1.0, 8).unwrap();
+    let mut water = FluidRegion::level(10.0);
+    water.density = 0.0;
+    water.linear_drag = 0.5;
+    world.regions.push(water);
+    for vertex in &world.verts {
+        vertex.borrow_mut().velocity = Vector2::new(5.0, 0.0);
+    }
+
+    for _ in 0..120 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+    for vertex in &world.verts {
+        assert!(vertex.borrow().velocity.x < 1.0);
+    }
+}
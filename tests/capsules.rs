@@ -0,0 +1,71 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::collisions;
+use spring::physics::simulation::{Vertex, World};
+
+/// A static floor from -2 to 2 at height 0, with a vertex above its middle
+fn floor_world(thickness: f64, height: f64) -> World {
+    let mut world = World::new();
+    for &x in &[-2.0, 2.0] {
+        let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    world.surfaces[0].thickness = thickness;
+    world.add_vertex(Vertex::new(Vector2::new(0.0, height)));
+    world
+}
+
+#[test]
+fn capsule_includes_the_thickness() {
+    let mut world = floor_world(0.1, 0.04);
+    let dt = 1.0 / 120.0;
+    let (vertex, a, b) = (
+        world.verts[2].borrow(),
+        world.verts[0].borrow(),
+        world.verts[1].borrow(),
+    );
+    assert!(collisions::colliding(&vertex, &a, &b, &world.surfaces[0], dt));
+
+    world.surfaces[0].thickness = 0.06;
+    assert!(!collisions::colliding(&vertex, &a, &b, &world.surfaces[0], dt));
+}
+
+#[test]
+fn capsule_distance_is_measured_from_the_segment() {
+    let a = Vector2::new(-1.0, 0.0);
+    let b = Vector2::new(1.0, 0.0);
+    assert_eq!(collisions::segment_distance(&Vector2::new(0.0, 0.5), &a, &b), 0.5);
+    // Past the ends the capsule is round
+    assert_eq!(collisions::segment_distance(&Vector2::new(4.0, 4.0), &a, &b), 5.0);
+}
+
+/// The lowest and the highest the vertex gets after bouncing, in 4 seconds
+fn bounce(thickness: f64) -> (f64, f64) {
+    let mut world = floor_world(thickness, 1.0);
+    let mut lowest = f64::MAX;
+    let mut highest = f64::MIN;
+    for _ in 0..240 {
+        world.update(1.0 / 60.0, 8, true);
+        let height = world.verts[2].borrow().position.y;
+        lowest = lowest.min(height);
+        if lowest < 0.5 {
+            highest = highest.max(height);
+        }
+    }
+    (lowest, highest)
+}
+
+#[test]
+fn vertex_bounces_off_the_capsule_surface() {
+    let (lowest, highest) = bounce(0.2);
+    assert!(lowest > 0.095 && lowest < 0.15, "lowest {}", lowest);
+    assert!(highest > 0.5, "only bounced to {}", highest);
+
+    // A thinner surface lets the vertex get closer
+    let (lowest, _) = bounce(0.02);
+    assert!(lowest > 0.0 && lowest < 0.05, "lowest {}", lowest);
+}
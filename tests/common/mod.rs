@@ -0,0 +1,50 @@
+//! The scenes shared by the tests, not every test uses all of them
+#![allow(dead_code)]
+
+use nalgebra::Vector2;
+use spring::physics::regions::FluidRegion;
+use spring::physics::simulation::{Vertex, World};
+use spring::shapes;
+
+/// An octagon above a static floor, in deterministic mode
+pub fn floor_world() -> World {
+    let mut world = World::new();
+    world.deterministic = true;
+
+    shapes::make_polygon(&mut world, Vector2::new(0.0, 0.0), 2.0, 8).unwrap();
+    let first = world.verts.len();
+    for &x in &[-20.0, 20.0] {
+        let mut vertex = Vertex::new(Vector2::new(x, -5.0));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(first, first + 1).unwrap();
+    world
+}
+
+/// The octagon falling into a pool with some air, wind, fluid and a second region
+pub fn busy_world() -> World {
+    let mut world = floor_world();
+    world.air_density = 0.25;
+    world.wind = Vector2::new(1.0, 0.0);
+    world.verts[3].borrow_mut().material = Some(String::from("rubber"));
+
+    world.regions.push(FluidRegion::level(-4.0));
+    world.regions.push(FluidRegion::polygon(vec![
+        Vector2::new(5.0, -5.0),
+        Vector2::new(8.0, -5.0),
+        Vector2::new(8.0, -2.0),
+    ]));
+    world.emit_fluid(Vector2::new(6.0, 2.0), 0.5);
+    world
+}
+
+/// The hash after each of the next steps
+pub fn run(world: &mut World, steps: usize) -> Vec<u64> {
+    (0..steps)
+        .map(|_| {
+            world.update(0.0, 8, true);
+            world.last_hash
+        })
+        .collect()
+}
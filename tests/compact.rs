@@ -0,0 +1,134 @@
+extern crate nalgebra;
+extern crate spring;
+
+mod common;
+
+use std::io::Cursor;
+
+use spring::compact::{self, Precision};
+use spring::physics::simulation::World;
+
+/// The busy scene run for a while so that the contacts are warm started
+fn busy_world() -> World {
+    let mut world = common::busy_world();
+    common::run(&mut world, 360);
+    world
+}
+
+fn encode(world: &World, precision: Precision) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    compact::write_world(&mut buffer, world, precision).unwrap();
+    buffer
+}
+
+fn decode(buffer: &[u8]) -> World {
+    compact::read_world(&mut Cursor::new(buffer)).unwrap()
+}
+
+#[test]
+fn world_round_trip_runs_identically() {
+    let mut world = busy_world();
+    assert!(!world.solver.cache().is_empty());
+
+    let mut loaded = decode(&encode(&world, Precision::Double));
+    assert_eq!(loaded.state_hash(), world.state_hash());
+    assert_eq!(loaded.step, world.step);
+    assert_eq!(loaded.air_density, 0.25);
+    assert_eq!(loaded.regions.len(), 2);
+    assert_eq!(loaded.solver.cache().len(), world.solver.cache().len());
+    assert_eq!(loaded.verts[3].borrow().material, Some(String::from("rubber")));
+    assert!(loaded.validate().is_valid());
+
+    assert_eq!(common::run(&mut loaded, 240), common::run(&mut world, 240));
+}
+
+#[test]
+fn snapshot_round_trip_runs_identically() {
+    let mut world = busy_world();
+    let snapshot = world.snapshot();
+
+    let mut buffer = Vec::new();
+    compact::write_snapshot(&mut buffer, &snapshot, Precision::Double).unwrap();
+    let loaded = compact::read_snapshot(&mut Cursor::new(&buffer)).unwrap();
+
+    // The bodies aren't written, restoring finds them again
+    assert!(loaded.verts.iter().all(|vertex| vertex.body == 0));
+    let expected = common::run(&mut world, 240);
+    world.restore(&loaded);
+    let floor = world.verts.len() - 1;
+    assert_eq!(world.verts[floor].borrow().body, floor - 1);
+    assert_eq!(common::run(&mut world, 240), expected);
+}
+
+#[test]
+fn single_precision_is_smaller_and_close() {
+    let world = busy_world();
+    let double = encode(&world, Precision::Double);
+    let single = encode(&world, Precision::Single);
+    assert!(single.len() < double.len());
+
+    let loaded = decode(&single);
+    assert_eq!(loaded.verts.len(), world.verts.len());
+    assert_eq!(loaded.surfaces.len(), world.surfaces.len());
+    assert_eq!(loaded.fluid.particles.len(), world.fluid.particles.len());
+    for (a, b) in loaded.verts.iter().zip(world.verts.iter()) {
+        let (a, b) = (a.borrow(), b.borrow());
+        assert!((a.position - b.position).norm() < 1e-5);
+        assert!((a.velocity - b.velocity).norm() < 1e-5);
+    }
+}
+
+#[test]
+fn rejects_bad_data() {
+    let world = busy_world();
+    let buffer = encode(&world, Precision::Double);
+
+    assert!(compact::read_world(&mut Cursor::new(&buffer[..buffer.len() / 2])).is_err());
+    assert!(compact::read_snapshot(&mut Cursor::new(&buffer)).is_err());
+
+    let mut corrupted = buffer.clone();
+    corrupted[0] = b'X';
+    assert!(compact::read_world(&mut Cursor::new(&corrupted)).is_err());
+}
+
+#[test]
+fn rejects_invalid_worlds() {
+    let mut world = busy_world();
+    world.verts[0].borrow_mut().body = 42;
+    let loaded = decode(&encode(&world, Precision::Double));
+    assert_eq!(loaded.verts[0].borrow().body, 0);
+
+    let light = busy_world();
+    light.verts[0].borrow_mut().mass = 0.0;
+    let buffer = encode(&light, Precision::Double);
+    assert!(compact::read_world(&mut Cursor::new(&buffer)).is_err());
+
+    let mut cache = world.solver.cache().clone();
+    cache.insert((world.verts.len(), 0), (1.0, 0.0, false));
+    world.solver.set_cache(cache);
+    let buffer = encode(&world, Precision::Double);
+    assert!(compact::read_world(&mut Cursor::new(&buffer)).is_err());
+}
+
+#[test]
+fn user_materials_are_saved() {
+    let mut world = busy_world();
+    world
+        .materials
+        .parse("[glass]\nrestitution = 0.9\n[rubber]\nmass = 0.2")
+        .unwrap();
+
+    let loaded = decode(&encode(&world, Precision::Double));
+    assert_eq!(loaded.materials.get("glass"), world.materials.get("glass"));
+    assert_eq!(loaded.materials.get("rubber").unwrap().mass, 0.2);
+    assert_eq!(loaded.materials.materials.len(), world.materials.materials.len());
+
+    // A material which can't exist is refused
+    for material in &mut world.materials.materials {
+        if material.name == "glass" {
+            material.restitution = 2.0;
+        }
+    }
+    let buffer = encode(&world, Precision::Double);
+    assert!(compact::read_world(&mut Cursor::new(&buffer)).is_err());
+}
@@ -0,0 +1,70 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World, GRAVITY};
+use spring::shapes;
+
+/// A square resting just above a static floor
+fn resting_world(warm_starting: bool) -> World {
+    let mut world = World::new();
+    world.solver.warm_starting = warm_starting;
+    for &x in &[-10.0, 10.0] {
+        let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    shapes::make_polygon(&mut world, Vector2::new(0.0, 0.75), 0.7, 4).unwrap();
+    world
+}
+
+/// The fastest vertex speed
+fn max_speed(world: &World) -> f64 {
+    world
+        .verts
+        .iter()
+        .map(|vertex| vertex.borrow().velocity.norm())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn resting_contact_settles() {
+    let mut world = resting_world(true);
+    for _ in 0..180 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+
+    assert!(max_speed(&world) < 0.01, "still moving at {}", max_speed(&world));
+    for vertex in &world.verts[2..] {
+        assert!(vertex.borrow().position.y > 0.0);
+    }
+
+    // The contacts under the square carry its weight from one step to the next
+    let cache = world.solver.cache();
+    assert!(!cache.is_empty());
+    assert!(cache.values().any(|&(normal, _, _)| normal > 0.0));
+}
+
+#[test]
+fn cached_impulses_carry_the_weight() {
+    let mut world = resting_world(true);
+    let substeps = 4;
+    for _ in 0..300 {
+        world.update(1.0 / 60.0, substeps, true);
+    }
+
+    // At rest the floor pushes back as much as gravity pulls within a substep
+    let mass: f64 = world.verts[2..]
+        .iter()
+        .map(|vertex| vertex.borrow().mass as f64)
+        .sum();
+    let weight = mass * GRAVITY / 60.0 / substeps as f64;
+    let support: f64 = world
+        .solver
+        .cache()
+        .values()
+        .map(|&(normal, _, _)| normal)
+        .sum();
+    assert!((support - weight).abs() < weight * 0.05, "support {}, weight {}", support, weight);
+}
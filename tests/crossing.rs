@@ -0,0 +1,85 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::collisions;
+use spring::physics::simulation::{Vertex, World};
+
+type Vector = Vector2<f64>;
+
+/// Two sticks, each its own body
+fn sticks(a: Vector, b: Vector, c: Vector, d: Vector) -> World {
+    let mut world = World::new();
+    for &position in &[a, b, c, d] {
+        world.add_vertex(Vertex::new(position));
+    }
+    world.create_surface(0, 1).unwrap();
+    world.create_surface(2, 3).unwrap();
+    world
+}
+
+fn positions(world: &World) -> Vec<Vector> {
+    world.verts.iter().map(|vertex| vertex.borrow().position).collect()
+}
+
+/// Where `p` is compared to the line from `a` to `b`, positive on the left
+fn side(p: Vector, a: Vector, b: Vector) -> f64 {
+    let (u, v) = (b - a, p - a);
+    u.x * v.y - u.y * v.x
+}
+
+fn above(p: Vector, a: Vector, b: Vector) -> bool {
+    side(p, a, b) > 0.0
+}
+
+/// True if the segments ab and cd intersect
+fn crossing(points: &[Vector]) -> bool {
+    side(points[0], points[2], points[3]) * side(points[1], points[2], points[3]) < 0.0
+        && side(points[2], points[0], points[1]) * side(points[3], points[0], points[1]) < 0.0
+}
+
+#[test]
+fn crossing_edges_are_separated() {
+    // A vertical stick poking a little through a horizontal one
+    let mut world = sticks(
+        Vector::new(-1.0, 0.0),
+        Vector::new(1.0, 0.0),
+        Vector::new(0.2, -0.05),
+        Vector::new(0.2, 1.0),
+    );
+    assert!(crossing(&positions(&world)));
+
+    world.update(1.0 / 60.0, 8, true);
+    let points = positions(&world);
+    assert!(!crossing(&points));
+
+    // The tip was pushed back above the horizontal stick, by at least the thickness
+    let thickness = world.surfaces[0].thickness;
+    let gap = collisions::segment_distance(&points[2], &points[0], &points[1]);
+    assert!(above(points[2], points[0], points[1]));
+    assert!(gap >= thickness * 0.99, "gap {}", gap);
+}
+
+#[test]
+fn edges_passing_through_within_a_step_are_caught() {
+    // The sticks would swap sides in a single step, and never cross at its start or end
+    let mut world = sticks(
+        Vector::new(-1.0, 0.0),
+        Vector::new(1.0, 0.0),
+        Vector::new(-0.5, 0.5),
+        Vector::new(0.5, 0.5),
+    );
+    world.verts[2].borrow_mut().velocity = Vector::new(0.0, -120.0);
+    world.verts[3].borrow_mut().velocity = Vector::new(0.0, -120.0);
+    {
+        let verts: Vec<_> = world.verts.iter().map(|vertex| vertex.borrow()).collect();
+        assert!(collisions::edges_colliding(&verts[0], &verts[1], &verts[2], &verts[3], 1.0 / 60.0));
+    }
+
+    world.update(1.0 / 60.0, 1, true);
+    let points = positions(&world);
+    // Momentum went to the other stick, but the ends are still above it
+    assert!(!crossing(&points));
+    assert!(above(points[2], points[0], points[1]));
+    assert!(above(points[3], points[0], points[1]));
+}
@@ -0,0 +1,166 @@
+extern crate nalgebra;
+extern crate spring;
+
+mod common;
+
+use spring::physics::material::CombineRule;
+use spring::physics::regions::FluidRegion;
+use spring::physics::simulation::World;
+
+#[test]
+fn same_scene_gives_the_same_hashes() {
+    let mut first = common::busy_world();
+    let mut second = common::busy_world();
+    assert_eq!(first.state_hash(), second.state_hash());
+
+    assert_eq!(common::run(&mut first, 360), common::run(&mut second, 360));
+    assert_eq!(first.last_hash, first.state_hash());
+    // With warm started contacts and fluid in the pool
+    assert!(!first.solver.cache().is_empty());
+    assert!(first
+        .fluid
+        .particles
+        .iter()
+        .any(|particle| particle.position.y < -4.0));
+}
+
+/// A change to the world, with the name of what it changes
+type Change = (&'static str, fn(&mut World));
+
+#[test]
+fn hash_covers_every_parameter() {
+    let changes: Vec<Change> = vec![
+        ("time", |world| world.time += 1.0),
+        ("fixed_dt", |world| world.fixed_dt /= 2.0),
+        ("deterministic", |world| world.deterministic = false),
+        ("air_density", |world| world.air_density = 1.0),
+        ("wind", |world| world.wind.y = 1.0),
+        ("vertex mass", |world| {
+            world.verts[0].borrow_mut().mass *= 2.0
+        }),
+        ("vertex static", |world| {
+            world.verts[0].borrow_mut().is_static = true
+        }),
+        ("vertex friction", |world| {
+            world.verts[0].borrow_mut().static_friction = 0.9
+        }),
+        ("vertex sliding", |world| {
+            world.verts[0].borrow_mut().dynamic_friction = 0.9
+        }),
+        ("vertex restitution", |world| {
+            world.verts[0].borrow_mut().restitution = 0.5
+        }),
+        ("vertex charge", |world| {
+            world.verts[0].borrow_mut().charge = 1.0
+        }),
+        ("vertex cohesion", |world| {
+            world.verts[0].borrow_mut().cohesion = 1.0
+        }),
+        ("surface damping", |world| {
+            world.surfaces[0].damping_ratio += 0.25
+        }),
+        ("surface strength", |world| {
+            world.surfaces[0].strength *= 2.0
+        }),
+        ("surface length", |world| {
+            world.surfaces[0].target_distance *= 2.0
+        }),
+        ("surface thickness", |world| {
+            world.surfaces[0].thickness *= 2.0
+        }),
+        ("surface friction", |world| {
+            world.surfaces[0].static_friction = 0.9
+        }),
+        ("surface sliding", |world| {
+            world.surfaces[0].dynamic_friction = 0.9
+        }),
+        ("surface restitution", |world| {
+            world.surfaces[0].restitution = 0.5
+        }),
+        ("surface drag", |world| {
+            world.surfaces[0].drag_coefficient = 2.0
+        }),
+        ("surface lift", |world| {
+            world.surfaces[0].lift_coefficient = 2.0
+        }),
+        ("region level", |world| {
+            world.regions[0] = FluidRegion::level(-2.0)
+        }),
+        ("region density", |world| world.regions[0].density *= 2.0),
+        ("region drag", |world| world.regions[0].linear_drag += 1.0),
+        ("region quadratic drag", |world| {
+            world.regions[0].quadratic_drag += 1.0
+        }),
+        ("new region", |world| {
+            world.regions.push(FluidRegion::level(-10.0))
+        }),
+        ("fluid radius", |world| world.fluid.smoothing_radius *= 2.0),
+        ("fluid mass", |world| world.fluid.particle_mass *= 2.0),
+        ("fluid density", |world| world.fluid.rest_density *= 2.0),
+        ("fluid stiffness", |world| world.fluid.stiffness *= 2.0),
+        ("fluid viscosity", |world| world.fluid.viscosity += 1.0),
+        ("fluid friction", |world| world.fluid.friction += 0.5),
+        ("fluid restitution", |world| world.fluid.restitution = 0.25),
+        ("particle", |world| {
+            world.fluid.particles[0].position.x += 1.0
+        }),
+        ("coulomb", |world| world.interactions.coulomb = true),
+        ("coulomb constant", |world| {
+            world.interactions.coulomb_constant *= 2.0
+        }),
+        ("gravity", |world| world.interactions.gravity = true),
+        ("gravitational constant", |world| {
+            world.interactions.gravitational_constant *= 2.0
+        }),
+        ("lennard jones", |world| {
+            world.interactions.lennard_jones = true
+        }),
+        ("cohesion distance", |world| {
+            world.interactions.cohesion_distance *= 2.0
+        }),
+        ("theta", |world| world.interactions.theta *= 2.0),
+        ("softening", |world| world.interactions.softening *= 2.0),
+        ("adaptive steps", |world| {
+            world.adaptive_steps.enabled = !world.adaptive_steps.enabled
+        }),
+        ("min substeps", |world| world.adaptive_steps.min += 1),
+        ("max substeps", |world| world.adaptive_steps.max += 1),
+        ("courant", |world| world.adaptive_steps.courant *= 2.0),
+        ("oscillation", |world| {
+            world.adaptive_steps.oscillation *= 2.0
+        }),
+        ("health", |world| {
+            world.health.enabled = !world.health.enabled
+        }),
+        ("max speed", |world| world.health.max_speed *= 2.0),
+        ("max rollbacks", |world| world.health.max_rollbacks += 1),
+        ("snapshot interval", |world| {
+            world.health.snapshot_interval += 1
+        }),
+        ("restitution threshold", |world| {
+            world.solver.restitution_threshold *= 2.0
+        }),
+        ("warm starting", |world| {
+            world.solver.warm_starting = !world.solver.warm_starting
+        }),
+        ("friction rule", |world| {
+            world.solver.friction_rule = CombineRule::Max
+        }),
+        ("restitution rule", |world| {
+            world.solver.restitution_rule = CombineRule::Max
+        }),
+        ("rng", |world| {
+            world.rng.next_u64();
+        }),
+    ];
+
+    let mut world = common::busy_world();
+    common::run(&mut world, 10);
+    let hash = world.state_hash();
+    for &(name, change) in &changes {
+        let mut changed = common::busy_world();
+        common::run(&mut changed, 10);
+        change(&mut changed);
+        assert!(changed.state_hash() != hash, "{} isn't hashed", name);
+    }
+}
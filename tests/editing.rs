@@ -0,0 +1,125 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::error::WorldError;
+use spring::physics::simulation::{Vertex, World};
+use spring::physics::surface::Surface;
+
+/// A triangle with a loose vertex on top of its first corner
+fn triangle() -> World {
+    let mut world = World::new();
+    for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (0.0, 0.0)] {
+        world.add_vertex(Vertex::new(Vector2::new(x, y)));
+    }
+    world.create_surface(0, 1).unwrap();
+    world.create_surface(1, 2).unwrap();
+    world.create_surface(2, 0).unwrap();
+    world
+}
+
+/// What an edit can change: the vertices, the surfaces and the bodies
+type Layout = (Vec<Vector2<f64>>, Vec<(usize, usize)>, Vec<usize>);
+
+fn layout(world: &World) -> Layout {
+    (
+        world.verts.iter().map(|vertex| vertex.borrow().position).collect(),
+        world
+            .surfaces
+            .iter()
+            .map(|surface| (surface.index_a, surface.index_b))
+            .collect(),
+        world.verts.iter().map(|vertex| vertex.borrow().body).collect(),
+    )
+}
+
+#[test]
+fn create_surface_rejects_bad_surfaces() {
+    let mut world = triangle();
+    let before = layout(&world);
+
+    assert_eq!(world.create_surface(1, 1), Err(WorldError::SelfLoop(1)));
+    assert_eq!(world.create_surface(1, 9), Err(WorldError::UnknownVertex(9)));
+    assert_eq!(world.create_surface(9, 1), Err(WorldError::UnknownVertex(9)));
+    assert_eq!(world.create_surface(0, 3), Err(WorldError::DegenerateSurface(0, 3)));
+    // In either order
+    assert_eq!(world.create_surface(1, 0), Err(WorldError::DuplicateSurface(0, 1)));
+    assert_eq!(world.create_surface(0, 2), Err(WorldError::DuplicateSurface(0, 2)));
+
+    assert_eq!(layout(&world), before);
+}
+
+#[test]
+fn create_surface_joins_the_bodies() {
+    let mut world = triangle();
+    world.verts[3].borrow_mut().position = Vector2::new(2.0, 0.0);
+    assert_eq!(world.verts[3].borrow().body, 3);
+
+    assert_eq!(world.create_surface(3, 1), Ok(3));
+    assert_eq!((world.surfaces[3].index_a, world.surfaces[3].index_b), (1, 3));
+    assert_eq!(world.verts[3].borrow().body, 0);
+}
+
+#[test]
+fn remove_rejects_unknown_indices() {
+    let mut world = triangle();
+    let before = layout(&world);
+
+    assert_eq!(world.remove_vertex(4), Err(WorldError::UnknownVertex(4)));
+    assert_eq!(world.remove_surface(3), Err(WorldError::UnknownSurface(3)));
+    assert_eq!(layout(&world), before);
+
+    let mut empty = World::new();
+    assert_eq!(empty.remove_vertex(0), Err(WorldError::UnknownVertex(0)));
+    assert_eq!(empty.remove_surface(0), Err(WorldError::UnknownSurface(0)));
+}
+
+#[test]
+fn remove_vertex_takes_its_surfaces_along() {
+    let mut world = triangle();
+    world.remove_vertex(1).unwrap();
+
+    // The two surfaces of vertex 1 are gone and the indices after it moved down
+    let (verts, surfaces, bodies) = layout(&world);
+    assert_eq!(verts.len(), 3);
+    assert_eq!(surfaces, vec![(0, 1)]);
+    assert_eq!(bodies, vec![0, 0, 2]);
+}
+
+#[test]
+fn remove_surface_splits_the_bodies() {
+    let mut world = triangle();
+    world.remove_surface(0).unwrap();
+    world.remove_surface(0).unwrap();
+    assert_eq!(layout(&world).2, vec![0, 1, 0, 3]);
+}
+
+#[test]
+fn surface_new_checks_its_vertices() {
+    let world = triangle();
+    assert_eq!(Surface::new(2, 2, &world.verts).err(), Some(WorldError::SelfLoop(2)));
+    assert_eq!(
+        Surface::new(0, 7, &world.verts).err(),
+        Some(WorldError::UnknownVertex(7))
+    );
+    assert_eq!(
+        Surface::new(3, 0, &world.verts).err(),
+        Some(WorldError::DegenerateSurface(3, 0))
+    );
+
+    let surface = Surface::new(1, 2, &world.verts).unwrap();
+    assert_eq!(surface.target_distance, 2f64.sqrt());
+}
+
+#[test]
+fn errors_explain_themselves() {
+    assert_eq!(WorldError::UnknownVertex(3).to_string(), "there is no vertex 3");
+    assert_eq!(
+        WorldError::DuplicateSurface(1, 2).to_string(),
+        "a surface between 1 and 2 already exists"
+    );
+    assert_eq!(
+        WorldError::SelfLoop(4).to_string(),
+        "vertex 4 can't be connected to itself"
+    );
+}
@@ -0,0 +1,88 @@
+extern crate nalgebra;
+extern crate serde_json;
+extern crate spring;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use nalgebra::Vector2;
+use serde_json::Value;
+use spring::export::{Exporter, Format, Quantity};
+use spring::physics::simulation::{Vertex, World};
+
+/// A vertex hanging from a static one, on an undamped spring 0.5 longer than its rest length
+fn spring_world() -> World {
+    let mut world = World::new();
+    let mut anchor = Vertex::new(Vector2::new(0.0, 0.0));
+    anchor.is_static = true;
+    world.add_vertex(anchor);
+    world.add_vertex(Vertex::new(Vector2::new(0.0, -2.0)));
+    world.create_surface(0, 1).unwrap();
+
+    let surface = &mut world.surfaces[0];
+    surface.target_distance = 1.5;
+    surface.strength = 40.0;
+    surface.damping_ratio = 0.0;
+    world
+}
+
+/// Exports the quantities of three steps, only the first and the third are sampled
+fn export(path: &Path, format: Format) -> String {
+    let mut world = spring_world();
+    let mut exporter = Exporter::create(
+        path,
+        format,
+        vec![Quantity::Position, Quantity::Extension, Quantity::Force],
+        2,
+    ).unwrap();
+    for _ in 0..3 {
+        exporter.record(&world).unwrap();
+        let dt = world.fixed_dt;
+        world.update(dt, 8, true);
+    }
+    assert_eq!(exporter.samples, 2);
+    exporter.finish().unwrap();
+
+    let mut text = String::new();
+    File::open(path).unwrap().read_to_string(&mut text).unwrap();
+    fs::remove_file(path).unwrap();
+    text
+}
+
+#[test]
+fn csv_has_the_spring_values() {
+    let path = env::temp_dir().join("spring_export_test.csv");
+    let text = export(&path, Format::Csv);
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[0], "step,time,object,index,quantity,value");
+    assert!(lines.contains(&"0,0,vertex,1,position_y,-2"));
+    assert!(lines.contains(&"0,0,surface,0,extension,0.5"));
+    assert!(lines.contains(&"0,0,surface,0,force,20"));
+    // Two samples of two positions with two components, an extension and a force
+    assert_eq!(lines.len(), 1 + 2 * (2 * 2 + 2));
+}
+
+#[test]
+fn json_lines_have_the_spring_values() {
+    let path = env::temp_dir().join("spring_export_test.jsonl");
+    let text = export(&path, Format::JsonLines);
+    let lines: Vec<Value> = text
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+
+    let first = &lines[0];
+    assert_eq!(first["step"], 0);
+    assert_eq!(first["vertices"][1]["position"][1], -2.0);
+    assert_eq!(first["surfaces"][0]["index"], 0);
+    assert_eq!(first["surfaces"][0]["extension"], 0.5);
+    assert_eq!(first["surfaces"][0]["force"], 20.0);
+
+    // The spring pulled the vertex up, so it is less stretched two steps later
+    assert_eq!(lines[1]["step"], 2);
+    assert!(lines[1]["surfaces"][0]["extension"].as_f64().unwrap() < 0.5);
+}
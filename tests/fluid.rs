@@ -0,0 +1,98 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::fluid::Fluid;
+use spring::physics::simulation::{Vertex, World};
+
+/// A static open box from -1 to 1 and from 0 to 3, with a blob of fluid in it
+fn pool(stiffness: f64) -> World {
+    let mut world = World::new();
+    world.fluid.stiffness = stiffness;
+
+    let corners = [(-1.0, 3.0), (-1.0, 0.0), (1.0, 0.0), (1.0, 3.0)];
+    for &(x, y) in &corners {
+        let mut vertex = Vertex::new(Vector2::new(x, y));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    for i in 0..3 {
+        world.create_surface(i, i + 1).unwrap();
+    }
+
+    world.emit_fluid(Vector2::new(0.0, 1.0), 0.6);
+    world
+}
+
+fn settle(world: &mut World) {
+    for _ in 0..300 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+}
+
+#[test]
+fn fluid_stays_in_the_box() {
+    let mut world = pool(200.0);
+    let count = world.fluid.particles.len();
+    assert!(count > 10);
+    settle(&mut world);
+
+    assert_eq!(world.fluid.particles.len(), count);
+    for particle in &world.fluid.particles {
+        let position = particle.position;
+        assert!(position.x > -1.0 && position.x < 1.0, "leaked at {:?}", position);
+        assert!(position.y > 0.0 && position.y < 3.0, "leaked at {:?}", position);
+    }
+
+    // It spreads over the floor into a puddle
+    let highest = world
+        .fluid
+        .particles
+        .iter()
+        .map(|particle| particle.position.y)
+        .fold(0.0, f64::max);
+    assert!(highest < 1.0, "still {} high", highest);
+}
+
+#[test]
+fn stiffer_fluid_is_less_compressed() {
+    let densest = |stiffness| {
+        let mut world = pool(stiffness);
+        settle(&mut world);
+        world
+            .fluid
+            .particles
+            .iter()
+            .map(|particle| particle.density)
+            .fold(0.0, f64::max)
+    };
+
+    let soft = densest(20.0);
+    let stiff = densest(400.0);
+    assert!(stiff < soft, "soft {}, stiff {}", soft, stiff);
+    assert!(stiff > 0.0);
+}
+
+#[test]
+fn viscosity_evens_out_velocities() {
+    let accelerations = |viscosity| {
+        let mut fluid = Fluid::new();
+        fluid.viscosity = viscosity;
+        let spacing = fluid.spacing();
+        fluid.add_particle(Vector2::new(0.0, 0.0));
+        fluid.add_particle(Vector2::new(0.0, spacing));
+        // Sliding past each other, so the pressure can't change their horizontal speed
+        fluid.particles[0].velocity = Vector2::new(1.0, 0.0);
+        fluid.particles[1].velocity = Vector2::new(-1.0, 0.0);
+
+        fluid.apply_forces(Vector2::new(0.0, 0.0));
+        (fluid.particles[0].acceleration.x, fluid.particles[1].acceleration.x)
+    };
+
+    assert_eq!(accelerations(0.0), (0.0, 0.0));
+
+    let (first, second) = accelerations(0.5);
+    assert!(first < 0.0 && second > 0.0);
+    let (faster_first, _) = accelerations(1.0);
+    assert!(faster_first < first);
+}
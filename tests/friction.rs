@@ -0,0 +1,99 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::material::{CombineRule, ContactMaterial};
+use spring::physics::simulation::{Vertex, World};
+use spring::physics::surface::Surface;
+
+#[test]
+fn combine_rules() {
+    assert_eq!(CombineRule::Average.combine(0.2, 0.6), 0.4);
+    assert_eq!(CombineRule::Min.combine(0.2, 0.6), 0.2);
+    assert_eq!(CombineRule::Max.combine(0.2, 0.6), 0.6);
+    assert_eq!(CombineRule::Multiply.combine(0.5, 0.6), 0.3);
+
+    // The order of the two materials doesn't matter
+    for rule in &CombineRule::ALL {
+        assert_eq!(rule.combine(0.2, 0.6), rule.combine(0.6, 0.2));
+    }
+}
+
+#[test]
+fn contact_material_combines_vertex_and_surface() {
+    let verts = vec![
+        Vertex::new(Vector2::new(0.0, 0.0)).into(),
+        Vertex::new(Vector2::new(1.0, 0.0)).into(),
+    ];
+    let mut surface = Surface::new(0, 1, &verts).unwrap();
+    surface.static_friction = 0.8;
+    surface.dynamic_friction = 0.6;
+    surface.restitution = 0.2;
+
+    let mut vertex = Vertex::new(Vector2::new(0.5, 1.0));
+    vertex.static_friction = 0.4;
+    vertex.dynamic_friction = 0.9;
+    vertex.restitution = 0.6;
+
+    let material = ContactMaterial::new(&vertex, &surface, CombineRule::Max, CombineRule::Min);
+    assert!((material.static_friction - 0.8).abs() < 1e-6);
+    // Sliding is never harder than starting to slide
+    assert!((material.dynamic_friction - 0.8).abs() < 1e-6);
+    assert!((material.restitution - 0.2).abs() < 1e-6);
+
+    let material = ContactMaterial::new(&vertex, &surface, CombineRule::Min, CombineRule::Max);
+    assert!((material.static_friction - 0.4).abs() < 1e-6);
+    assert!((material.dynamic_friction - 0.4).abs() < 1e-6);
+    assert!((material.restitution - 0.6).abs() < 1e-6);
+}
+
+/// How far a vertex resting on a 20 degrees slope slides in 2 seconds
+fn slide(friction: f32, rule: CombineRule) -> f64 {
+    let mut world = World::new();
+    world.solver.friction_rule = rule;
+
+    let angle = 20f64.to_radians();
+    let direction = Vector2::new(angle.cos(), angle.sin());
+    for &end in &[-2.0, 2.0] {
+        let mut vertex = Vertex::new(direction * end);
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    {
+        let surface = &mut world.surfaces[0];
+        surface.static_friction = friction;
+        surface.dynamic_friction = friction;
+        surface.restitution = 0.0;
+    }
+
+    let normal = Vector2::new(-direction.y, direction.x);
+    let start = normal * world.surfaces[0].thickness / 2.0;
+    let mut vertex = Vertex::new(start);
+    vertex.static_friction = 1.0;
+    vertex.dynamic_friction = 1.0;
+    vertex.restitution = 0.0;
+    world.add_vertex(vertex);
+
+    for _ in 0..120 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+    let position = world.verts[2].borrow().position;
+    (position - start).norm()
+}
+
+#[test]
+fn vertex_rests_on_a_rough_slope() {
+    // tan(20 degrees) is about 0.36, less is not enough to hold it
+    assert!(slide(0.6, CombineRule::Min) < 0.01);
+    assert!(slide(0.2, CombineRule::Min) > 1.0);
+}
+
+#[test]
+fn friction_rule_picks_the_slope_behaviour() {
+    // A frictionless surface under a sticky vertex
+    assert!(slide(0.0, CombineRule::Max) < 0.01);
+    assert!(slide(0.0, CombineRule::Average) < 0.01);
+    assert!(slide(0.0, CombineRule::Min) > 1.0);
+    assert!(slide(0.0, CombineRule::Multiply) > 1.0);
+}
@@ -0,0 +1,165 @@
+extern crate nalgebra;
+extern crate spring;
+
+use std::f64;
+
+use nalgebra::Vector2;
+use spring::physics::health::HealthPolicy;
+use spring::physics::simulation::{Vertex, World};
+
+/// A free stick and a lone vertex, run for a few healthy steps
+fn world(policy: HealthPolicy) -> World {
+    let mut world = World::new();
+    world.health.enabled = true;
+    world.health.policy = policy;
+    world.add_vertex(Vertex::new(Vector2::new(0.0, 0.0)));
+    world.add_vertex(Vertex::new(Vector2::new(1.0, 0.0)));
+    world.create_surface(0, 1).unwrap();
+    world.add_vertex(Vertex::new(Vector2::new(5.0, 0.0)));
+
+    for _ in 0..10 {
+        world.update(1.0 / 60.0, 8, true);
+    }
+    assert!(world.health.report.is_none());
+    world
+}
+
+fn blow_up(world: &World, index: usize) {
+    world.verts[index].borrow_mut().velocity = Vector2::new(f64::NAN, 0.0);
+}
+
+fn speed_up(world: &World, index: usize) {
+    world.verts[index].borrow_mut().velocity = Vector2::new(0.0, 1e5);
+}
+
+#[test]
+fn monitor_is_off_by_default() {
+    let mut world = World::new();
+    assert!(!world.health.enabled);
+    world.add_vertex(Vertex::new(Vector2::new(0.0, 0.0)));
+    blow_up(&world, 0);
+
+    world.update(1.0 / 60.0, 8, true);
+    // Left as it is for the user to see
+    assert!(world.health.report.is_none());
+    assert!(world.verts[0].borrow().position.x.is_nan());
+}
+
+#[test]
+fn rollback_goes_back_to_the_last_healthy_step() {
+    let mut world = world(HealthPolicy::Rollback);
+    let snapshot = world.health.last_good().unwrap().clone();
+
+    blow_up(&world, 2);
+    world.update(1.0 / 60.0, 8, true);
+
+    let report = world.health.report.clone().unwrap();
+    assert_eq!(report.action, HealthPolicy::Rollback);
+    assert_eq!(report.broken_vertices, vec![2]);
+    assert_eq!(world.step, snapshot.step);
+    for (vertex, saved) in world.verts.iter().zip(&snapshot.verts) {
+        assert_eq!(vertex.borrow().position, saved.position);
+        assert_eq!(vertex.borrow().velocity, saved.velocity);
+    }
+}
+
+#[test]
+fn rollback_freezes_the_offenders_when_it_keeps_blowing_up() {
+    let mut world = world(HealthPolicy::Rollback);
+    world.health.max_rollbacks = 2;
+
+    let mut actions = Vec::new();
+    for _ in 0..3 {
+        blow_up(&world, 2);
+        world.update(1.0 / 60.0, 8, true);
+        actions.push(world.health.report.clone().unwrap().action);
+    }
+    assert_eq!(
+        actions,
+        vec![
+            HealthPolicy::Rollback,
+            HealthPolicy::Rollback,
+            HealthPolicy::FreezeOffenders,
+        ]
+    );
+    assert!(world.verts[2].borrow().is_static);
+}
+
+#[test]
+fn clamp_slows_down_the_fast_vertices() {
+    let mut world = world(HealthPolicy::ClampVelocities);
+    speed_up(&world, 2);
+    world.update(1.0 / 60.0, 8, true);
+
+    let report = world.health.report.clone().unwrap();
+    assert_eq!(report.action, HealthPolicy::ClampVelocities);
+    assert_eq!(report.fast_vertices, vec![2]);
+
+    let vertex = world.verts[2].borrow();
+    assert!((vertex.velocity.norm() - world.health.max_speed).abs() < 1e-9);
+    assert!(!vertex.is_static);
+}
+
+#[test]
+fn clamp_puts_back_the_broken_vertices() {
+    let mut world = world(HealthPolicy::ClampVelocities);
+    let before = world.verts[2].borrow().position;
+    blow_up(&world, 2);
+    world.update(1.0 / 60.0, 8, true);
+
+    let report = world.health.report.clone().unwrap();
+    assert_eq!(report.broken_vertices, vec![2]);
+    assert!(report.surfaces.is_empty());
+
+    // Back where it was before the step which broke it, stopped
+    let vertex = world.verts[2].borrow();
+    assert_eq!(vertex.position, before);
+    assert_eq!(vertex.velocity, Vector2::new(0.0, 0.0));
+}
+
+#[test]
+fn report_lists_the_surfaces_of_the_offenders() {
+    let mut world = world(HealthPolicy::ClampVelocities);
+    blow_up(&world, 1);
+    world.update(1.0 / 60.0, 8, true);
+
+    // The spring carries the NaN to the other end within the step
+    let report = world.health.report.clone().unwrap();
+    assert_eq!(report.broken_vertices, vec![0, 1]);
+    assert_eq!(report.surfaces, vec![0]);
+    for vertex in &world.verts {
+        let vertex = vertex.borrow();
+        assert!(vertex.position.x.is_finite() && vertex.velocity.x.is_finite());
+    }
+}
+
+#[test]
+fn freeze_makes_the_offenders_static() {
+    let mut world = world(HealthPolicy::FreezeOffenders);
+    speed_up(&world, 2);
+    world.update(1.0 / 60.0, 8, true);
+
+    assert_eq!(
+        world.health.report.clone().unwrap().action,
+        HealthPolicy::FreezeOffenders
+    );
+    let vertex = world.verts[2].borrow();
+    assert!(vertex.is_static);
+    assert_eq!(vertex.velocity, Vector2::new(0.0, 0.0));
+    // The others go on
+    assert!(!world.verts[0].borrow().is_static);
+    assert!(!world.verts[1].borrow().is_static);
+}
+
+#[test]
+fn broken_particles_are_removed() {
+    let mut world = world(HealthPolicy::ClampVelocities);
+    world.fluid.add_particle(Vector2::new(10.0, 0.0));
+    world.fluid.add_particle(Vector2::new(20.0, 0.0));
+    world.fluid.particles[0].velocity = Vector2::new(f64::INFINITY, 0.0);
+    world.update(1.0 / 60.0, 8, true);
+
+    assert_eq!(world.health.report.clone().unwrap().particles, vec![0]);
+    assert_eq!(world.fluid.particles.len(), 1);
+    assert!(world.fluid.particles[0].position.x > 19.0);
+}
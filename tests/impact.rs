@@ -0,0 +1,65 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::collisions;
+use spring::physics::simulation::{Vertex, World};
+
+fn moving(x: f64, y: f64, vx: f64, vy: f64) -> Vertex {
+    let mut vertex = Vertex::new(Vector2::new(x, y));
+    vertex.velocity = Vector2::new(vx, vy);
+    vertex
+}
+
+#[test]
+fn impact_with_a_moving_edge() {
+    // The vertex falls at 10 and the edge rises at 10, 1 apart
+    let vertex = moving(0.0, 1.0, 0.0, -10.0);
+    let a = moving(-1.0, 0.0, 0.0, 10.0);
+    let b = moving(1.0, 0.0, 0.0, 10.0);
+    let t = collisions::time_of_impact(&vertex, &a, &b, 0.1).unwrap();
+    assert!((t - 0.05).abs() < 1e-12, "t = {}", t);
+
+    // Not within a shorter step
+    assert_eq!(collisions::time_of_impact(&vertex, &a, &b, 0.04), None);
+    // Nor when moving apart
+    let away = moving(0.0, 1.0, 0.0, 10.0);
+    assert_eq!(collisions::time_of_impact(&away, &a, &b, 0.1), None);
+    // Nor when passing beside the edge
+    let beside = moving(3.0, 1.0, 0.0, -10.0);
+    assert_eq!(collisions::time_of_impact(&beside, &a, &b, 0.1), None);
+}
+
+#[test]
+fn impact_with_a_turning_edge() {
+    // The ends of the edge move apart, so the edge turns while the vertex moves sideways,
+    // the vertex is on the line when 100 t^2 + 10 t - 1 = 0
+    let vertex = moving(0.5, 0.5, 5.0, 0.0);
+    let a = moving(-1.0, 0.0, 0.0, -10.0);
+    let b = moving(1.0, 0.0, 0.0, 10.0);
+    let t = collisions::time_of_impact(&vertex, &a, &b, 0.1).unwrap();
+    let expected = (-10.0 + 500.0f64.sqrt()) / 200.0;
+    assert!((t - expected).abs() < 1e-12, "t = {}", t);
+
+    // At that time the vertex lies on the edge
+    let (a, b) = (a.position + a.velocity * t, b.position + b.velocity * t);
+    let vertex = vertex.position + vertex.velocity * t;
+    let cross = (b - a).x * (vertex - a).y - (b - a).y * (vertex - a).x;
+    assert!(cross.abs() < 1e-9, "cross = {}", cross);
+}
+
+#[test]
+fn fast_vertex_does_not_tunnel() {
+    let mut world = World::new();
+    for &x in &[-2.0, 2.0] {
+        let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    world.add_vertex(moving(0.0, 0.5, 0.0, -2000.0));
+
+    // Far more than the distance to the floor in a single substep
+    world.update(1.0 / 60.0, 1, true);
+    assert!(world.verts[2].borrow().position.y > 0.0);
+}
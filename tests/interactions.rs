@@ -0,0 +1,165 @@
+extern crate nalgebra;
+extern crate spring;
+
+use std::cell::RefCell;
+
+use nalgebra::Vector2;
+use spring::physics::interactions::Interactions;
+use spring::physics::quadtree::{Point, QuadTree};
+use spring::physics::simulation::Vertex;
+
+/// Vertices spread over a disk of radius 10 like the seeds of a sunflower,
+/// with various masses and charges
+fn cloud(count: usize) -> Vec<RefCell<Vertex>> {
+    (0..count)
+        .map(|i| {
+            let radius = 10.0 * (i as f64 / count as f64).sqrt();
+            let angle = i as f64 * 2.4;
+            let mut vertex = Vertex::new(Vector2::new(radius * angle.cos(), radius * angle.sin()));
+            vertex.mass = 0.5 + (i % 7) as f32 / 4.0;
+            vertex.charge = (i % 5) as f32 / 2.0 - 1.0;
+            RefCell::new(vertex)
+        })
+        .collect()
+}
+
+/// The forces on every vertex from summing over all the couples
+fn brute_force(interactions: &Interactions, verts: &[RefCell<Vertex>]) -> Vec<Vector2<f64>> {
+    let softening = interactions.softening * interactions.softening;
+    (0..verts.len())
+        .map(|i| {
+            let vertex = verts[i].borrow();
+            let mut force = Vector2::new(0.0, 0.0);
+            for (j, other) in verts.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let other = other.borrow();
+                let delta = other.position - vertex.position;
+                let distance_squared = delta.norm_squared() + softening;
+                let inverse_square = delta / (distance_squared * distance_squared.sqrt());
+
+                if interactions.gravity {
+                    force += inverse_square * interactions.gravitational_constant
+                        * vertex.mass as f64 * other.mass as f64;
+                }
+                if interactions.coulomb {
+                    force -= inverse_square * interactions.coulomb_constant
+                        * vertex.charge as f64 * other.charge as f64;
+                }
+            }
+            force
+        })
+        .collect()
+}
+
+/// The forces on every vertex from the quadtree
+fn tree_forces(interactions: &Interactions, verts: &[RefCell<Vertex>]) -> Vec<Vector2<f64>> {
+    for vertex in verts {
+        vertex.borrow_mut().acceleration = Vector2::new(0.0, 0.0);
+    }
+    interactions.apply_forces(verts);
+    verts
+        .iter()
+        .map(|vertex| {
+            let vertex = vertex.borrow();
+            vertex.acceleration * vertex.mass as f64
+        })
+        .collect()
+}
+
+/// The largest error relative to the largest force
+fn error(expected: &[Vector2<f64>], actual: &[Vector2<f64>]) -> f64 {
+    let scale = expected.iter().map(|force| force.norm()).fold(0.0, f64::max);
+    expected
+        .iter()
+        .zip(actual)
+        .map(|(a, b)| (a - b).norm())
+        .fold(0.0, f64::max) / scale
+}
+
+#[test]
+fn exact_tree_matches_brute_force() {
+    let verts = cloud(200);
+    let mut interactions = Interactions::new();
+    interactions.gravity = true;
+    interactions.coulomb = true;
+    interactions.theta = 0.0;
+
+    let expected = brute_force(&interactions, &verts);
+    let error = error(&expected, &tree_forces(&interactions, &verts));
+    assert!(error < 1e-9, "off by {}", error);
+}
+
+#[test]
+fn approximate_tree_is_close_to_brute_force() {
+    let verts = cloud(500);
+    let mut interactions = Interactions::new();
+    interactions.gravity = true;
+
+    let expected = brute_force(&interactions, &verts);
+    let mut last_error = 0.0;
+    for &theta in &[0.2, 0.3, 0.5, 1.0] {
+        interactions.theta = theta;
+        let error = error(&expected, &tree_forces(&interactions, &verts));
+        // The error of taking a group as its center grows with the square of its apparent size
+        assert!(error < 0.2 * theta * theta, "theta {} off by {}", theta, error);
+        assert!(error > last_error);
+        last_error = error;
+    }
+}
+
+#[test]
+fn near_finds_the_points_within_the_radius() {
+    let verts = cloud(300);
+    let points = verts
+        .iter()
+        .map(|vertex| {
+            let vertex = vertex.borrow();
+            Point {
+                position: vertex.position,
+                mass: vertex.mass as f64,
+                charge: vertex.charge as f64,
+            }
+        })
+        .collect();
+    let tree = QuadTree::new(points);
+
+    let center = Vector2::new(1.0, -2.0);
+    for &radius in &[0.5, 2.0, 6.0, 30.0] {
+        let mut found = Vec::new();
+        tree.near(&center, radius, |index| found.push(index));
+        found.sort();
+
+        let expected: Vec<usize> = (0..verts.len())
+            .filter(|&index| (verts[index].borrow().position - center).norm() < radius)
+            .collect();
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn cohesion_balances_at_the_potential_minimum() {
+    let mut interactions = Interactions::new();
+    interactions.lennard_jones = true;
+    let minimum = interactions.cohesion_distance * 2f64.powf(1.0 / 6.0);
+
+    let force_at = |distance: f64| {
+        let verts: Vec<RefCell<Vertex>> = [0.0, distance]
+            .iter()
+            .map(|&x| {
+                let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+                vertex.cohesion = 1.0;
+                RefCell::new(vertex)
+            })
+            .collect();
+        tree_forces(&interactions, &verts)[1].x
+    };
+
+    assert!(force_at(minimum).abs() < 1e-9);
+    // Pushed away when too close, pulled back when too far
+    assert!(force_at(minimum * 0.9) > 0.0);
+    assert!(force_at(minimum * 1.2) < 0.0);
+    // Past the cutoff nothing is felt
+    assert_eq!(force_at(interactions.cohesion_distance * 3.0), 0.0);
+}
@@ -0,0 +1,116 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::error::WorldError;
+use spring::physics::material::MaterialLibrary;
+use spring::physics::simulation::{Vertex, World};
+
+fn stick() -> World {
+    let mut world = World::new();
+    world.add_vertex(Vertex::new(Vector2::new(0.0, 0.0)));
+    world.add_vertex(Vertex::new(Vector2::new(1.0, 0.0)));
+    world.create_surface(0, 1).unwrap();
+    world
+}
+
+#[test]
+fn materials_are_applied_by_name() {
+    let mut world = stick();
+    world.set_vertex_material(0, "steel").unwrap();
+    world.set_surface_material(0, "ice").unwrap();
+
+    let steel = world.materials.get("steel").unwrap().clone();
+    let ice = world.materials.get("ice").unwrap().clone();
+    {
+        let vertex = world.verts[0].borrow();
+        assert_eq!(vertex.material, Some(String::from("steel")));
+        assert_eq!(vertex.mass, steel.mass);
+        assert_eq!(vertex.restitution, steel.restitution);
+    }
+    assert_eq!(world.surfaces[0].material, Some(String::from("ice")));
+    assert_eq!(world.surfaces[0].strength, ice.strength);
+
+    world.set_body_material(0, "rubber").unwrap();
+    assert_eq!(world.verts[1].borrow().material, Some(String::from("rubber")));
+    assert_eq!(world.surfaces[0].material, Some(String::from("rubber")));
+}
+
+#[test]
+fn setting_a_material_checks_the_names_and_indices() {
+    let mut world = stick();
+    assert_eq!(
+        world.set_vertex_material(0, "cheese"),
+        Err(WorldError::UnknownMaterial(String::from("cheese")))
+    );
+    assert_eq!(
+        world.set_vertex_material(5, "steel"),
+        Err(WorldError::UnknownVertex(5))
+    );
+    assert_eq!(
+        world.set_surface_material(1, "steel"),
+        Err(WorldError::UnknownSurface(1))
+    );
+    assert_eq!(
+        world.set_body_material(0, "cheese"),
+        Err(WorldError::UnknownMaterial(String::from("cheese")))
+    );
+
+    // Nothing was changed by the failed calls
+    assert_eq!(world.verts[0].borrow().material, None);
+    assert_eq!(world.surfaces[0].material, None);
+}
+
+#[test]
+fn library_reads_materials() {
+    let mut library = MaterialLibrary::new();
+    let text = "
+        # A bouncy and slippery one
+        [glass]
+        restitution = 0.9
+        static_friction = 0.1
+
+        [steel]
+        mass = 1.0
+    ";
+    assert_eq!(library.parse(text), Ok(2));
+
+    let glass = library.get("glass").unwrap();
+    assert_eq!(glass.restitution, 0.9);
+    assert_eq!(glass.static_friction, 0.1);
+    // The rest comes from the default material
+    assert_eq!(glass.strength, 30.0);
+    assert_eq!(library.get("steel").unwrap().mass, 1.0);
+
+    // Only the added and changed materials are worth saving
+    let custom: Vec<String> = library
+        .custom()
+        .iter()
+        .map(|material| material.name.clone())
+        .collect();
+    assert_eq!(custom, vec![String::from("steel"), String::from("glass")]);
+}
+
+#[test]
+fn library_rejects_impossible_properties() {
+    let bad = [
+        "[a]\nmass = 0",
+        "[a]\nmass = -1",
+        "[a]\nstatic_friction = -0.1",
+        "[a]\ndynamic_friction = -2",
+        "[a]\nrestitution = -0.5",
+        "[a]\nrestitution = 1.5",
+        "[a]\nstrength = inf",
+        "[a]\nstrength = soft",
+        "[a]\ncolour = 1",
+        "mass = 1",
+    ];
+    for text in &bad {
+        let mut library = MaterialLibrary::new();
+        assert!(library.parse(text).is_err(), "{:?} was read", text);
+        assert!(library.get("a").is_none());
+    }
+
+    let mut library = MaterialLibrary::new();
+    assert_eq!(library.parse("[a]\nrestitution = 1\nstatic_friction = 0"), Ok(1));
+}
@@ -0,0 +1,105 @@
+extern crate serde_json;
+extern crate spring;
+
+use spring::physics::health::HealthPolicy;
+use spring::physics::material::CombineRule;
+use spring::physics::simulation::World;
+use spring::scene::{Scene, ViewSettings, SCENE_VERSION};
+
+/// A scene written before the version was added, with an unknown field and missing ones
+const UNVERSIONED: &str = include_str!("scenes/unversioned.json");
+/// A scene which kept the combine rules and the health policy as numbers in the settings
+const VERSION_1: &str = include_str!("scenes/version_1.json");
+
+#[test]
+fn current_scene_is_read_as_written() {
+    let scene = Scene::new(&World::new(), Some(ViewSettings::default()));
+    let text = serde_json::to_string(&scene).unwrap();
+
+    let (scene, report) = Scene::parse(&text).unwrap();
+    assert!(report.is_empty(), "{}", report);
+    assert_eq!(scene.version, SCENE_VERSION);
+}
+
+#[test]
+fn unversioned_scene_is_upgraded() {
+    let (scene, report) = Scene::parse(UNVERSIONED).unwrap();
+    assert_eq!(report.from_version, 0);
+    assert_eq!(report.migrations.len(), SCENE_VERSION as usize);
+    assert_eq!(scene.version, SCENE_VERSION);
+
+    assert_eq!(report.dropped.get("wobble"), Some(&1));
+    assert_eq!(report.dropped.get("verts[].colour"), Some(&1));
+    assert_eq!(report.defaulted.get("verts[].is_static"), Some(&1));
+    assert_eq!(report.defaulted.get("surfaces[].thickness"), Some(&1));
+    assert!(report.defaulted.contains_key("view"));
+
+    let world = scene.to_world().unwrap();
+    assert!(world.validate().is_valid());
+    assert_eq!(world.air_density, 0.5);
+    assert!(world.verts[0].borrow().is_static);
+    assert!(!world.verts[1].borrow().is_static);
+    assert_eq!(world.verts[1].borrow().mass, 2.0);
+    assert_eq!(world.surfaces[0].strength, 40.0);
+    assert_eq!(world.surfaces[0].thickness, 0.01);
+}
+
+#[test]
+fn version_1_rules_are_read_by_name() {
+    let (scene, report) = Scene::parse(VERSION_1).unwrap();
+    assert_eq!(report.from_version, 1);
+    assert_eq!(report.migrations.len(), 1);
+    assert!(!report.dropped.contains_key("settings.friction_rule"));
+
+    let world = scene.to_world().unwrap();
+    assert_eq!(world.solver.friction_rule, CombineRule::Min);
+    assert_eq!(world.solver.restitution_rule, CombineRule::Multiply);
+    assert_eq!(world.health.policy, HealthPolicy::FreezeOffenders);
+
+    // Written again, the rules are names and the settings only have numbers
+    let text = serde_json::to_string(&Scene::new(&world, None)).unwrap();
+    assert!(text.contains("\"friction_rule\":\"Min\""));
+    let (scene, report) = Scene::parse(&text).unwrap();
+    assert!(report.is_empty(), "{}", report);
+    assert_eq!(scene.to_world().unwrap().solver.friction_rule, CombineRule::Min);
+}
+
+#[test]
+fn rejects_bad_scenes() {
+    assert!(Scene::parse(r#"{"version": 99}"#).is_err());
+    assert!(Scene::parse(r#"{"version": 4294967297}"#).is_err());
+    assert!(Scene::parse(r#"{"version": -1}"#).is_err());
+    assert!(Scene::parse(r#"[1]"#).is_err());
+
+    // A surface without its vertices
+    let missing = r#"{"verts": [{"position": [0, 0]}, {"position": [1, 0]}],
+                      "surfaces": [{"index_a": 1}]}"#;
+    assert!(Scene::parse(missing).is_err());
+
+    let self_loop = r#"{"verts": [{"position": [0, 0]}, {"position": [1, 0]}],
+                        "surfaces": [{"index_a": 1, "index_b": 1, "target_distance": 1}]}"#;
+    assert!(Scene::parse(self_loop).unwrap().0.to_world().is_err());
+}
+
+#[test]
+fn user_materials_are_saved() {
+    let mut world = World::new();
+    world
+        .materials
+        .parse("[glass]\nrestitution = 0.9\n[steel]\nmass = 1.0")
+        .unwrap();
+
+    let text = serde_json::to_string(&Scene::new(&world, None)).unwrap();
+    let (scene, report) = Scene::parse(&text).unwrap();
+    assert!(report.is_empty(), "{}", report);
+    // The built-in materials which weren't changed aren't written
+    assert_eq!(scene.materials.len(), 2);
+
+    let loaded = scene.to_world().unwrap();
+    assert_eq!(loaded.materials.get("glass").unwrap().restitution, 0.9);
+    assert_eq!(loaded.materials.get("steel").unwrap().mass, 1.0);
+    assert!(loaded.materials.get("rubber").is_some());
+
+    let bouncy = r#"{"materials": [{"name": "flubber", "restitution": 3}]}"#;
+    assert!(Scene::parse(bouncy).unwrap().0.to_world().is_err());
+}
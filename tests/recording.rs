@@ -0,0 +1,115 @@
+extern crate nalgebra;
+extern crate spring;
+
+mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Write};
+use std::path::Path;
+
+use nalgebra::Vector2;
+use spring::binary::write_signed;
+use spring::physics::simulation::{Vertex, World};
+use spring::recording::{self, Recorder};
+
+/// Records the world and the steps after it
+fn record(path: &Path, world: &mut World, steps: usize, precision: f64) {
+    let mut recorder = Recorder::with_precision(path, precision).unwrap();
+    recorder.record(world).unwrap();
+    for _ in 0..steps {
+        world.update(0.0, 8, true);
+        recorder.record(world).unwrap();
+    }
+    recorder.finish().unwrap();
+}
+
+fn read_bytes(path: &Path) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+fn write_bytes(path: &Path, bytes: &[u8]) {
+    File::create(path).unwrap().write_all(bytes).unwrap();
+}
+
+/// A lone static vertex, far enough that its quantized position is close to the largest integer
+fn far_world(x: f64) -> World {
+    let mut world = World::new();
+    let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+    vertex.is_static = true;
+    world.add_vertex(vertex);
+    world
+}
+
+#[test]
+fn recording_plays_back_within_the_precision() {
+    let path = env::temp_dir().join("spring_recording_test.rec");
+    let mut world = common::busy_world();
+    let mut positions = Vec::new();
+    let mut recorder = Recorder::with_precision(&path, 1e-3).unwrap();
+    for _ in 0..300 {
+        world.update(0.0, 8, true);
+        recorder.record(&world).unwrap();
+        positions.push(world.verts[0].borrow().position);
+    }
+    recorder.finish().unwrap();
+
+    let history = recording::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(history.len(), 300);
+    for (index, position) in positions.iter().enumerate() {
+        let snapshot = history.get(index).unwrap();
+        assert!((snapshot.verts[0].position - position).norm() < 1e-3);
+    }
+    assert_eq!(history.get(299).unwrap().step, world.step);
+}
+
+#[test]
+fn truncated_recording_is_rejected() {
+    let path = env::temp_dir().join("spring_truncated_test.rec");
+    record(&path, &mut common::busy_world(), 10, 1e-4);
+
+    let bytes = read_bytes(&path);
+    write_bytes(&path, &bytes[..bytes.len() - 3]);
+    let error = recording::load(&path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn corrupted_delta_is_rejected() {
+    let path = env::temp_dir().join("spring_corrupted_test.rec");
+    record(&path, &mut far_world(9e18), 1, 1.0);
+
+    // The delta frame ends with the four unchanged values of the vertex, a byte each
+    let mut bytes = read_bytes(&path);
+    let length = bytes.len() - 4;
+    bytes.truncate(length);
+    write_signed(&mut bytes, i64::max_value()).unwrap();
+    bytes.extend_from_slice(&[0, 0, 0]);
+    write_bytes(&path, &bytes);
+
+    let error = recording::load(&path).err().unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(error.to_string(), "delta out of range");
+}
+
+#[test]
+fn large_jumps_are_written_as_keyframes() {
+    let path = env::temp_dir().join("spring_jump_test.rec");
+    let world = far_world(9e18);
+    let mut recorder = Recorder::with_precision(&path, 1.0).unwrap();
+    recorder.record(&world).unwrap();
+    // Too far from the last position for the difference to fit
+    world.verts[0].borrow_mut().position.x = -9e18;
+    recorder.record(&world).unwrap();
+    recorder.finish().unwrap();
+
+    let history = recording::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(history.get(0).unwrap().verts[0].position.x, 9e18);
+    assert_eq!(history.get(1).unwrap().verts[0].position.x, -9e18);
+}
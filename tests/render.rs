@@ -0,0 +1,105 @@
+extern crate gif;
+extern crate nalgebra;
+extern crate png;
+extern crate spring;
+
+use std::env;
+use std::fs::{self, File};
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World};
+use spring::raster::{self, Camera, Canvas, Color, GifWriter};
+
+/// The bytes a color is written with when it's fully opaque
+fn bytes(color: Color) -> [u8; 4] {
+    let byte = |value: f32| (value * 255.0).round() as u8;
+    [byte(color[0]), byte(color[1]), byte(color[2]), byte(color[3])]
+}
+
+fn pixel(canvas: &Canvas, x: u32, y: u32) -> [u8; 4] {
+    let index = (y * canvas.width + x) as usize * 4;
+    let mut pixel = [0; 4];
+    pixel.copy_from_slice(&canvas.pixels[index..index + 4]);
+    pixel
+}
+
+/// A thick horizontal surface across the middle of a 64x64 image, 60 pixels per meter
+fn render_bar() -> Canvas {
+    let mut world = World::new();
+    world.add_vertex(Vertex::new(Vector2::new(-0.4, 0.0)));
+    world.add_vertex(Vertex::new(Vector2::new(0.4, 0.0)));
+    world.create_surface(0, 1).unwrap();
+    world.surfaces[0].thickness = 0.2;
+
+    let mut camera = Camera::new(64, 64);
+    camera.vertex_scale = 0.05;
+    raster::render(&world, &camera)
+}
+
+#[test]
+fn render_draws_surfaces_and_vertices() {
+    let canvas = render_bar();
+    assert_eq!((canvas.width, canvas.height), (64, 64));
+    assert_eq!(canvas.pixels.len(), 64 * 64 * 4);
+
+    // The vertices are at 8 and 56 pixels, the surface is 12 pixels thick
+    assert_eq!(pixel(&canvas, 8, 32), bytes(raster::VERTEX_COLOR));
+    assert_eq!(pixel(&canvas, 55, 31), bytes(raster::VERTEX_COLOR));
+    assert_eq!(pixel(&canvas, 32, 32), bytes(raster::SURFACE_COLOR));
+    assert_eq!(pixel(&canvas, 32, 27), bytes(raster::SURFACE_COLOR));
+    assert_eq!(pixel(&canvas, 32, 10), bytes(raster::BACKGROUND_COLOR));
+    assert_eq!(pixel(&canvas, 0, 0), bytes(raster::BACKGROUND_COLOR));
+}
+
+#[test]
+fn png_keeps_the_pixels() {
+    let canvas = render_bar();
+    let path = env::temp_dir().join("spring_render_test.png");
+    canvas.save_png(&path).unwrap();
+
+    let (info, mut reader) = png::Decoder::new(File::open(&path).unwrap())
+        .read_info()
+        .unwrap();
+    assert_eq!((info.width, info.height), (64, 64));
+    assert_eq!(info.color_type, png::ColorType::RGBA);
+    let mut pixels = vec![0; canvas.pixels.len()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert!(pixels == canvas.pixels);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn gif_has_every_frame() {
+    let canvas = render_bar();
+    let path = env::temp_dir().join("spring_render_test.gif");
+    {
+        let mut writer = GifWriter::create(&path, 64, 64, 3).unwrap();
+        writer.add_frame(&canvas).unwrap();
+        writer.add_frame(&canvas).unwrap();
+        assert!(writer.add_frame(&Canvas::new(8, 8)).is_err());
+        assert_eq!(writer.frames, 2);
+    }
+
+    let mut reader = gif::Decoder::new(File::open(&path).unwrap())
+        .read_info()
+        .unwrap();
+    assert_eq!((reader.width(), reader.height()), (64, 64));
+    let palette = reader.global_palette().unwrap().to_vec();
+
+    let mut frames = 0;
+    while let Some(frame) = reader.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 3);
+        // The palette has the exact colors of the surfaces and the background
+        let color = |x: usize, y: usize| {
+            let index = frame.buffer[y * 64 + x] as usize * 3;
+            [palette[index], palette[index + 1], palette[index + 2]]
+        };
+        assert_eq!(color(32, 32), [255, 0, 0]);
+        assert_eq!(color(0, 0), [255, 255, 255]);
+        frames += 1;
+    }
+    assert_eq!(frames, 2);
+
+    fs::remove_file(&path).unwrap();
+}
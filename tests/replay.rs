@@ -0,0 +1,77 @@
+extern crate nalgebra;
+extern crate spring;
+
+mod common;
+
+use std::env;
+
+use nalgebra::Vector2;
+use spring::physics::simulation::World;
+use spring::replay::{Action, InputRecorder, Parameter, Replay};
+
+/// Does an action the way the viewer does, recording it
+fn act(world: &mut World, recorder: &mut InputRecorder, action: Action) {
+    recorder.flush(world, 8, true);
+    action.apply(world).unwrap();
+    recorder.record(world, action);
+}
+
+#[test]
+fn replay_runs_like_the_recorded_session() {
+    let mut world = common::floor_world();
+    let mut recorder = InputRecorder::start(&world, 8, true);
+
+    for i in 0..240 {
+        match i {
+            10 => act(
+                &mut world,
+                &mut recorder,
+                Action::AddVertex(Vector2::new(4.0, 2.0)),
+            ),
+            11 => act(&mut world, &mut recorder, Action::CreateSurface(10, 0)),
+            i if i >= 20 && i < 60 => act(
+                &mut world,
+                &mut recorder,
+                Action::Pull(3, Vector2::new(5.0, 12.0)),
+            ),
+            80 => {
+                // Edited in the interface right before an action in the same frame
+                world.verts[2].borrow_mut().mass = 0.2;
+                world.air_density = 0.3;
+                act(
+                    &mut world,
+                    &mut recorder,
+                    Action::Drag(5, Vector2::new(0.1, 0.05)),
+                );
+            }
+            90 => act(
+                &mut world,
+                &mut recorder,
+                Action::EmitFluid(Vector2::new(0.0, 5.0), 0.4),
+            ),
+            100 => act(&mut world, &mut recorder, Action::AddLevel(-3.0)),
+            _ => {}
+        }
+
+        let iterations = if i > 150 { 4 } else { 8 };
+        recorder.flush(&world, iterations, true);
+        world.update(0.0, iterations, true);
+        recorder.after_step(&world);
+    }
+    let replay = recorder.finish(&world);
+
+    let path = env::temp_dir().join("spring_replay_test.replay");
+    replay.save(&path).unwrap();
+    let loaded = Replay::load(&path).unwrap();
+    assert_eq!(loaded.actions, replay.actions);
+    assert!(loaded
+        .actions
+        .contains(&(80, Action::Set(Parameter::Vertex(2, "mass"), 0.2f32 as f64))));
+
+    let mut hashes = Vec::new();
+    let replayed = loaded.run(|world| hashes.push(world.last_hash)).unwrap();
+    assert_eq!(hashes.len(), 240);
+    assert_eq!(replayed.step, world.step);
+    assert_eq!(replayed.last_hash, world.last_hash);
+    assert_eq!(replayed.state_hash(), world.state_hash());
+}
@@ -0,0 +1,131 @@
+extern crate nalgebra;
+extern crate spring;
+
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World};
+use spring::runner::{self, ReplayOptions, RunOptions, WorldFile};
+use spring::scene::ViewSettings;
+
+fn parse(args: &[&str]) -> Result<RunOptions, String> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    RunOptions::parse(&args)
+}
+
+#[test]
+fn parses_the_options() {
+    let options = parse(&[
+        "scene.json",
+        "--dt",
+        "1/240",
+        "--size",
+        "320x200",
+        "--center",
+        "1.5,-2",
+        "--steps",
+        "50",
+        "--no-collisions",
+    ])
+    .unwrap();
+
+    assert_eq!(options.scene, Path::new("scene.json"));
+    assert_eq!(options.dt, Some(1.0 / 240.0));
+    assert_eq!(options.size, (320, 200));
+    assert_eq!(options.center, Some(Vector2::new(1.5, -2.0)));
+    assert_eq!(options.steps, 50);
+    assert!(!options.collisions);
+
+    let defaults = parse(&["scene.json", "--dt", "0.004"]).unwrap();
+    assert_eq!(defaults.dt, Some(0.004));
+    assert_eq!(defaults.center, None);
+    assert!(defaults.collisions);
+}
+
+#[test]
+fn rejects_bad_options() {
+    assert!(parse(&[]).is_err());
+    assert!(parse(&["scene.json", "other.json"]).is_err());
+    assert!(parse(&["scene.json", "--wobble"]).is_err());
+    assert!(parse(&["scene.json", "--steps"]).is_err());
+
+    for dt in &["0", "-1", "1/0", "1/", "fast", "1/240/2"] {
+        assert!(parse(&["scene.json", "--dt", dt]).is_err(), "--dt {}", dt);
+    }
+    for size in &["320", "0x200", "320x", "axb", "-320x200"] {
+        assert!(
+            parse(&["scene.json", "--size", size]).is_err(),
+            "--size {}",
+            size
+        );
+    }
+    for center in &["1", "1,", "a,b"] {
+        assert!(
+            parse(&["scene.json", "--center", center]).is_err(),
+            "--center {}",
+            center
+        );
+    }
+    assert!(parse(&["scene.json", "--iterations", "0"]).is_err());
+}
+
+#[test]
+fn rejects_unknown_world_files() {
+    assert_eq!(
+        WorldFile::from_path(Path::new("scene.json")),
+        Ok(WorldFile::Scene)
+    );
+    assert_eq!(
+        WorldFile::from_path(Path::new("world.sprw")),
+        Ok(WorldFile::Compact)
+    );
+
+    let error = parse(&["scene.ron"]).err().unwrap();
+    assert_eq!(
+        error,
+        "Unknown world file scene.ron, use .json for a scene or .sprw for a compact world"
+    );
+    assert!(parse(&["scene"]).is_err());
+    assert!(parse(&["scene.json", "--output", "final.bin"]).is_err());
+    assert!(parse(&["world.sprw", "--output", "final.json"]).is_ok());
+
+    let args = vec![
+        String::from("session.replay"),
+        String::from("--output"),
+        String::from("end"),
+    ];
+    assert!(ReplayOptions::parse(&args).is_err());
+}
+
+#[test]
+fn contacts_are_counted_over_the_substeps() {
+    // A vertex resting on a static floor touches it in every substep
+    let mut world = World::new();
+    for &x in &[-10.0, 10.0] {
+        let mut vertex = Vertex::new(Vector2::new(x, 0.0));
+        vertex.is_static = true;
+        world.add_vertex(vertex);
+    }
+    world.create_surface(0, 1).unwrap();
+    let mut vertex = Vertex::new(Vector2::new(0.0, 0.5));
+    vertex.restitution = 0.0;
+    world.add_vertex(vertex);
+    for _ in 0..120 {
+        world.update(1.0 / 60.0, 4, true);
+    }
+    assert_eq!(world.contacts, 4);
+
+    let mut options = RunOptions::new(PathBuf::from("scene.json"));
+    options.steps = 10;
+    options.iterations = 4;
+    options.report_interval = 0;
+    let statistics = runner::run(
+        &mut world,
+        &ViewSettings::default(),
+        &options,
+        &mut Vec::new(),
+    )
+    .unwrap();
+    assert_eq!(statistics.total_contacts, 40);
+    assert_eq!(statistics.max_contacts, 4);
+}
@@ -0,0 +1,45 @@
+extern crate nalgebra;
+extern crate spring;
+
+mod common;
+
+use nalgebra::Vector2;
+use spring::physics::regions::FluidRegion;
+
+#[test]
+fn restored_world_runs_the_same_steps_again() {
+    let mut world = common::busy_world();
+    common::run(&mut world, 360);
+    // Resting on the floor with warm started contacts, the fluid pouring in
+    assert!(!world.solver.cache().is_empty());
+    assert!(!world.fluid.particles.is_empty());
+
+    let snapshot = world.snapshot();
+    let hash = world.state_hash();
+    let first = common::run(&mut world, 120);
+
+    world.restore(&snapshot);
+    assert_eq!(world.state_hash(), hash);
+    assert_eq!(world.last_hash, hash);
+    assert_eq!(common::run(&mut world, 120), first);
+}
+
+#[test]
+fn restore_undoes_the_edits() {
+    let mut world = common::busy_world();
+    common::run(&mut world, 60);
+    let snapshot = world.snapshot();
+    let hash = world.state_hash();
+    let vertex_count = world.verts.len();
+
+    world.remove_vertex(0).unwrap();
+    world.surfaces[0].strength *= 2.0;
+    world.regions.push(FluidRegion::level(10.0));
+    world.emit_fluid(Vector2::new(0.0, 10.0), 1.0);
+    common::run(&mut world, 10);
+
+    world.restore(&snapshot);
+    assert_eq!(world.verts.len(), vertex_count);
+    assert_eq!(world.state_hash(), hash);
+    assert!(world.validate().is_valid());
+}
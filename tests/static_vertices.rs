@@ -0,0 +1,74 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::collisions;
+use spring::physics::simulation::{Vertex, World};
+
+fn anchor(position: Vector2<f64>) -> Vertex {
+    let mut vertex = Vertex::new(position);
+    vertex.is_static = true;
+    vertex
+}
+
+#[test]
+fn static_vertex_has_no_inverse_mass() {
+    let mut vertex = anchor(Vector2::new(0.0, 0.0));
+    vertex.mass = 3.0;
+    assert_eq!(vertex.inverse_mass(), 0.0);
+
+    vertex.is_static = false;
+    assert_eq!(vertex.inverse_mass(), 1.0 / 3.0);
+}
+
+#[test]
+fn impulses_leave_static_vertices_alone() {
+    let mut vertex = Vertex::new(Vector2::new(0.0, 1.0));
+    let mut a = anchor(Vector2::new(-1.0, 0.0));
+    let mut b = anchor(Vector2::new(1.0, 0.0));
+
+    collisions::apply_impulse(Vector2::new(0.0, 2.0), &mut vertex, &mut a, &mut b, 0.5);
+    collisions::push_apart(Vector2::new(0.0, 0.5), &mut vertex, &mut a, &mut b, 0.5);
+
+    // The whole impulse and correction go to the free vertex
+    assert_eq!(vertex.velocity, Vector2::new(0.0, 2.0 / vertex.mass as f64));
+    assert_eq!(vertex.position, Vector2::new(0.0, 1.5));
+    assert_eq!(a.velocity, Vector2::new(0.0, 0.0));
+    assert_eq!(b.velocity, Vector2::new(0.0, 0.0));
+    assert_eq!(a.position, Vector2::new(-1.0, 0.0));
+    assert_eq!(b.position, Vector2::new(1.0, 0.0));
+}
+
+#[test]
+fn static_vertex_never_moves() {
+    // A heavy pendulum hanging from an anchor, swinging onto a static floor
+    let mut world = World::new();
+    world.add_vertex(anchor(Vector2::new(0.0, 2.0)));
+    let mut bob = Vertex::new(Vector2::new(1.5, 2.0));
+    bob.mass = 50.0;
+    world.add_vertex(bob);
+    world.create_surface(0, 1).unwrap();
+
+    world.add_vertex(anchor(Vector2::new(-3.0, 0.0)));
+    world.add_vertex(anchor(Vector2::new(3.0, 0.0)));
+    world.create_surface(2, 3).unwrap();
+
+    let anchors = [0, 2, 3];
+    let start: Vec<_> = anchors
+        .iter()
+        .map(|&i| world.verts[i].borrow().position)
+        .collect();
+
+    let mut touched = false;
+    for _ in 0..300 {
+        world.update(1.0 / 60.0, 8, true);
+        touched |= !world.solver.cache().is_empty();
+
+        for (&i, position) in anchors.iter().zip(&start) {
+            let vertex = world.verts[i].borrow();
+            assert_eq!(vertex.position, *position);
+            assert_eq!(vertex.velocity, Vector2::new(0.0, 0.0));
+        }
+    }
+    assert!(touched, "the pendulum never reached the floor");
+}
@@ -0,0 +1,97 @@
+extern crate nalgebra;
+extern crate spring;
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World};
+
+/// Two free vertices 1 apart joined by a surface of `strength`, the second one moving at `speed`
+fn stick(strength: f32, speed: f64) -> World {
+    let mut world = World::new();
+    world.add_vertex(Vertex::new(Vector2::new(0.0, 0.0)));
+    let mut vertex = Vertex::new(Vector2::new(1.0, 0.0));
+    vertex.velocity = Vector2::new(0.0, speed);
+    world.add_vertex(vertex);
+    world.create_surface(0, 1).unwrap();
+    world.surfaces[0].strength = strength;
+    world
+}
+
+#[test]
+fn substeps_follow_the_stiffest_spring() {
+    let world = stick(30.0, 0.0);
+    let dt = 1.0 / 60.0;
+
+    // omega = sqrt(k (1 / m_a + 1 / m_b))
+    let inverse_mass = 2.0 / world.verts[0].borrow().mass as f64;
+    let omega = (30.0 * inverse_mass).sqrt();
+    let expected = (omega * dt / world.adaptive_steps.oscillation).ceil() as u32;
+    assert_eq!(world.adaptive_substeps(dt), expected);
+
+    // Four times stiffer oscillates twice as fast
+    let stiff = stick(120.0, 0.0);
+    let expected = (2.0 * omega * dt / world.adaptive_steps.oscillation).ceil() as u32;
+    assert_eq!(stiff.adaptive_substeps(dt), expected);
+}
+
+#[test]
+fn substeps_follow_the_fastest_vertex() {
+    // Crossing the whole surface in a step needs two substeps with a courant of 0.5
+    let world = stick(0.0, 60.0);
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), 2);
+
+    let world = stick(0.0, 600.0);
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), 20);
+}
+
+#[test]
+fn substeps_stay_within_the_bounds() {
+    let mut world = stick(0.0, 0.0);
+    world.adaptive_steps.min = 4;
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), 4);
+
+    let mut world = stick(1e9, 1e6);
+    world.adaptive_steps.max = 16;
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), 16);
+
+    // A blown up world takes as many as allowed instead of overflowing
+    let world = stick(30.0, ::std::f64::INFINITY);
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), world.adaptive_steps.max);
+    let world = stick(30.0, ::std::f64::NAN);
+    assert_eq!(world.adaptive_substeps(1.0 / 60.0), world.adaptive_steps.max);
+}
+
+#[test]
+fn update_uses_the_adaptive_substeps() {
+    let mut world = stick(2000.0, 0.0);
+    world.adaptive_steps.enabled = true;
+    let expected = world.adaptive_substeps(1.0 / 60.0);
+    assert!(expected > 1);
+
+    world.update(1.0 / 60.0, 1, false);
+    assert_eq!(world.substeps, expected);
+
+    world.adaptive_steps.enabled = false;
+    world.update(1.0 / 60.0, 3, false);
+    assert_eq!(world.substeps, 3);
+}
+
+#[test]
+fn adaptive_substeps_keep_a_stiff_spring_stable() {
+    let run = |adaptive| {
+        let mut world = stick(5000.0, 0.0);
+        world.verts[1].borrow_mut().position.x = 1.2;
+        world.adaptive_steps.enabled = adaptive;
+        for _ in 0..120 {
+            world.update(1.0 / 60.0, 1, false);
+        }
+        let a = world.verts[0].borrow().position;
+        let b = world.verts[1].borrow().position;
+        (a - b).norm()
+    };
+
+    // A single step overshoots more and more, the substeps keep it near its length
+    let stable = run(true);
+    assert!(stable > 0.5 && stable < 1.5, "stretched to {}", stable);
+    let unstable = run(false);
+    assert!(!(unstable < 1.5), "only stretched to {}", unstable);
+}
@@ -0,0 +1,142 @@
+extern crate nalgebra;
+extern crate spring;
+
+use std::f64;
+
+use nalgebra::Vector2;
+use spring::physics::simulation::{Vertex, World};
+use spring::physics::surface::Surface;
+use spring::physics::validation::Violation;
+
+/// Two separate sticks, 0-1 and 2-3
+fn sticks() -> World {
+    let mut world = World::new();
+    for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 2.0), (1.0, 2.0)] {
+        world.add_vertex(Vertex::new(Vector2::new(x, y)));
+    }
+    world.create_surface(0, 1).unwrap();
+    world.create_surface(2, 3).unwrap();
+    world
+}
+
+fn violations(world: &World) -> Vec<Violation> {
+    world.validate().violations
+}
+
+/// A surface put in directly, the way a broken file or tool would
+fn raw_surface(world: &mut World, index_a: usize, index_b: usize) {
+    world.surfaces.push(Surface {
+        index_a,
+        index_b,
+        target_distance: 1.0,
+        ..Surface::default()
+    });
+}
+
+#[test]
+fn valid_world_has_no_violations() {
+    let world = sticks();
+    assert!(world.validate().is_valid());
+    assert_eq!(world.validate().to_string(), "The world is valid");
+    assert!(World::new().validate().is_valid());
+}
+
+#[test]
+fn finds_surfaces_out_of_range() {
+    let mut world = sticks();
+    raw_surface(&mut world, 1, 4);
+    assert_eq!(
+        violations(&world),
+        vec![Violation::SurfaceOutOfRange {
+            surface: 2,
+            vertex: 4,
+        }]
+    );
+}
+
+#[test]
+fn finds_self_surfaces() {
+    let mut world = sticks();
+    raw_surface(&mut world, 2, 2);
+    assert_eq!(violations(&world), vec![Violation::SelfSurface(2)]);
+}
+
+#[test]
+fn finds_duplicate_surfaces() {
+    let mut world = sticks();
+    // The same vertices the other way round
+    raw_surface(&mut world, 3, 2);
+    assert_eq!(
+        violations(&world),
+        vec![Violation::DuplicateSurface {
+            first: 1,
+            second: 2,
+        }]
+    );
+}
+
+#[test]
+fn finds_bad_target_distances() {
+    for &distance in &[0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let mut world = sticks();
+        world.surfaces[1].target_distance = distance;
+        assert_eq!(violations(&world), vec![Violation::BadTargetDistance(1)]);
+    }
+}
+
+#[test]
+fn finds_bad_masses() {
+    for &mass in &[0.0, -1.0, ::std::f32::NAN, ::std::f32::INFINITY] {
+        let world = sticks();
+        world.verts[2].borrow_mut().mass = mass;
+        assert_eq!(violations(&world), vec![Violation::BadMass(2)]);
+    }
+}
+
+#[test]
+fn finds_non_finite_vertices() {
+    let world = sticks();
+    world.verts[1].borrow_mut().position.y = f64::NAN;
+    world.verts[3].borrow_mut().velocity.x = f64::NEG_INFINITY;
+    assert_eq!(
+        violations(&world),
+        vec![Violation::NonFinite(1), Violation::NonFinite(3)]
+    );
+}
+
+#[test]
+fn finds_mislabeled_bodies() {
+    let world = sticks();
+    world.verts[3].borrow_mut().body = 0;
+    assert_eq!(
+        violations(&world),
+        vec![Violation::WrongBody {
+            vertex: 3,
+            body: 0,
+            expected: 2,
+        }]
+    );
+
+    // Joining the sticks without finding the bodies again
+    let mut world = sticks();
+    raw_surface(&mut world, 1, 2);
+    assert_eq!(violations(&world).len(), 2);
+    world.update_bodies();
+    assert!(world.validate().is_valid());
+}
+
+#[test]
+fn report_lists_every_violation() {
+    let mut world = sticks();
+    world.verts[0].borrow_mut().mass = 0.0;
+    raw_surface(&mut world, 3, 3);
+
+    let report = world.validate();
+    assert!(!report.is_valid());
+    assert_eq!(
+        report.to_string(),
+        "2 problems found\n\
+         vertex 0 has an invalid mass\n\
+         surface 2 connects a vertex to itself"
+    );
+}